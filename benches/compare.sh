@@ -0,0 +1,38 @@
+#!/bin/sh
+# 在两个版本上运行同一个 fib 基准并对比耗时，例如：
+#   benches/compare.sh <before>         # <before> 对比 HEAD
+#   benches/compare.sh <before> <after> # 任意两个版本
+# 每个版本检出到临时的 git worktree，使用当前的 benches/fib.rs，
+# 老版本的 Cargo.toml 没有 [[bench]] 时补上。
+# 求值器每一步都打印调试输出的老版本测不出有意义的结果
+set -eu
+
+if [ $# -lt 1 ] || [ $# -gt 2 ]; then
+    echo "usage: benches/compare.sh <before> [<after>]" >&2
+    exit 2
+fi
+
+repo=$(git rev-parse --show-toplevel)
+before=$1
+after=${2:-HEAD}
+work=$(mktemp -d)
+trap 'git -C "$repo" worktree remove --force "$work/before" 2>/dev/null || true
+      git -C "$repo" worktree remove --force "$work/after" 2>/dev/null || true
+      rm -rf "$work"' EXIT
+
+run() {
+    name=$1
+    rev=$2
+    dir=$work/$name
+    git -C "$repo" worktree add --quiet --detach "$dir" "$rev"
+    mkdir -p "$dir/benches"
+    cp "$repo/benches/fib.rs" "$dir/benches/fib.rs"
+    if ! grep -q '^\[\[bench\]\]' "$dir/Cargo.toml"; then
+        printf '\n[[bench]]\nname = "fib"\nharness = false\n' >>"$dir/Cargo.toml"
+    fi
+    result=$(cd "$dir" && cargo bench --quiet --bench fib 2>&1 >/dev/null | grep '^fib(25)')
+    printf '%-7s %s  %s\n' "$name" "$(git -C "$repo" rev-parse --short "$rev")" "$result"
+}
+
+run before "$before"
+run after "$after"
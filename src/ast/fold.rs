@@ -0,0 +1,198 @@
+use super::{
+    ArrayPattern, BlockStatement, Expression, FunctionLiteral, HashPattern, Identifier, MatchArm,
+    Parameter, Pattern, Program, Statement,
+};
+
+// 按值重写AST。默认实现调用对应的walk_*函数重建所有子节点，
+// 优化、脱糖之类的pass只需要重写关心的fold_*方法
+pub trait Fold {
+    fn fold_program(&mut self, program: Program) -> Program {
+        walk_program(self, program)
+    }
+    fn fold_statement(&mut self, stmt: Statement) -> Statement {
+        walk_statement(self, stmt)
+    }
+    fn fold_block(&mut self, block: BlockStatement) -> BlockStatement {
+        walk_block(self, block)
+    }
+    fn fold_expression(&mut self, expr: Expression) -> Expression {
+        walk_expression(self, expr)
+    }
+    // 包括绑定位置（let、参数、函数名、模式）和引用位置的标识符
+    fn fold_identifier(&mut self, ident: Identifier) -> Identifier {
+        ident
+    }
+    fn fold_pattern(&mut self, pattern: Pattern) -> Pattern {
+        walk_pattern(self, pattern)
+    }
+    fn fold_parameter(&mut self, param: Parameter) -> Parameter {
+        walk_parameter(self, param)
+    }
+}
+
+pub fn walk_program<F: Fold + ?Sized>(folder: &mut F, program: Program) -> Program {
+    Program {
+        statements: program
+            .statements
+            .into_iter()
+            .map(|stmt| folder.fold_statement(stmt))
+            .collect(),
+    }
+}
+
+pub fn walk_statement<F: Fold + ?Sized>(folder: &mut F, stmt: Statement) -> Statement {
+    match stmt {
+        Statement::Let(mut let_stmt) => {
+            *let_stmt.name = folder.fold_identifier(*let_stmt.name);
+            *let_stmt.value = folder.fold_expression(*let_stmt.value);
+            Statement::Let(let_stmt)
+        }
+        Statement::DestructuringLet(mut let_stmt) => {
+            let_stmt.pattern = folder.fold_pattern(let_stmt.pattern);
+            *let_stmt.value = folder.fold_expression(*let_stmt.value);
+            Statement::DestructuringLet(let_stmt)
+        }
+        Statement::Return(mut return_stmt) => {
+            *return_stmt.return_value = folder.fold_expression(*return_stmt.return_value);
+            Statement::Return(return_stmt)
+        }
+        Statement::Expression(mut expr_stmt) => {
+            *expr_stmt.expression = folder.fold_expression(*expr_stmt.expression);
+            Statement::Expression(expr_stmt)
+        }
+        Statement::Block(block) => Statement::Block(folder.fold_block(block)),
+        Statement::FunctionDeclaration(mut decl) => {
+            decl.name = folder.fold_identifier(decl.name);
+            decl.function = walk_function(folder, decl.function);
+            Statement::FunctionDeclaration(decl)
+        }
+    }
+}
+
+pub fn walk_block<F: Fold + ?Sized>(folder: &mut F, block: BlockStatement) -> BlockStatement {
+    BlockStatement {
+        token: block.token,
+        statements: block
+            .statements
+            .into_iter()
+            .map(|stmt| folder.fold_statement(stmt))
+            .collect(),
+    }
+}
+
+pub fn walk_expression<F: Fold + ?Sized>(folder: &mut F, expr: Expression) -> Expression {
+    match expr {
+        Expression::Identifier(ident) => Expression::Identifier(folder.fold_identifier(ident)),
+        Expression::Integer(_) | Expression::String(_) | Expression::Boolean(_) => expr,
+        Expression::Prefix(mut prefix) => {
+            *prefix.right = folder.fold_expression(*prefix.right);
+            Expression::Prefix(prefix)
+        }
+        Expression::Infix(mut infix) => {
+            *infix.left = folder.fold_expression(*infix.left);
+            *infix.right = folder.fold_expression(*infix.right);
+            Expression::Infix(infix)
+        }
+        Expression::If(mut if_expr) => {
+            *if_expr.condition = folder.fold_expression(*if_expr.condition);
+            if_expr.consequence = folder.fold_block(if_expr.consequence);
+            if_expr.alternative = if_expr.alternative.map(|alt| folder.fold_block(alt));
+            Expression::If(if_expr)
+        }
+        Expression::Function(function) => Expression::Function(walk_function(folder, function)),
+        Expression::Call(mut call) => {
+            *call.function = folder.fold_expression(*call.function);
+            call.arguments = fold_expressions(folder, call.arguments);
+            Expression::Call(call)
+        }
+        Expression::Array(mut array) => {
+            array.elements = fold_expressions(folder, array.elements);
+            Expression::Array(array)
+        }
+        Expression::Hash(mut hash) => {
+            hash.pairs = hash
+                .pairs
+                .into_iter()
+                .map(|(key, value)| (folder.fold_expression(key), folder.fold_expression(value)))
+                .collect();
+            Expression::Hash(hash)
+        }
+        Expression::Match(mut match_expr) => {
+            *match_expr.subject = folder.fold_expression(*match_expr.subject);
+            match_expr.arms = match_expr
+                .arms
+                .into_iter()
+                .map(|arm| MatchArm {
+                    pattern: folder.fold_pattern(arm.pattern),
+                    guard: arm.guard.map(|guard| folder.fold_expression(guard)),
+                    body: folder.fold_statement(arm.body),
+                })
+                .collect();
+            Expression::Match(match_expr)
+        }
+        Expression::Spread(mut spread) => {
+            *spread.value = folder.fold_expression(*spread.value);
+            Expression::Spread(spread)
+        }
+    }
+}
+
+pub fn walk_parameter<F: Fold + ?Sized>(folder: &mut F, param: Parameter) -> Parameter {
+    match param {
+        Parameter::Plain(ident) => Parameter::Plain(folder.fold_identifier(ident)),
+        Parameter::Default(mut param) => {
+            param.name = folder.fold_identifier(param.name);
+            *param.default = folder.fold_expression(*param.default);
+            Parameter::Default(param)
+        }
+        Parameter::Rest(mut param) => {
+            param.name = folder.fold_identifier(param.name);
+            Parameter::Rest(param)
+        }
+    }
+}
+
+pub fn walk_pattern<F: Fold + ?Sized>(folder: &mut F, pattern: Pattern) -> Pattern {
+    match pattern {
+        Pattern::Wildcard | Pattern::Integer(_) | Pattern::String(_) | Pattern::Boolean(_) => {
+            pattern
+        }
+        Pattern::Identifier(ident) => Pattern::Identifier(folder.fold_identifier(ident)),
+        Pattern::Array(array) => Pattern::Array(ArrayPattern {
+            token: array.token,
+            elements: array
+                .elements
+                .into_iter()
+                .map(|element| folder.fold_pattern(element))
+                .collect(),
+            rest: array.rest.map(|rest| folder.fold_identifier(rest)),
+        }),
+        Pattern::Hash(hash) => Pattern::Hash(HashPattern {
+            token: hash.token,
+            pairs: hash
+                .pairs
+                .into_iter()
+                .map(|(key, pattern)| (key, folder.fold_pattern(pattern)))
+                .collect(),
+        }),
+    }
+}
+
+fn walk_function<F: Fold + ?Sized>(folder: &mut F, function: FunctionLiteral) -> FunctionLiteral {
+    FunctionLiteral {
+        token: function.token,
+        parameters: function
+            .parameters
+            .into_iter()
+            .map(|param| folder.fold_parameter(param))
+            .collect(),
+        body: folder.fold_block(function.body),
+    }
+}
+
+fn fold_expressions<F: Fold + ?Sized>(folder: &mut F, exprs: Vec<Expression>) -> Vec<Expression> {
+    exprs
+        .into_iter()
+        .map(|expr| folder.fold_expression(expr))
+        .collect()
+}
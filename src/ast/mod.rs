@@ -1,115 +1,112 @@
 use core::fmt;
-use std::any::Any;
 
 use crate::token::token::Token;
 
+pub mod fold;
+pub mod printer;
+pub mod visitor;
+
 // 定义Node trait作为AST节点的基本特征
-pub trait Node: std::fmt::Debug + Any {
+pub trait Node: std::fmt::Debug {
     fn token_literal(&self) -> String;
-    fn as_any(&self) -> &dyn Any;
     fn to_string(&self) -> String {
         format!("{:?}", self)
     }
 }
 
-// Statement trait代表语句节点
-// 任何实现了Statement的必须也实现Node Tarit
-pub trait Statement: Node + std::fmt::Debug {
-    fn statement_node(&self);
-}
-
-// Expression trait代表表达式节点
-pub trait Expression: Node + std::fmt::Debug {
-    fn expression_node(&self);
+// 语句节点
+#[derive(Debug, Clone, PartialEq)]
+pub enum Statement {
+    Let(LetStatement),
+    DestructuringLet(DestructuringLetStatement),
+    Return(ReturnStatement),
+    Expression(ExpressionStatement),
+    Block(BlockStatement),
+    FunctionDeclaration(FunctionDeclaration),
 }
 
-#[derive(Debug)]
-pub enum NodeType {
-    Statement(Box<dyn Statement>),
-    Expression(Box<dyn Expression>),
-}
-
-impl NodeType {
-    pub fn clone_node(&self) -> Self {
+impl Node for Statement {
+    fn token_literal(&self) -> String {
         match self {
-            NodeType::Statement(stmt) => {
-                if let Some(expr_stmt) = stmt.as_any().downcast_ref::<ExpressionStatement>() {
-                    NodeType::Statement(Box::new(ExpressionStatement {
-                        token: expr_stmt.token.clone(),
-                        expression: Box::new(expr_stmt.expression.clone_node()),
-                    }))
-                } else if let Some(let_stmt) = stmt.as_any().downcast_ref::<LetStatement>() {
-                    NodeType::Statement(Box::new(LetStatement {
-                        token: let_stmt.token.clone(),
-                        name: let_stmt.name.clone(),
-                        value: Box::new(let_stmt.value.clone_node()),
-                    }))
-                } else if let Some(ret_stmt) = stmt.as_any().downcast_ref::<ReturnStatement>() {
-                    NodeType::Statement(Box::new(ReturnStatement {
-                        token: ret_stmt.token.clone(),
-                        return_value: Box::new(ret_stmt.return_value.clone_node()),
-                    }))
-                } else if let Some(block_stmt) = stmt.as_any().downcast_ref::<BlockStatement>() {
-                    let cloned_statements = block_stmt
-                        .statements
-                        .iter()
-                        .map(|s| s.clone_node())
-                        .collect();
-                    NodeType::Statement(Box::new(BlockStatement {
-                        token: block_stmt.token.clone(),
-                        statements: cloned_statements,
-                    }))
-                } else {
-                    panic!("Unknown statement type in clone_node")
-                }
-            }
-            NodeType::Expression(expr) => {
-                if let Some(int_lit) = expr.as_any().downcast_ref::<IntegerLiteral>() {
-                    NodeType::Expression(Box::new(IntegerLiteral {
-                        token: int_lit.token.clone(),
-                        value: int_lit.value,
-                    }))
-                } else if let Some(bool_expr) = expr.as_any().downcast_ref::<Boolean>() {
-                    NodeType::Expression(Box::new(Boolean {
-                        token: bool_expr.token.clone(),
-                        value: bool_expr.value,
-                    }))
-                } else if let Some(ident) = expr.as_any().downcast_ref::<Identifier>() {
-                    NodeType::Expression(Box::new(Identifier {
-                        token: ident.token.clone(),
-                        value: ident.value.clone(),
-                    }))
-                }
-                // 为其他表达式类型添加类似的匹配分支
-                else {
-                    panic!("Unknown expression type in clone_node")
-                }
-            }
+            Statement::Let(stmt) => stmt.token_literal(),
+            Statement::DestructuringLet(stmt) => stmt.token_literal(),
+            Statement::Return(stmt) => stmt.token_literal(),
+            Statement::Expression(stmt) => stmt.token_literal(),
+            Statement::Block(stmt) => stmt.token_literal(),
+            Statement::FunctionDeclaration(stmt) => stmt.token_literal(),
+        }
+    }
+    fn to_string(&self) -> String {
+        match self {
+            Statement::Let(stmt) => stmt.to_string(),
+            Statement::DestructuringLet(stmt) => stmt.to_string(),
+            Statement::Return(stmt) => stmt.to_string(),
+            Statement::Expression(stmt) => stmt.to_string(),
+            Statement::Block(stmt) => stmt.to_string(),
+            Statement::FunctionDeclaration(stmt) => stmt.to_string(),
         }
     }
 }
 
-impl Node for NodeType {
+// 表达式节点
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expression {
+    Identifier(Identifier),
+    Integer(IntegerLiteral),
+    String(StringLiteral),
+    Boolean(Boolean),
+    Prefix(PrefixExpression),
+    Infix(InfixExpression),
+    If(IfExpression),
+    Function(FunctionLiteral),
+    Call(CallExpression),
+    Array(ArrayLiteral),
+    Hash(HashLiteral),
+    Match(MatchExpression),
+    // 只出现在调用参数中
+    Spread(SpreadExpression),
+}
+
+impl Node for Expression {
     fn token_literal(&self) -> String {
         match self {
-            NodeType::Statement(stmt) => stmt.token_literal(),
-            NodeType::Expression(expr) => expr.token_literal(),
+            Expression::Identifier(expr) => expr.token_literal(),
+            Expression::Integer(expr) => expr.token_literal(),
+            Expression::String(expr) => expr.token_literal(),
+            Expression::Boolean(expr) => expr.token_literal(),
+            Expression::Prefix(expr) => expr.token_literal(),
+            Expression::Infix(expr) => expr.token_literal(),
+            Expression::If(expr) => expr.token_literal(),
+            Expression::Function(expr) => expr.token_literal(),
+            Expression::Call(expr) => expr.token_literal(),
+            Expression::Array(expr) => expr.token_literal(),
+            Expression::Hash(expr) => expr.token_literal(),
+            Expression::Match(expr) => expr.token_literal(),
+            Expression::Spread(expr) => expr.token_literal(),
         }
     }
-    fn as_any(&self) -> &dyn Any {
-        self
-    }
     fn to_string(&self) -> String {
         match self {
-            NodeType::Statement(stmt) => stmt.to_string(),
-            NodeType::Expression(expr) => expr.to_string(),
+            Expression::Identifier(expr) => expr.to_string(),
+            Expression::Integer(expr) => Node::to_string(expr),
+            Expression::String(expr) => expr.to_string(),
+            Expression::Boolean(expr) => expr.to_string(),
+            Expression::Prefix(expr) => expr.to_string(),
+            Expression::Infix(expr) => expr.to_string(),
+            Expression::If(expr) => expr.to_string(),
+            Expression::Function(expr) => expr.to_string(),
+            Expression::Call(expr) => expr.to_string(),
+            Expression::Array(expr) => expr.to_string(),
+            Expression::Hash(expr) => expr.to_string(),
+            Expression::Match(expr) => expr.to_string(),
+            Expression::Spread(expr) => expr.to_string(),
         }
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Program {
-    pub statements: Vec<NodeType>,
+    pub statements: Vec<Statement>,
 }
 
 impl Program {
@@ -125,9 +122,10 @@ impl Program {
     //         .collect()
     // }
 }
-impl AsRef<dyn Node> for Program {
-    fn as_ref(&self) -> &(dyn Node + 'static) {
-        self as &dyn Node
+
+impl Default for Program {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
@@ -139,50 +137,37 @@ impl Node for Program {
             String::new()
         }
     }
-    fn as_any(&self) -> &dyn Any {
-        self
-    }
     fn to_string(&self) -> String {
         self.statements.iter().map(|i| i.to_string()).collect()
     }
 }
 
 // LetStatement结构体，对应let语句
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct LetStatement {
     pub token: Token, // token.LET词法单元
     pub name: Box<Identifier>,
-    pub value: Box<NodeType>,
+    pub value: Box<Expression>,
 }
 
 impl Node for LetStatement {
     fn token_literal(&self) -> String {
         self.token.literal.clone()
     }
-    fn as_any(&self) -> &dyn Any {
-        self
-    }
     fn to_string(&self) -> String {
         let mut out = String::new();
         out.push_str(&self.token_literal());
         out.push(' ');
         out.push_str(&self.name.value);
         out.push_str(" = ");
-        match &*self.value {
-            NodeType::Expression(e) => out.push_str(&e.to_string()),
-            NodeType::Statement(s) => out.push_str(&s.to_string()),
-        }
+        out.push_str(&self.value.to_string());
         out.push(';');
         out
     }
 }
 
-impl Statement for LetStatement {
-    fn statement_node(&self) {}
-}
-
 // Identifier结构体，表示标识符
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Identifier {
     pub token: Token, // token.IDENT词法单元
     pub value: String,
@@ -192,75 +177,48 @@ impl Node for Identifier {
     fn token_literal(&self) -> String {
         self.token.literal.clone()
     }
-    fn as_any(&self) -> &dyn Any {
-        self
-    }
     fn to_string(&self) -> String {
         self.value.clone()
     }
 }
 
-impl Expression for Identifier {
-    fn expression_node(&self) {}
-}
-
 /// return <表达式>;
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct ReturnStatement {
     pub token: Token,
-    pub return_value: Box<NodeType>, // NodeType::Expression
+    pub return_value: Box<Expression>,
 }
 
 impl Node for ReturnStatement {
     fn token_literal(&self) -> String {
         self.token.literal.clone()
     }
-    fn as_any(&self) -> &dyn Any {
-        self
-    }
     fn to_string(&self) -> String {
         let mut out = String::new();
         out.push_str(&self.token_literal());
         out.push(' ');
-        match &*self.return_value {
-            NodeType::Expression(e) => out.push_str(&e.to_string()),
-            NodeType::Statement(s) => out.push_str(&s.to_string()),
-        }
+        out.push_str(&self.return_value.to_string());
         out.push(';');
         out
     }
 }
 
-impl Statement for ReturnStatement {
-    fn statement_node(&self) {}
-}
-
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct ExpressionStatement {
     pub token: Token,
-    pub expression: Box<NodeType>, // NodeType::Statement
+    pub expression: Box<Expression>,
 }
 
 impl Node for ExpressionStatement {
-    fn as_any(&self) -> &dyn Any {
-        self
-    }
     fn token_literal(&self) -> String {
         self.token.literal.to_string()
     }
     fn to_string(&self) -> String {
-        match &*self.expression {
-            NodeType::Expression(e) => e.to_string(),
-            NodeType::Statement(s) => s.to_string(),
-        }
+        self.expression.to_string()
     }
 }
 
-impl Statement for ExpressionStatement {
-    fn statement_node(&self) {}
-}
-
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct IntegerLiteral {
     pub token: Token,
     pub value: i64,
@@ -270,34 +228,24 @@ impl Node for IntegerLiteral {
     fn token_literal(&self) -> String {
         self.token.literal.clone()
     }
-    fn as_any(&self) -> &dyn Any {
-        self
-    }
     fn to_string(&self) -> String {
         self.token.literal.to_string() // not sure
     }
 }
 
-impl Expression for IntegerLiteral {
-    fn expression_node(&self) {}
-}
-
 impl fmt::Display for IntegerLiteral {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "{}", self.token.literal)
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct PrefixExpression {
     pub token: Token, // 前缀词法单元，如!
     pub operator: String,
-    pub right: Box<NodeType>,
+    pub right: Box<Expression>,
 }
 impl Node for PrefixExpression {
-    fn as_any(&self) -> &dyn Any {
-        self
-    }
     fn token_literal(&self) -> String {
         self.token.literal.to_string()
     }
@@ -305,67 +253,45 @@ impl Node for PrefixExpression {
         let mut out = String::new();
         out.push('(');
         out.push_str(&self.operator);
-        match &*self.right {
-            NodeType::Expression(e) => out.push_str(&e.to_string()),
-            NodeType::Statement(s) => out.push_str(&s.to_string()),
-        }
+        out.push_str(&self.right.to_string());
         out.push(')');
         out
     }
 }
 
-impl Expression for PrefixExpression {
-    fn expression_node(&self) {}
-}
-
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct InfixExpression {
     pub token: Token,
-    pub left: Box<NodeType>,
+    pub left: Box<Expression>,
     pub operator: String,
-    pub right: Box<NodeType>,
+    pub right: Box<Expression>,
 }
 
 impl Node for InfixExpression {
-    fn as_any(&self) -> &dyn Any {
-        self
-    }
     fn token_literal(&self) -> String {
         self.token.literal.to_string()
     }
     fn to_string(&self) -> String {
         let mut out = String::new();
         out.push('(');
-        match &*self.left {
-            NodeType::Expression(e) => out.push_str(&e.to_string()),
-            NodeType::Statement(s) => out.push_str(&s.to_string()),
-        };
+        out.push_str(&self.left.to_string());
         out.push(' ');
         out.push_str(&self.operator);
         out.push(' ');
-        match &*self.right {
-            NodeType::Expression(e) => out.push_str(&e.to_string()),
-            NodeType::Statement(s) => out.push_str(&s.to_string()),
-        };
+        out.push_str(&self.right.to_string());
         out.push(')');
 
         out
     }
 }
-impl Expression for InfixExpression {
-    fn expression_node(&self) {}
-}
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Boolean {
     pub token: Token,
     pub value: bool,
 }
 
 impl Node for Boolean {
-    fn as_any(&self) -> &dyn Any {
-        self
-    }
     fn to_string(&self) -> String {
         self.token.literal.to_string()
     }
@@ -373,17 +299,14 @@ impl Node for Boolean {
         self.token.literal.to_string()
     }
 }
-impl Expression for Boolean {
-    fn expression_node(&self) {}
-}
 
 // if 表达式
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct IfExpression {
-    pub token: Token,                       // 'if'词法单元
-    pub condition: Box<NodeType>,           // 条件表达式
-    pub consequence: Box<NodeType>,         // 如果条件为真时执行的语句块
-    pub alternative: Option<Box<NodeType>>, // 可选的else语句块
+    pub token: Token,                        // 'if'词法单元
+    pub condition: Box<Expression>,          // 条件表达式
+    pub consequence: BlockStatement,         // 如果条件为真时执行的语句块
+    pub alternative: Option<BlockStatement>, // 可选的else语句块
 }
 
 impl Node for IfExpression {
@@ -391,16 +314,12 @@ impl Node for IfExpression {
         self.token.literal.clone()
     }
 
-    fn as_any(&self) -> &dyn Any {
-        self
-    }
-
     fn to_string(&self) -> String {
         let mut out = String::new();
 
         out.push_str("if");
         out.push_str(&self.condition.to_string());
-        out.push_str(" ");
+        out.push(' ');
         out.push_str(&self.consequence.to_string());
 
         // 如果有else分支，则添加
@@ -413,15 +332,11 @@ impl Node for IfExpression {
     }
 }
 
-impl Expression for IfExpression {
-    fn expression_node(&self) {}
-}
-
 // 定义BlockStatement结构体
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct BlockStatement {
     pub token: Token, // { 词法单元
-    pub statements: Vec<NodeType>,
+    pub statements: Vec<Statement>,
 }
 
 impl Node for BlockStatement {
@@ -429,10 +344,6 @@ impl Node for BlockStatement {
         self.token.literal.clone()
     }
 
-    fn as_any(&self) -> &dyn Any {
-        self
-    }
-
     fn to_string(&self) -> String {
         let mut out = String::new();
 
@@ -444,21 +355,15 @@ impl Node for BlockStatement {
     }
 }
 
-impl Statement for BlockStatement {
-    fn statement_node(&self) {}
-}
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct FunctionLiteral {
     pub token: Token,
     // pub parameters: Vec<Identifier>,
-    pub parameters: Vec<NodeType>,
-    pub body: Box<NodeType>, // BlockStatement
+    pub parameters: Vec<Parameter>,
+    pub body: BlockStatement,
 }
 
 impl Node for FunctionLiteral {
-    fn as_any(&self) -> &dyn Any {
-        self
-    }
     fn to_string(&self) -> String {
         let mut out = String::new();
         // let mut params = String::new();
@@ -480,30 +385,136 @@ impl Node for FunctionLiteral {
     }
 }
 
-impl Expression for FunctionLiteral {
-    fn expression_node(&self) {}
+// 函数参数，rest参数只能是最后一个
+#[derive(Debug, Clone, PartialEq)]
+pub enum Parameter {
+    Plain(Identifier),
+    Default(DefaultParameter),
+    Rest(RestParameter),
+}
+
+impl Parameter {
+    pub fn name(&self) -> &Identifier {
+        match self {
+            Parameter::Plain(ident) => ident,
+            Parameter::Default(param) => &param.name,
+            Parameter::Rest(param) => &param.name,
+        }
+    }
+}
+
+impl Node for Parameter {
+    fn to_string(&self) -> String {
+        match self {
+            Parameter::Plain(ident) => ident.to_string(),
+            Parameter::Default(param) => param.to_string(),
+            Parameter::Rest(param) => param.to_string(),
+        }
+    }
+    fn token_literal(&self) -> String {
+        match self {
+            Parameter::Plain(ident) => ident.token_literal(),
+            Parameter::Default(param) => param.token_literal(),
+            Parameter::Rest(param) => param.token_literal(),
+        }
+    }
+}
+
+// 带默认值的参数：<标识符> = <表达式>，默认值在调用时于函数环境中求值
+#[derive(Debug, Clone, PartialEq)]
+pub struct DefaultParameter {
+    pub token: Token, // '='词法单元
+    pub name: Identifier,
+    pub default: Box<Expression>,
+}
+
+impl Node for DefaultParameter {
+    fn to_string(&self) -> String {
+        format!("{} = {}", self.name.value, self.default.to_string())
+    }
+    fn token_literal(&self) -> String {
+        self.token.literal.to_string()
+    }
+}
+
+// rest参数：...<标识符>，把剩余的参数收集成数组
+#[derive(Debug, Clone, PartialEq)]
+pub struct RestParameter {
+    pub token: Token, // '...'词法单元
+    pub name: Identifier,
+}
+
+impl Node for RestParameter {
+    fn to_string(&self) -> String {
+        format!("...{}", self.name.value)
+    }
+    fn token_literal(&self) -> String {
+        self.token.literal.to_string()
+    }
+}
+
+// ...<表达式>：在调用参数中展开数组
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpreadExpression {
+    pub token: Token, // '...'词法单元
+    pub value: Box<Expression>,
+}
+
+impl Node for SpreadExpression {
+    fn to_string(&self) -> String {
+        format!("...{}", self.value.to_string())
+    }
+    fn token_literal(&self) -> String {
+        self.token.literal.to_string()
+    }
+}
+
+// fn <名字>(<参数>) <块语句>，求值块时会先绑定名字（提升），因此支持递归和相互递归
+#[derive(Debug, Clone, PartialEq)]
+pub struct FunctionDeclaration {
+    pub token: Token, // 'fn'词法单元
+    pub name: Identifier,
+    pub function: FunctionLiteral,
 }
 
-#[derive(Debug)]
+impl Node for FunctionDeclaration {
+    fn to_string(&self) -> String {
+        let params: Vec<String> = self
+            .function
+            .parameters
+            .iter()
+            .map(|p| p.to_string())
+            .collect();
+        format!(
+            "{} {}({}) {}",
+            self.token_literal(),
+            self.name.value,
+            params.join(", "),
+            self.function.body.to_string()
+        )
+    }
+    fn token_literal(&self) -> String {
+        self.token.literal.to_string()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct CallExpression {
     pub token: Token,
-    pub function: Box<NodeType>,
-    pub arguments: Vec<NodeType>,
+    pub function: Box<Expression>,
+    pub arguments: Vec<Expression>,
 }
 
 impl Node for CallExpression {
-    fn as_any(&self) -> &dyn Any {
-        self
-    }
     fn to_string(&self) -> String {
         let mut out = String::new();
 
         let args: Vec<String> = self.arguments.iter().map(|a| a.to_string()).collect();
 
         out.push_str(&self.function.to_string());
-        out.push_str("(");
+        out.push('(');
         out.push_str(&args.join(", "));
-        out.push_str(")");
+        out.push(')');
 
         out
     }
@@ -512,6 +523,191 @@ impl Node for CallExpression {
     }
 }
 
-impl Expression for CallExpression {
-    fn expression_node(&self) {}
+#[derive(Debug, Clone, PartialEq)]
+pub struct StringLiteral {
+    pub token: Token,
+    pub value: String,
+}
+
+impl Node for StringLiteral {
+    fn to_string(&self) -> String {
+        format!("\"{}\"", self.value)
+    }
+    fn token_literal(&self) -> String {
+        self.token.literal.to_string()
+    }
+}
+
+// [<表达式>, <表达式>, ...]
+#[derive(Debug, Clone, PartialEq)]
+pub struct ArrayLiteral {
+    pub token: Token, // '['词法单元
+    pub elements: Vec<Expression>,
+}
+
+impl Node for ArrayLiteral {
+    fn to_string(&self) -> String {
+        let elements: Vec<String> = self.elements.iter().map(|e| e.to_string()).collect();
+        format!("[{}]", elements.join(", "))
+    }
+    fn token_literal(&self) -> String {
+        self.token.literal.to_string()
+    }
+}
+
+// {<表达式>: <表达式>, ...}，按源码顺序保存键值对
+#[derive(Debug, Clone, PartialEq)]
+pub struct HashLiteral {
+    pub token: Token, // '{'词法单元
+    pub pairs: Vec<(Expression, Expression)>,
+}
+
+impl Node for HashLiteral {
+    fn to_string(&self) -> String {
+        let pairs: Vec<String> = self
+            .pairs
+            .iter()
+            .map(|(k, v)| format!("{}: {}", k.to_string(), v.to_string()))
+            .collect();
+        format!("{{{}}}", pairs.join(", "))
+    }
+    fn token_literal(&self) -> String {
+        self.token.literal.to_string()
+    }
+}
+
+// 模式：用于 let 解构和 match 分支，模式之间可以嵌套
+#[derive(Debug, Clone, PartialEq)]
+pub enum Pattern {
+    Wildcard,               // _
+    Integer(i64),           // 字面量模式
+    String(String),         // 字面量模式
+    Boolean(bool),          // 字面量模式
+    Identifier(Identifier), // 绑定
+    Array(ArrayPattern),
+    Hash(HashPattern),
+}
+
+impl fmt::Display for Pattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Pattern::Wildcard => write!(f, "_"),
+            Pattern::Integer(value) => write!(f, "{}", value),
+            Pattern::String(value) => write!(f, "\"{}\"", value),
+            Pattern::Boolean(value) => write!(f, "{}", value),
+            Pattern::Identifier(ident) => write!(f, "{}", ident.value),
+            Pattern::Array(array) => write!(f, "{}", array),
+            Pattern::Hash(hash) => write!(f, "{}", hash),
+        }
+    }
+}
+
+// [a, b, ...rest]
+#[derive(Debug, Clone, PartialEq)]
+pub struct ArrayPattern {
+    pub token: Token, // '['词法单元
+    pub elements: Vec<Pattern>,
+    pub rest: Option<Identifier>, // 只能出现在最后
+}
+
+impl fmt::Display for ArrayPattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut parts: Vec<String> = self.elements.iter().map(|e| e.to_string()).collect();
+        if let Some(rest) = &self.rest {
+            parts.push(format!("...{}", rest.value));
+        }
+        write!(f, "[{}]", parts.join(", "))
+    }
+}
+
+// {name, age: years}，键是字段名，值是绑定该字段的模式
+#[derive(Debug, Clone, PartialEq)]
+pub struct HashPattern {
+    pub token: Token, // '{'词法单元
+    pub pairs: Vec<(String, Pattern)>,
+}
+
+impl fmt::Display for HashPattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let pairs: Vec<String> = self
+            .pairs
+            .iter()
+            .map(|(key, pattern)| match pattern {
+                // 简写形式 {name}
+                Pattern::Identifier(ident) if &ident.value == key => key.clone(),
+                // 不是合法标识符的键需要写成字符串形式
+                _ if key.is_empty()
+                    || !key.chars().all(|c| c.is_ascii_alphabetic() || c == '_') =>
+                {
+                    format!("\"{}\": {}", key, pattern)
+                }
+                _ => format!("{}: {}", key, pattern),
+            })
+            .collect();
+        write!(f, "{{{}}}", pairs.join(", "))
+    }
+}
+
+// let <模式> = <表达式>;
+#[derive(Debug, Clone, PartialEq)]
+pub struct DestructuringLetStatement {
+    pub token: Token, // token.LET词法单元
+    pub pattern: Pattern,
+    pub value: Box<Expression>,
+}
+
+impl Node for DestructuringLetStatement {
+    fn token_literal(&self) -> String {
+        self.token.literal.clone()
+    }
+    fn to_string(&self) -> String {
+        format!(
+            "{} {} = {};",
+            self.token_literal(),
+            self.pattern,
+            self.value.to_string()
+        )
+    }
+}
+
+// match (<表达式>) { <模式> [if <守卫>] => <表达式或块语句>, ... }
+#[derive(Debug, Clone, PartialEq)]
+pub struct MatchExpression {
+    pub token: Token, // 'match'词法单元
+    pub subject: Box<Expression>,
+    pub arms: Vec<MatchArm>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MatchArm {
+    pub pattern: Pattern,
+    pub guard: Option<Expression>,
+    pub body: Statement, // ExpressionStatement或BlockStatement
+}
+
+impl Node for MatchExpression {
+    fn token_literal(&self) -> String {
+        self.token.literal.clone()
+    }
+    fn to_string(&self) -> String {
+        let arms: Vec<String> = self
+            .arms
+            .iter()
+            .map(|arm| {
+                let mut out = arm.pattern.to_string();
+                if let Some(guard) = &arm.guard {
+                    out.push_str(" if ");
+                    out.push_str(&guard.to_string());
+                }
+                out.push_str(" => ");
+                out.push_str(&arm.body.to_string());
+                out
+            })
+            .collect();
+        format!(
+            "match ({}) {{ {} }}",
+            self.subject.to_string(),
+            arms.join(", ")
+        )
+    }
 }
@@ -0,0 +1,482 @@
+use crate::parser::comments::{Comment, Comments};
+use crate::parser::parser::{Precedence, token_precedence};
+
+use super::{
+    BlockStatement, Expression, FunctionLiteral, MatchExpression, Parameter, Program, Statement,
+};
+
+const INDENT: &str = "    ";
+
+pub const DEFAULT_WIDTH: usize = 80;
+
+// 把AST打印成规范的Monkey源码：每条语句一行，块内缩进四个空格，
+// 只在优先级需要时加括号，输出重新解析后得到相同的AST
+pub fn print_program(program: &Program) -> String {
+    Printer::new().print_program(program)
+}
+
+pub fn print_statement(stmt: &Statement) -> String {
+    let mut printer = Printer::new();
+    printer.statement(stmt);
+    printer.out
+}
+
+pub fn print_expression(expr: &Expression) -> String {
+    let mut printer = Printer::new();
+    printer.expression(expr);
+    printer.out
+}
+
+// 超过行宽的参数列表、数组和哈希字面量拆成每个元素一行。
+// 设置了注释时按解析器的顺序给语句、块和列表编号，把注释放回原来的位置
+pub struct Printer<'a> {
+    out: String,
+    level: usize,        // 当前缩进层级
+    start_column: usize, // out的第一行从哪一列开始
+    width: usize,
+    comments: Option<&'a Comments>,
+    statements: usize, // 下一条语句的编号
+    blocks: usize,     // 下一个块的编号
+    lists: usize,      // 下一个列表的编号，match的分支也算一个列表
+}
+
+impl<'a> Printer<'a> {
+    pub fn new() -> Self {
+        Printer {
+            out: String::new(),
+            level: 0,
+            start_column: 0,
+            width: DEFAULT_WIDTH,
+            comments: None,
+            statements: 0,
+            blocks: 0,
+            lists: 0,
+        }
+    }
+
+    pub fn set_width(&mut self, width: usize) {
+        self.width = width;
+    }
+
+    pub fn set_comments(&mut self, comments: &'a Comments) {
+        self.comments = Some(comments);
+    }
+
+    pub fn print_program(mut self, program: &Program) -> String {
+        self.statements(&program.statements, false);
+        if let Some(comments) = self.comments {
+            self.comment_lines(&comments.end, !program.statements.is_empty());
+        }
+        self.out
+    }
+
+    // 在当前位置单独渲染一个节点，用于决定分隔符，编号照常前移
+    fn render(&mut self, f: impl FnOnce(&mut Printer<'a>)) -> String {
+        let mut printer = Printer {
+            out: String::new(),
+            level: self.level,
+            start_column: self.column(),
+            width: self.width,
+            comments: self.comments,
+            statements: self.statements,
+            blocks: self.blocks,
+            lists: self.lists,
+        };
+        f(&mut printer);
+        self.statements = printer.statements;
+        self.blocks = printer.blocks;
+        self.lists = printer.lists;
+        printer.out
+    }
+
+    // 试渲染，只用来测量长度，不移动编号
+    fn measure(&mut self, f: impl FnOnce(&mut Printer<'a>)) -> String {
+        let counters = (self.statements, self.blocks, self.lists);
+        let out = self.render(f);
+        (self.statements, self.blocks, self.lists) = counters;
+        out
+    }
+
+    fn column(&self) -> usize {
+        match self.out.rfind('\n') {
+            Some(i) => self.out[i + 1..].chars().count(),
+            None => self.start_column + self.out.chars().count(),
+        }
+    }
+
+    fn newline(&mut self) {
+        self.out.push('\n');
+        for _ in 0..self.level {
+            self.out.push_str(INDENT);
+        }
+    }
+
+    // 单独成行的注释，after_content表示前面已经有内容，需要先换行
+    fn comment_lines(&mut self, comments: &[Comment], after_content: bool) {
+        for (i, comment) in comments.iter().enumerate() {
+            if after_content || i > 0 {
+                if comment.blank_line_before {
+                    self.out.push('\n');
+                }
+                self.newline();
+            }
+            self.out.push_str(&comment.text);
+        }
+    }
+
+    // block_value表示最后一条表达式语句是块的值，省略它的分号
+    fn statements(&mut self, statements: &[Statement], block_value: bool) {
+        // 先渲染所有语句，决定分号时要看下一条语句的开头
+        let mut rendered = Vec::with_capacity(statements.len());
+        for stmt in statements {
+            let index = self.statements;
+            self.statements += 1;
+            rendered.push((index, self.render(|p| p.statement(stmt))));
+        }
+
+        for (i, stmt) in statements.iter().enumerate() {
+            let (index, text) = &rendered[i];
+            let leading = self.comments.and_then(|c| c.leading.get(index));
+            if let Some(leading) = leading {
+                self.comment_lines(leading, i > 0);
+            }
+            if i > 0 || leading.is_some() {
+                if self
+                    .comments
+                    .is_some_and(|c| c.blank_line_before.contains(index))
+                {
+                    self.out.push('\n');
+                }
+                self.newline();
+            }
+            self.out.push_str(text);
+
+            let last = i + 1 == statements.len();
+            let terminated = match stmt {
+                Statement::Let(_) | Statement::DestructuringLet(_) | Statement::Return(_) => true,
+                Statement::Block(_) | Statement::FunctionDeclaration(_) => false,
+                // if和match以}结尾，后面没有分号时下一条语句开头的(或-会被当成调用或减法
+                Statement::Expression(expr_stmt) => {
+                    let block_like = matches!(
+                        *expr_stmt.expression,
+                        Expression::If(_) | Expression::Match(_)
+                    );
+                    if last {
+                        !block_value && !block_like
+                    } else {
+                        !block_like || rendered[i + 1].1.starts_with(['(', '-'])
+                    }
+                }
+            };
+            if terminated {
+                self.out.push(';');
+            }
+            if let Some(trailing) = self.comments.and_then(|c| c.trailing.get(index)) {
+                self.out.push(' ');
+                self.out.push_str(trailing);
+            }
+        }
+    }
+
+    // 语句本身，不含结尾的分号
+    fn statement(&mut self, stmt: &Statement) {
+        match stmt {
+            Statement::Let(let_stmt) => {
+                self.out.push_str("let ");
+                self.out.push_str(&let_stmt.name.value);
+                self.out.push_str(" = ");
+                self.expression(&let_stmt.value);
+            }
+            Statement::DestructuringLet(let_stmt) => {
+                self.out.push_str(&format!("let {} = ", let_stmt.pattern));
+                self.expression(&let_stmt.value);
+            }
+            Statement::Return(return_stmt) => {
+                self.out.push_str("return ");
+                self.expression(&return_stmt.return_value);
+            }
+            Statement::Expression(expr_stmt) => self.expression(&expr_stmt.expression),
+            Statement::Block(block) => self.block(block),
+            Statement::FunctionDeclaration(decl) => {
+                self.out.push_str("fn ");
+                self.out.push_str(&decl.name.value);
+                self.function(&decl.function);
+            }
+        }
+    }
+
+    fn block(&mut self, block: &BlockStatement) {
+        let index = self.blocks;
+        self.blocks += 1;
+        let end = self.comments.and_then(|c| c.block_end.get(&index));
+
+        if block.statements.is_empty() && end.is_none() {
+            self.out.push_str("{}");
+            return;
+        }
+        self.out.push('{');
+        self.level += 1;
+        self.newline();
+        self.statements(&block.statements, true);
+        if let Some(end) = end {
+            self.comment_lines(end, !block.statements.is_empty());
+        }
+        self.level -= 1;
+        self.newline();
+        self.out.push('}');
+    }
+
+    // (<参数>) <块语句>，fn关键字和函数名由调用方输出
+    fn function(&mut self, function: &FunctionLiteral) {
+        let params = &function.parameters;
+        self.items("(", params.len(), ")", |p, i| p.parameter(&params[i]));
+        self.out.push(' ');
+        self.block(&function.body);
+    }
+
+    fn parameter(&mut self, param: &Parameter) {
+        match param {
+            Parameter::Plain(ident) => self.out.push_str(&ident.value),
+            Parameter::Default(param) => {
+                self.out.push_str(&param.name.value);
+                self.out.push_str(" = ");
+                self.expression(&param.default);
+            }
+            Parameter::Rest(param) => {
+                self.out.push_str("...");
+                self.out.push_str(&param.name.value);
+            }
+        }
+    }
+
+    fn list(&mut self, open: &str, exprs: &[Expression], close: &str) {
+        self.items(open, exprs.len(), close, |p, i| p.expression(&exprs[i]));
+    }
+
+    // 放得下时写在一行，否则每个元素单独一行，缩进一层。
+    // 元素带注释时注释要单独成行或放在行尾，也是每个元素一行
+    fn items(
+        &mut self,
+        open: &str,
+        len: usize,
+        close: &str,
+        mut item: impl FnMut(&mut Printer<'a>, usize),
+    ) {
+        let list = self.lists;
+        self.lists += 1;
+        let end = self.list_end(list);
+        let commented = end.is_some()
+            || (0..len).any(|i| {
+                self.item_leading(list, i).is_some() || self.item_trailing(list, i).is_some()
+            });
+
+        self.out.push_str(open);
+        // width为usize::MAX时是在测量外层的单行长度，不用再测量
+        let fits = !commented
+            && (len == 0 || self.width == usize::MAX || {
+                let column = self.column();
+                let flat = self.measure(|p| {
+                    p.width = usize::MAX;
+                    for i in 0..len {
+                        if i > 0 {
+                            p.out.push_str(", ");
+                        }
+                        item(p, i);
+                    }
+                });
+                // 元素里有块（比如函数字面量）时只看第一行
+                let first_line = match flat.split_once('\n') {
+                    Some((line, _)) => line.chars().count(),
+                    None => flat.chars().count() + close.len(),
+                };
+                column + first_line <= self.width
+            });
+
+        if fits {
+            for i in 0..len {
+                if i > 0 {
+                    self.out.push_str(", ");
+                }
+                item(self, i);
+            }
+        } else {
+            self.level += 1;
+            for i in 0..len {
+                self.newline();
+                if let Some(leading) = self.item_leading(list, i) {
+                    self.comment_lines(leading, false);
+                    self.newline();
+                }
+                item(self, i);
+                if i + 1 < len {
+                    self.out.push(',');
+                }
+                if let Some(trailing) = self.item_trailing(list, i) {
+                    self.out.push(' ');
+                    self.out.push_str(trailing);
+                }
+            }
+            if let Some(end) = end {
+                self.newline();
+                self.comment_lines(end, false);
+            }
+            self.level -= 1;
+            self.newline();
+        }
+        self.out.push_str(close);
+    }
+
+    // 第list个列表中第i个元素前面单独成行的注释
+    fn item_leading(&self, list: usize, i: usize) -> Option<&'a Vec<Comment>> {
+        self.comments.and_then(|c| c.item_leading.get(&(list, i)))
+    }
+
+    // 第list个列表中第i个元素行尾的注释
+    fn item_trailing(&self, list: usize, i: usize) -> Option<&'a String> {
+        self.comments.and_then(|c| c.item_trailing.get(&(list, i)))
+    }
+
+    // 第list个列表最后一个元素之后的注释
+    fn list_end(&self, list: usize) -> Option<&'a Vec<Comment>> {
+        self.comments.and_then(|c| c.list_end.get(&list))
+    }
+
+    fn expression(&mut self, expr: &Expression) {
+        match expr {
+            Expression::Identifier(ident) => self.out.push_str(&ident.value),
+            Expression::Integer(int) => self.out.push_str(&int.value.to_string()),
+            Expression::String(string) => self.out.push_str(&format!("\"{}\"", string.value)),
+            Expression::Boolean(boolean) => self.out.push_str(&boolean.value.to_string()),
+            Expression::Prefix(prefix) => {
+                self.out.push_str(&prefix.operator);
+                // 前缀运算符的操作数按PREFIX优先级解析
+                self.operand(
+                    &prefix.right,
+                    precedence(&prefix.right) < Precedence::PREFIX,
+                );
+            }
+            Expression::Infix(infix) => {
+                // 中缀运算符左结合：左侧优先级更低时加括号，右侧优先级不高于当前运算符时加括号
+                let current = token_precedence(infix.token.token_type);
+                self.operand(&infix.left, precedence(&infix.left) < current);
+                self.out.push_str(&format!(" {} ", infix.operator));
+                self.operand(&infix.right, precedence(&infix.right) <= current);
+            }
+            Expression::If(if_expr) => {
+                self.out.push_str("if (");
+                self.expression(&if_expr.condition);
+                self.out.push_str(") ");
+                self.block(&if_expr.consequence);
+                if let Some(alt) = &if_expr.alternative {
+                    self.out.push_str(" else ");
+                    self.block(alt);
+                }
+            }
+            Expression::Function(function) => {
+                self.out.push_str("fn");
+                self.function(function);
+            }
+            Expression::Call(call) => {
+                self.operand(
+                    &call.function,
+                    precedence(&call.function) < Precedence::CALL,
+                );
+                self.list("(", &call.arguments, ")");
+            }
+            Expression::Array(array) => self.list("[", &array.elements, "]"),
+            Expression::Hash(hash) => {
+                self.items("{", hash.pairs.len(), "}", |p, i| {
+                    let (key, value) = &hash.pairs[i];
+                    p.expression(key);
+                    p.out.push_str(": ");
+                    p.expression(value);
+                });
+            }
+            Expression::Match(match_expr) => self.match_expression(match_expr),
+            Expression::Spread(spread) => {
+                self.out.push_str("...");
+                self.operand(
+                    &spread.value,
+                    precedence(&spread.value) < Precedence::PREFIX,
+                );
+            }
+        }
+    }
+
+    fn operand(&mut self, expr: &Expression, parenthesize: bool) {
+        if parenthesize {
+            self.out.push('(');
+            self.expression(expr);
+            self.out.push(')');
+        } else {
+            self.expression(expr);
+        }
+    }
+
+    fn match_expression(&mut self, match_expr: &MatchExpression) {
+        self.out.push_str("match (");
+        self.expression(&match_expr.subject);
+        self.out.push_str(") {");
+        let list = self.lists;
+        self.lists += 1;
+        let end = self.list_end(list);
+        if match_expr.arms.is_empty() && end.is_none() {
+            self.out.push('}');
+            return;
+        }
+        self.level += 1;
+        for (i, arm) in match_expr.arms.iter().enumerate() {
+            self.newline();
+            if let Some(leading) = self.item_leading(list, i) {
+                self.comment_lines(leading, false);
+                self.newline();
+            }
+            self.out.push_str(&arm.pattern.to_string());
+            if let Some(guard) = &arm.guard {
+                self.out.push_str(" if ");
+                self.expression(guard);
+            }
+            self.out.push_str(" => ");
+            match &arm.body {
+                // 块语句后面的逗号可以省略
+                Statement::Block(block) => self.block(block),
+                Statement::Expression(expr_stmt) => {
+                    // 以{开头的分支体会被当成块语句，需要括起来
+                    let body = self.measure(|p| p.expression(&expr_stmt.expression));
+                    self.operand(&expr_stmt.expression, body.starts_with('{'));
+                    self.out.push(',');
+                }
+                body => {
+                    self.statement(body);
+                    self.out.push(',');
+                }
+            }
+            if let Some(trailing) = self.item_trailing(list, i) {
+                self.out.push(' ');
+                self.out.push_str(trailing);
+            }
+        }
+        if let Some(end) = end {
+            self.newline();
+            self.comment_lines(end, false);
+        }
+        self.level -= 1;
+        self.newline();
+        self.out.push('}');
+    }
+}
+
+impl Default for Printer<'_> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// 表达式作为操作数时的优先级，字面量、调用以及if、fn、match等以关键字开头的表达式不需要括号
+fn precedence(expr: &Expression) -> Precedence {
+    match expr {
+        Expression::Infix(infix) => token_precedence(infix.token.token_type),
+        Expression::Prefix(_) | Expression::Spread(_) => Precedence::PREFIX,
+        _ => Precedence::CALL,
+    }
+}
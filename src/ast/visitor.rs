@@ -0,0 +1,141 @@
+use super::{BlockStatement, Expression, Identifier, Parameter, Pattern, Program, Statement};
+
+// 只读遍历AST。默认实现调用对应的walk_*函数访问所有子节点，
+// 重写某个visit_*方法时可以在其中调用walk_*继续向下遍历
+pub trait Visitor {
+    fn visit_program(&mut self, program: &Program) {
+        walk_program(self, program);
+    }
+    fn visit_statement(&mut self, stmt: &Statement) {
+        walk_statement(self, stmt);
+    }
+    fn visit_block(&mut self, block: &BlockStatement) {
+        walk_block(self, block);
+    }
+    fn visit_expression(&mut self, expr: &Expression) {
+        walk_expression(self, expr);
+    }
+    // 包括绑定位置（let、参数、函数名、模式）和引用位置的标识符
+    fn visit_identifier(&mut self, _ident: &Identifier) {}
+    fn visit_pattern(&mut self, pattern: &Pattern) {
+        walk_pattern(self, pattern);
+    }
+    fn visit_parameter(&mut self, param: &Parameter) {
+        walk_parameter(self, param);
+    }
+}
+
+pub fn walk_program<V: Visitor + ?Sized>(visitor: &mut V, program: &Program) {
+    for stmt in &program.statements {
+        visitor.visit_statement(stmt);
+    }
+}
+
+pub fn walk_statement<V: Visitor + ?Sized>(visitor: &mut V, stmt: &Statement) {
+    match stmt {
+        Statement::Let(let_stmt) => {
+            visitor.visit_identifier(&let_stmt.name);
+            visitor.visit_expression(&let_stmt.value);
+        }
+        Statement::DestructuringLet(let_stmt) => {
+            visitor.visit_pattern(&let_stmt.pattern);
+            visitor.visit_expression(&let_stmt.value);
+        }
+        Statement::Return(return_stmt) => visitor.visit_expression(&return_stmt.return_value),
+        Statement::Expression(expr_stmt) => visitor.visit_expression(&expr_stmt.expression),
+        Statement::Block(block) => visitor.visit_block(block),
+        Statement::FunctionDeclaration(decl) => {
+            visitor.visit_identifier(&decl.name);
+            for param in &decl.function.parameters {
+                visitor.visit_parameter(param);
+            }
+            visitor.visit_block(&decl.function.body);
+        }
+    }
+}
+
+pub fn walk_block<V: Visitor + ?Sized>(visitor: &mut V, block: &BlockStatement) {
+    for stmt in &block.statements {
+        visitor.visit_statement(stmt);
+    }
+}
+
+pub fn walk_expression<V: Visitor + ?Sized>(visitor: &mut V, expr: &Expression) {
+    match expr {
+        Expression::Identifier(ident) => visitor.visit_identifier(ident),
+        Expression::Integer(_) | Expression::String(_) | Expression::Boolean(_) => {}
+        Expression::Prefix(prefix) => visitor.visit_expression(&prefix.right),
+        Expression::Infix(infix) => {
+            visitor.visit_expression(&infix.left);
+            visitor.visit_expression(&infix.right);
+        }
+        Expression::If(if_expr) => {
+            visitor.visit_expression(&if_expr.condition);
+            visitor.visit_block(&if_expr.consequence);
+            if let Some(alt) = &if_expr.alternative {
+                visitor.visit_block(alt);
+            }
+        }
+        Expression::Function(function) => {
+            for param in &function.parameters {
+                visitor.visit_parameter(param);
+            }
+            visitor.visit_block(&function.body);
+        }
+        Expression::Call(call) => {
+            visitor.visit_expression(&call.function);
+            for arg in &call.arguments {
+                visitor.visit_expression(arg);
+            }
+        }
+        Expression::Array(array) => {
+            for element in &array.elements {
+                visitor.visit_expression(element);
+            }
+        }
+        Expression::Hash(hash) => {
+            for (key, value) in &hash.pairs {
+                visitor.visit_expression(key);
+                visitor.visit_expression(value);
+            }
+        }
+        Expression::Match(match_expr) => {
+            visitor.visit_expression(&match_expr.subject);
+            for arm in &match_expr.arms {
+                visitor.visit_pattern(&arm.pattern);
+                if let Some(guard) = &arm.guard {
+                    visitor.visit_expression(guard);
+                }
+                visitor.visit_statement(&arm.body);
+            }
+        }
+        Expression::Spread(spread) => visitor.visit_expression(&spread.value),
+    }
+}
+
+pub fn walk_parameter<V: Visitor + ?Sized>(visitor: &mut V, param: &Parameter) {
+    visitor.visit_identifier(param.name());
+    if let Parameter::Default(param) = param {
+        visitor.visit_expression(&param.default);
+    }
+}
+
+pub fn walk_pattern<V: Visitor + ?Sized>(visitor: &mut V, pattern: &Pattern) {
+    match pattern {
+        Pattern::Wildcard | Pattern::Integer(_) | Pattern::String(_) | Pattern::Boolean(_) => {}
+        Pattern::Identifier(ident) => visitor.visit_identifier(ident),
+        Pattern::Array(array) => {
+            for element in &array.elements {
+                visitor.visit_pattern(element);
+            }
+            if let Some(rest) = &array.rest {
+                visitor.visit_identifier(rest);
+            }
+        }
+        Pattern::Hash(hash) => {
+            for (_, pattern) in &hash.pairs {
+                visitor.visit_pattern(pattern);
+            }
+        }
+    }
+}
@@ -0,0 +1,317 @@
+use std::fs;
+use std::io::{self, IsTerminal, Read};
+use std::rc::Rc;
+
+use crate::dot::dot::call_graph_to_dot;
+use crate::dump::dump::{DumpMode, dump};
+use crate::evaluator::context::{
+    DEFAULT_MAX_DEPTH, EvalContext, MAX_DEPTH_LIMIT, run_with_stack, stack_size_for,
+};
+use crate::evaluator::evaluator::eval_with_context;
+use crate::evaluator::trace::IndentedTrace;
+use crate::formatter::formatter;
+use crate::lexer::lexer::Lexer;
+use crate::object::environment::Environment;
+use crate::object::{Array, Value};
+use crate::parser::parser::Parser;
+use crate::prelude::prelude;
+use crate::repl::repl;
+
+// 进程退出码
+pub const EXIT_OK: i32 = 0;
+pub const EXIT_RUNTIME_ERROR: i32 = 1;
+pub const EXIT_PARSE_ERROR: i32 = 2; // 也用于用法错误和读取失败
+
+const USAGE: &str =
+    "usage: monkey [options]                          start the REPL, or run a script piped to stdin
+       monkey [options] [run] <file> [args...]  run a script file, - reads stdin
+       monkey [options] -e <code> [args...]     run code given on the command line
+       monkey fmt [--check] [--width N] [path ...]
+
+options print the front end output instead of evaluating:
+       --tokens          the token stream with line:column positions
+       --tokens=json     the token stream as JSON with byte spans
+       --ast[=tree]      the AST as an indented tree
+       --ast=sexp        the AST as S-expressions
+       --ast=json        the AST as versioned JSON with byte spans
+       --ast=dot         the AST as a Graphviz graph
+
+       --call-graph[=<file>]  after running, write the calls made as a Graphviz
+                              graph with call counts to stdout or <file>
+       --trace                write an indented evaluation trace to stderr
+       --bare                 start without the prelude (map, filter, reduce, ...)
+                              and without ~/.monkeyrc or $MONKEYRC
+       --max-depth=<n>        allow at most n nested function calls (default 1000,
+                              at most 100000); tail calls do not count
+
+Arguments after the script are available to it as the array `args`.";
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ScriptError {
+    Parse(Vec<String>),
+    Runtime(String),
+}
+
+impl ScriptError {
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            ScriptError::Parse(_) => EXIT_PARSE_ERROR,
+            ScriptError::Runtime(_) => EXIT_RUNTIME_ERROR,
+        }
+    }
+}
+
+// 命令前面的选项
+#[derive(Debug)]
+struct Options {
+    dump_mode: Option<DumpMode>,
+    call_graph: Option<String>, // 调用图写到的文件，-是标准输出
+    trace: bool,
+    bare: bool,       // 不载入prelude和启动脚本
+    max_depth: usize, // 最多嵌套的函数调用数
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            dump_mode: None,
+            call_graph: None,
+            trace: false,
+            bare: false,
+            max_depth: DEFAULT_MAX_DEPTH,
+        }
+    }
+}
+
+// 命令行入口，args不包含程序名，返回进程退出码
+pub fn run(args: &[String]) -> i32 {
+    let mut args = args;
+    let mut options = Options::default();
+    while let Some(option) = args.first() {
+        match option.as_str() {
+            "--tokens" => options.dump_mode = Some(DumpMode::Tokens),
+            "--tokens=json" => options.dump_mode = Some(DumpMode::TokensJson),
+            "--ast" => options.dump_mode = Some(DumpMode::Tree),
+            "--call-graph" => options.call_graph = Some("-".to_string()),
+            "--trace" => options.trace = true,
+            "--bare" => options.bare = true,
+            option => {
+                if let Some(name) = option.strip_prefix("--ast=") {
+                    match DumpMode::from_name(name)
+                        .filter(|m| !matches!(m, DumpMode::Tokens | DumpMode::TokensJson))
+                    {
+                        Some(mode) => options.dump_mode = Some(mode),
+                        None => return usage_error(&format!("unknown AST format: {}", name)),
+                    }
+                } else if let Some(path) = option.strip_prefix("--call-graph=") {
+                    options.call_graph = Some(path.to_string());
+                } else if let Some(depth) = option.strip_prefix("--max-depth=") {
+                    match depth.parse() {
+                        Ok(depth) if depth <= MAX_DEPTH_LIMIT => options.max_depth = depth,
+                        Ok(_) => {
+                            return usage_error(&format!(
+                                "--max-depth must be at most {}: {}",
+                                MAX_DEPTH_LIMIT, depth
+                            ));
+                        }
+                        Err(_) => return usage_error(&format!("invalid --max-depth: {}", depth)),
+                    }
+                } else {
+                    break;
+                }
+            }
+        }
+        args = &args[1..];
+    }
+
+    // 主线程的栈不够 max_depth 层调用，在栈足够大的线程上执行
+    let args = args.to_vec();
+    let stack_size = stack_size_for(options.max_depth);
+    match run_with_stack(stack_size, move || run_command(&args, &options)) {
+        Ok(code) => code,
+        Err(err) => {
+            eprintln!(
+                "monkey: cannot allocate a {} byte stack: {}",
+                stack_size, err
+            );
+            EXIT_RUNTIME_ERROR
+        }
+    }
+}
+
+fn run_command(args: &[String], options: &Options) -> i32 {
+    let Some(first) = args.first() else {
+        // 标准输入不是终端时把它当作脚本执行，例如 echo '1 + 2' | monkey
+        if io::stdin().is_terminal() {
+            if options.call_graph.is_some() {
+                return usage_error("--call-graph expects a script");
+            }
+            repl::start_with(
+                options.dump_mode,
+                options.trace,
+                options.bare,
+                options.max_depth,
+            );
+            return EXIT_OK;
+        }
+        return run_file("-", &[], options);
+    };
+
+    match first.as_str() {
+        "fmt" => formatter::run(&args[1..]),
+        "-h" | "--help" => {
+            println!("{}", USAGE);
+            EXIT_OK
+        }
+        "-e" => match args.get(1) {
+            Some(code) => execute("-e", code, &args[2..], options),
+            None => usage_error("-e expects an argument"),
+        },
+        "run" => match args.get(1) {
+            Some(path) => run_file(path, &args[2..], options),
+            None => usage_error("run expects a file"),
+        },
+        option if option.starts_with('-') && option != "-" => {
+            usage_error(&format!("unknown option: {}", option))
+        }
+        // 带 #!/usr/bin/env monkey 的脚本直接执行时走这里
+        path => run_file(path, &args[1..], options),
+    }
+}
+
+fn usage_error(message: &str) -> i32 {
+    eprintln!("monkey: {}\n{}", message, USAGE);
+    EXIT_PARSE_ERROR
+}
+
+fn run_file(path: &str, script_args: &[String], options: &Options) -> i32 {
+    let (name, source) = if path == "-" {
+        let mut source = String::new();
+        let result = io::stdin().read_to_string(&mut source);
+        ("<stdin>", result.map(|_| source))
+    } else {
+        (path, fs::read_to_string(path))
+    };
+    match source {
+        Ok(source) => execute(name, &source, script_args, options),
+        Err(err) => {
+            eprintln!("monkey: {}: {}", name, err);
+            EXIT_PARSE_ERROR
+        }
+    }
+}
+
+// 执行脚本，打印非null的结果，错误写到标准错误
+fn execute(name: &str, source: &str, script_args: &[String], options: &Options) -> i32 {
+    if let Some(mode) = options.dump_mode {
+        return match dump(&strip_shebang(source), mode) {
+            Ok(out) => {
+                print!("{}", out);
+                EXIT_OK
+            }
+            Err(errors) => {
+                print_parse_errors(name, &errors);
+                EXIT_PARSE_ERROR
+            }
+        };
+    }
+
+    let mut ctx = EvalContext::with_max_depth(options.max_depth);
+    if options.call_graph.is_some() {
+        ctx.enable_call_graph();
+    }
+    if options.trace {
+        ctx.set_trace_sink(Box::new(IndentedTrace::new(io::stderr())));
+    }
+    // 启动脚本出错时提示后照常执行
+    let mut env = Environment::new();
+    if !options.bare
+        && let Err(message) = prelude::load(&mut env)
+    {
+        eprintln!("monkey: {}", message);
+    }
+    let code = match run_script_in(source, script_args, &mut env, &mut ctx) {
+        Ok(Value::Null) => EXIT_OK,
+        Ok(value) => {
+            println!("{}", value.inspect());
+            EXIT_OK
+        }
+        Err(err) => {
+            match &err {
+                ScriptError::Parse(errors) => print_parse_errors(name, errors),
+                ScriptError::Runtime(message) => eprintln!("{}: ERROR: {}", name, message),
+            }
+            err.exit_code()
+        }
+    };
+
+    // 运行出错时也输出已经记录的调用
+    if let (Some(path), Some(graph)) = (&options.call_graph, ctx.call_graph()) {
+        let dot = call_graph_to_dot(graph);
+        if path == "-" {
+            print!("{}", dot);
+        } else if let Err(err) = fs::write(path, dot) {
+            eprintln!("monkey: {}: {}", path, err);
+            return EXIT_PARSE_ERROR;
+        }
+    }
+    code
+}
+
+fn print_parse_errors(name: &str, errors: &[String]) {
+    for error in errors {
+        eprintln!("{}: {}", name, error);
+    }
+}
+
+// 解析并求值一段脚本，脚本参数绑定为字符串数组args
+pub fn run_script(source: &str, script_args: &[String]) -> Result<Value, ScriptError> {
+    run_script_with_context(source, script_args, &mut EvalContext::new())
+}
+
+// 同run_script，使用调用者提供的求值上下文，可以设置限制或在求值后读取调用图
+pub fn run_script_with_context(
+    source: &str,
+    script_args: &[String],
+    ctx: &mut EvalContext,
+) -> Result<Value, ScriptError> {
+    run_script_in(source, script_args, &mut Environment::new(), ctx)
+}
+
+// 同run_script_with_context，在env中求值。递归较深的脚本需要在栈不小于
+// ctx.stack_size()的线程上调用，见run_with_stack，例如已经载入prelude的环境
+pub fn run_script_in(
+    source: &str,
+    script_args: &[String],
+    env: &mut Environment,
+    ctx: &mut EvalContext,
+) -> Result<Value, ScriptError> {
+    let mut p = Parser::new(Lexer::new(strip_shebang(source)));
+    let program = p.parse_program();
+    if !p.errors().is_empty() {
+        return Err(ScriptError::Parse(p.errors()));
+    }
+
+    let args = script_args
+        .iter()
+        .map(|arg| Value::String(Rc::from(arg.as_str())))
+        .collect();
+    env.set("args", Value::Array(Rc::new(Array::new(args))));
+
+    match eval_with_context(&program, env, ctx) {
+        Value::Error(message) => Err(ScriptError::Runtime(message.to_string())),
+        value => Ok(value),
+    }
+}
+
+// 去掉第一行的 #!，保留换行使行号不变
+pub(crate) fn strip_shebang(source: &str) -> String {
+    if source.starts_with("#!") {
+        match source.find('\n') {
+            Some(end) => source[end..].to_string(),
+            None => String::new(),
+        }
+    } else {
+        source.to_string()
+    }
+}
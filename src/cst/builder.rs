@@ -0,0 +1,133 @@
+use std::ops::Range;
+use std::rc::Rc;
+
+use crate::lexer::lexer::Lexer;
+use crate::token::token::TokenType;
+
+use super::{GreenElement, GreenNode, GreenToken, SyntaxKind};
+
+// 还没有放进树中的词法单元，范围按字符计
+#[derive(Debug, Clone)]
+struct PendingToken {
+    kind: TokenType,
+    trivia: Range<usize>, // 前面的空白和注释
+    text: Range<usize>,
+}
+
+// 解析器移动词法单元时构建绿树。词法单元在解析器越过它时放进树中，
+// 节点在解析完成后用解析开始时的检查点包起来，所以解析失败时不会留下没有结束的节点
+#[derive(Debug, Default)]
+pub(crate) struct CstBuilder {
+    enabled: bool,
+    children: Vec<GreenElement>,
+    cur: Option<PendingToken>, // cur_token，放进树中后为None
+    cur_trivia_done: bool,     // cur_token前面的trivia已经放进树中
+    peek: Option<PendingToken>,
+    last_end: usize, // 上一个词法单元的结束位置
+    root: Option<Rc<GreenNode>>,
+}
+
+impl CstBuilder {
+    pub(crate) fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+    }
+
+    pub(crate) fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub(crate) fn root(&self) -> Option<Rc<GreenNode>> {
+        self.root.clone()
+    }
+
+    // 词法分析器刚返回了新的peek_token，原来的cur_token已经被越过
+    pub(crate) fn advance(&mut self, l: &Lexer, kind: TokenType) {
+        let text = l.token_range();
+        let trivia = self.last_end..text.start;
+        self.last_end = text.end;
+
+        if let Some(cur) = self.cur.take() {
+            self.push_token(l, cur);
+        }
+        self.cur = self.peek.take();
+        self.cur_trivia_done = false;
+        self.peek = Some(PendingToken { kind, trivia, text });
+    }
+
+    // cur_token是下一个节点的第一个词法单元，它前面的trivia留在节点外
+    pub(crate) fn checkpoint(&mut self, l: &Lexer) -> usize {
+        if !self.enabled {
+            return 0;
+        }
+        if let Some(cur) = &self.cur
+            && !self.cur_trivia_done
+        {
+            let trivia = cur.trivia.clone();
+            self.push_trivia(l, trivia);
+            self.cur_trivia_done = true;
+        }
+        self.children.len()
+    }
+
+    // cur_token是节点的最后一个词法单元，把检查点之后的内容包成节点
+    pub(crate) fn finish_node(&mut self, l: &Lexer, checkpoint: usize, kind: SyntaxKind) {
+        if !self.enabled {
+            return;
+        }
+        if let Some(cur) = self.cur.take() {
+            self.push_token(l, cur);
+        }
+        let children = self.children.split_off(checkpoint);
+        self.children
+            .push(GreenElement::Node(Rc::new(GreenNode::new(kind, children))));
+    }
+
+    // cur_token是EOF，剩下的trivia放在程序末尾
+    pub(crate) fn finish(&mut self, l: &Lexer) {
+        if !self.enabled {
+            return;
+        }
+        if let Some(cur) = self.cur.take() {
+            self.push_token(l, cur);
+        }
+        let children = std::mem::take(&mut self.children);
+        self.root = Some(Rc::new(GreenNode::new(SyntaxKind::Program, children)));
+    }
+
+    fn push_token(&mut self, l: &Lexer, token: PendingToken) {
+        if !self.enabled {
+            return;
+        }
+        if !self.cur_trivia_done {
+            self.push_trivia(l, token.trivia);
+        }
+        self.cur_trivia_done = false;
+        // EOF没有原文，不放进树中
+        if token.kind != TokenType::EOF {
+            let text = l.slice(token.text);
+            self.children
+                .push(GreenElement::Token(Rc::new(GreenToken::new(
+                    token.kind, text,
+                ))));
+        }
+    }
+
+    // 把空白和注释拆成WHITESPACE和COMMENT词法单元
+    fn push_trivia(&mut self, l: &Lexer, range: Range<usize>) {
+        let trivia = l.slice(range);
+        let mut rest = trivia.as_str();
+        while !rest.is_empty() {
+            let (kind, len) = if rest.starts_with("//") {
+                (TokenType::COMMENT, rest.find('\n').unwrap_or(rest.len()))
+            } else {
+                (TokenType::WHITESPACE, rest.find("//").unwrap_or(rest.len()))
+            };
+            self.children
+                .push(GreenElement::Token(Rc::new(GreenToken::new(
+                    kind,
+                    rest[..len].to_string(),
+                ))));
+            rest = &rest[len..];
+        }
+    }
+}
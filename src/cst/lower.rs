@@ -0,0 +1,282 @@
+use crate::ast::{
+    ArrayLiteral, ArrayPattern, BlockStatement, Boolean, CallExpression, DefaultParameter,
+    DestructuringLetStatement, Expression, ExpressionStatement, FunctionDeclaration,
+    FunctionLiteral, HashLiteral, HashPattern, Identifier, IfExpression, InfixExpression,
+    IntegerLiteral, LetStatement, MatchArm, MatchExpression, Parameter, Pattern, PrefixExpression,
+    Program, RestParameter, ReturnStatement, SpreadExpression, Statement, StringLiteral,
+};
+use crate::token::token::{Token, TokenType};
+
+use super::{SyntaxKind, SyntaxNode, SyntaxToken};
+
+// 由具体语法树得到AST。错误节点被跳过，与解析器丢弃解析失败的语句一致
+pub fn lower_program(root: &SyntaxNode) -> Program {
+    Program {
+        statements: root.children().iter().filter_map(statement).collect(),
+    }
+}
+
+pub fn statement(node: &SyntaxNode) -> Option<Statement> {
+    let tokens = node.tokens();
+    let children = node.children();
+    let stmt = match node.kind() {
+        SyntaxKind::LetStatement => Statement::Let(LetStatement {
+            token: token(tokens.first()?),
+            name: Box::new(identifier(tokens.get(1)?)),
+            value: Box::new(expression(children.first()?)?),
+        }),
+        SyntaxKind::DestructuringLetStatement => {
+            Statement::DestructuringLet(DestructuringLetStatement {
+                token: token(tokens.first()?),
+                pattern: pattern(children.first()?)?,
+                value: Box::new(expression(children.get(1)?)?),
+            })
+        }
+        SyntaxKind::ReturnStatement => Statement::Return(ReturnStatement {
+            token: token(tokens.first()?),
+            return_value: Box::new(expression(children.first()?)?),
+        }),
+        SyntaxKind::ExpressionStatement => Statement::Expression(expression_statement(node)?),
+        SyntaxKind::Block => Statement::Block(block(node)?),
+        SyntaxKind::FunctionDeclaration => {
+            let token = token(tokens.first()?);
+            Statement::FunctionDeclaration(FunctionDeclaration {
+                token: token.clone(),
+                name: identifier(tokens.get(1)?),
+                function: FunctionLiteral {
+                    token,
+                    parameters: parameters(children.first()?)?,
+                    body: block(children.get(1)?)?,
+                },
+            })
+        }
+        _ => return None,
+    };
+    Some(stmt)
+}
+
+pub fn expression(node: &SyntaxNode) -> Option<Expression> {
+    let tokens = node.tokens();
+    let children = node.children();
+    let expr = match node.kind() {
+        SyntaxKind::Identifier => Expression::Identifier(identifier(tokens.first()?)),
+        SyntaxKind::Integer => {
+            let token = token(tokens.first()?);
+            let value = token.literal.parse().ok()?;
+            Expression::Integer(IntegerLiteral { token, value })
+        }
+        SyntaxKind::String => {
+            let token = token(tokens.first()?);
+            let value = token.literal.clone();
+            Expression::String(StringLiteral { token, value })
+        }
+        SyntaxKind::Boolean => {
+            let token = token(tokens.first()?);
+            let value = token.token_type == TokenType::TRUE;
+            Expression::Boolean(Boolean { token, value })
+        }
+        SyntaxKind::Prefix => {
+            let token = token(tokens.first()?);
+            Expression::Prefix(PrefixExpression {
+                operator: token.literal.clone(),
+                token,
+                right: Box::new(expression(children.first()?)?),
+            })
+        }
+        SyntaxKind::Infix => {
+            let token = token(tokens.first()?);
+            Expression::Infix(InfixExpression {
+                operator: token.literal.clone(),
+                token,
+                left: Box::new(expression(children.first()?)?),
+                right: Box::new(expression(children.get(1)?)?),
+            })
+        }
+        SyntaxKind::Paren => expression(children.first()?)?,
+        SyntaxKind::If => Expression::If(IfExpression {
+            token: token(tokens.first()?),
+            condition: Box::new(expression(children.first()?)?),
+            consequence: block(children.get(1)?)?,
+            alternative: match children.get(2) {
+                Some(alt) => Some(block(alt)?),
+                None => None,
+            },
+        }),
+        SyntaxKind::Function => Expression::Function(FunctionLiteral {
+            token: token(tokens.first()?),
+            parameters: parameters(children.first()?)?,
+            body: block(children.get(1)?)?,
+        }),
+        SyntaxKind::Call => Expression::Call(CallExpression {
+            token: token(tokens.first()?),
+            function: Box::new(expression(children.first()?)?),
+            arguments: expressions(&children[1..])?,
+        }),
+        SyntaxKind::Array => Expression::Array(ArrayLiteral {
+            token: token(tokens.first()?),
+            elements: expressions(&children)?,
+        }),
+        SyntaxKind::Hash => Expression::Hash(HashLiteral {
+            token: token(tokens.first()?),
+            pairs: children
+                .chunks(2)
+                .map(|pair| Some((expression(pair.first()?)?, expression(pair.get(1)?)?)))
+                .collect::<Option<_>>()?,
+        }),
+        SyntaxKind::Match => Expression::Match(MatchExpression {
+            token: token(tokens.first()?),
+            subject: Box::new(expression(children.first()?)?),
+            arms: children[1..].iter().map(match_arm).collect::<Option<_>>()?,
+        }),
+        SyntaxKind::Spread => Expression::Spread(SpreadExpression {
+            token: token(tokens.first()?),
+            value: Box::new(expression(children.first()?)?),
+        }),
+        _ => return None,
+    };
+    Some(expr)
+}
+
+pub fn pattern(node: &SyntaxNode) -> Option<Pattern> {
+    let tokens = node.tokens();
+    let pattern = match node.kind() {
+        SyntaxKind::WildcardPattern => Pattern::Wildcard,
+        SyntaxKind::IntegerPattern => {
+            let value: i64 = tokens.last()?.text().parse().ok()?;
+            if tokens.first()?.kind() == TokenType::MINUS {
+                Pattern::Integer(-value)
+            } else {
+                Pattern::Integer(value)
+            }
+        }
+        SyntaxKind::StringPattern => Pattern::String(token(tokens.first()?).literal),
+        SyntaxKind::BooleanPattern => Pattern::Boolean(tokens.first()?.kind() == TokenType::TRUE),
+        SyntaxKind::IdentifierPattern => Pattern::Identifier(identifier(tokens.first()?)),
+        SyntaxKind::ArrayPattern => {
+            // ...后面的标识符是剩余绑定
+            let rest = tokens
+                .iter()
+                .position(|t| t.kind() == TokenType::ELLIPSIS)
+                .map(|i| tokens.get(i + 1).map(identifier))
+                .map_or(Some(None), |rest| rest.map(Some))?;
+            Pattern::Array(ArrayPattern {
+                token: token(tokens.first()?),
+                elements: node.children().iter().map(pattern).collect::<Option<_>>()?,
+                rest,
+            })
+        }
+        SyntaxKind::HashPattern => Pattern::Hash(HashPattern {
+            token: token(tokens.first()?),
+            pairs: node
+                .children()
+                .iter()
+                .map(|field| {
+                    let key = field.tokens().first()?.clone();
+                    // 简写形式 {name} 等价于 {name: name}
+                    let value = match field.children().first() {
+                        Some(value) => pattern(value)?,
+                        None => Pattern::Identifier(identifier(&key)),
+                    };
+                    Some((token(&key).literal, value))
+                })
+                .collect::<Option<_>>()?,
+        }),
+        _ => return None,
+    };
+    Some(pattern)
+}
+
+fn block(node: &SyntaxNode) -> Option<BlockStatement> {
+    if node.kind() != SyntaxKind::Block {
+        return None;
+    }
+    Some(BlockStatement {
+        token: token(node.tokens().first()?),
+        statements: node
+            .children()
+            .iter()
+            .map(statement)
+            .collect::<Option<_>>()?,
+    })
+}
+
+// 表达式语句的词法单元是表达式的第一个词法单元
+fn expression_statement(node: &SyntaxNode) -> Option<ExpressionStatement> {
+    let expr = match node.kind() {
+        SyntaxKind::ExpressionStatement => node.children().into_iter().next()?,
+        _ => node.clone(),
+    };
+    Some(ExpressionStatement {
+        token: token(&node.first_token()?),
+        expression: Box::new(expression(&expr)?),
+    })
+}
+
+fn match_arm(node: &SyntaxNode) -> Option<MatchArm> {
+    let children = node.children();
+    let has_guard = node.tokens().iter().any(|t| t.kind() == TokenType::IF);
+    let body = children.last()?;
+    Some(MatchArm {
+        pattern: pattern(children.first()?)?,
+        guard: if has_guard {
+            Some(expression(children.get(1)?)?)
+        } else {
+            None
+        },
+        body: match body.kind() {
+            SyntaxKind::Block => Statement::Block(block(body)?),
+            _ => Statement::Expression(expression_statement(body)?),
+        },
+    })
+}
+
+fn parameters(node: &SyntaxNode) -> Option<Vec<Parameter>> {
+    node.children()
+        .iter()
+        .map(|param| {
+            let tokens = param.tokens();
+            let first = tokens.first()?;
+            let param = if first.kind() == TokenType::ELLIPSIS {
+                Parameter::Rest(RestParameter {
+                    token: token(first),
+                    name: identifier(tokens.get(1)?),
+                })
+            } else if let Some(assign) = tokens.get(1) {
+                Parameter::Default(DefaultParameter {
+                    token: token(assign),
+                    name: identifier(first),
+                    default: Box::new(expression(param.children().first()?)?),
+                })
+            } else {
+                Parameter::Plain(identifier(first))
+            };
+            Some(param)
+        })
+        .collect()
+}
+
+fn expressions(nodes: &[SyntaxNode]) -> Option<Vec<Expression>> {
+    nodes.iter().map(expression).collect()
+}
+
+fn identifier(t: &SyntaxToken) -> Identifier {
+    Identifier {
+        token: token(t),
+        value: t.text().to_string(),
+    }
+}
+
+// 还原词法分析器给出的词法单元，字符串的字面量不含引号
+fn token(t: &SyntaxToken) -> Token {
+    let literal = match t.kind() {
+        TokenType::STRING => {
+            let inner = &t.text()[1..];
+            inner.strip_suffix('"').unwrap_or(inner).to_string()
+        }
+        _ => t.text().to_string(),
+    };
+    Token {
+        token_type: t.kind(),
+        literal,
+    }
+}
@@ -0,0 +1,315 @@
+use core::fmt;
+use std::ops::Range;
+use std::rc::Rc;
+
+use crate::ast::Program;
+use crate::lexer::lexer::Lexer;
+use crate::parser::parser::Parser;
+use crate::token::token::TokenType;
+
+pub(crate) mod builder;
+pub mod lower;
+
+// 无损的具体语法树：绿树只记录种类和原文，可以共享和复用；
+// 红树在绿树上加上偏移和父节点，用于定位。拼接所有词法单元的原文得到的就是源码本身，
+// 空白、注释（trivia）和解析失败的部分都保留在树中
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SyntaxKind {
+    Program,
+    LetStatement,
+    DestructuringLetStatement,
+    ReturnStatement,
+    ExpressionStatement,
+    FunctionDeclaration,
+    Block,
+    Identifier,
+    Integer,
+    String,
+    Boolean,
+    Prefix,
+    Infix,
+    Paren, // 括号只影响结合顺序，AST中没有对应的节点
+    If,
+    Function,
+    ParameterList,
+    Parameter,
+    Call,
+    Array,
+    Hash,
+    Match,
+    MatchArm,
+    Spread,
+    WildcardPattern,
+    IntegerPattern,
+    StringPattern,
+    BooleanPattern,
+    IdentifierPattern,
+    ArrayPattern,
+    HashPattern,
+    HashPatternField,
+    Error, // 解析失败的语句，里面是原样保留的词法单元
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GreenToken {
+    kind: TokenType,
+    text: String,
+}
+
+impl GreenToken {
+    pub fn new(kind: TokenType, text: String) -> Self {
+        GreenToken { kind, text }
+    }
+
+    pub fn kind(&self) -> TokenType {
+        self.kind
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GreenElement {
+    Node(Rc<GreenNode>),
+    Token(Rc<GreenToken>),
+}
+
+impl GreenElement {
+    // 原文的字节长度
+    pub fn len(&self) -> usize {
+        match self {
+            GreenElement::Node(node) => node.len(),
+            GreenElement::Token(token) => token.text.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GreenNode {
+    kind: SyntaxKind,
+    len: usize,
+    children: Vec<GreenElement>,
+}
+
+impl GreenNode {
+    pub fn new(kind: SyntaxKind, children: Vec<GreenElement>) -> Self {
+        let len = children.iter().map(GreenElement::len).sum();
+        GreenNode {
+            kind,
+            len,
+            children,
+        }
+    }
+
+    pub fn kind(&self) -> SyntaxKind {
+        self.kind
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn children(&self) -> &[GreenElement] {
+        &self.children
+    }
+}
+
+// 输出节点覆盖的原文
+impl fmt::Display for GreenNode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for child in &self.children {
+            match child {
+                GreenElement::Node(node) => write!(f, "{}", node)?,
+                GreenElement::Token(token) => f.write_str(&token.text)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+// 红树节点，偏移按字节计
+#[derive(Clone)]
+pub struct SyntaxNode(Rc<SyntaxData>);
+
+struct SyntaxData {
+    green: Rc<GreenNode>,
+    offset: usize,
+    parent: Option<SyntaxNode>,
+}
+
+#[derive(Debug, Clone)]
+pub enum SyntaxElement {
+    Node(SyntaxNode),
+    Token(SyntaxToken),
+}
+
+#[derive(Clone)]
+pub struct SyntaxToken {
+    green: Rc<GreenToken>,
+    offset: usize,
+    parent: SyntaxNode,
+}
+
+impl SyntaxNode {
+    pub fn new_root(green: Rc<GreenNode>) -> Self {
+        SyntaxNode(Rc::new(SyntaxData {
+            green,
+            offset: 0,
+            parent: None,
+        }))
+    }
+
+    pub fn kind(&self) -> SyntaxKind {
+        self.0.green.kind
+    }
+
+    pub fn green(&self) -> &Rc<GreenNode> {
+        &self.0.green
+    }
+
+    pub fn parent(&self) -> Option<SyntaxNode> {
+        self.0.parent.clone()
+    }
+
+    pub fn text_range(&self) -> Range<usize> {
+        self.0.offset..self.0.offset + self.0.green.len
+    }
+
+    pub fn text(&self) -> String {
+        self.0.green.to_string()
+    }
+
+    pub fn children_with_tokens(&self) -> Vec<SyntaxElement> {
+        let mut offset = self.0.offset;
+        let mut elements = Vec::with_capacity(self.0.green.children.len());
+        for child in &self.0.green.children {
+            elements.push(match child {
+                GreenElement::Node(node) => SyntaxElement::Node(SyntaxNode(Rc::new(SyntaxData {
+                    green: node.clone(),
+                    offset,
+                    parent: Some(self.clone()),
+                }))),
+                GreenElement::Token(token) => SyntaxElement::Token(SyntaxToken {
+                    green: token.clone(),
+                    offset,
+                    parent: self.clone(),
+                }),
+            });
+            offset += child.len();
+        }
+        elements
+    }
+
+    pub fn children(&self) -> Vec<SyntaxNode> {
+        self.children_with_tokens()
+            .into_iter()
+            .filter_map(|element| match element {
+                SyntaxElement::Node(node) => Some(node),
+                SyntaxElement::Token(_) => None,
+            })
+            .collect()
+    }
+
+    // 直接子词法单元，不含trivia
+    pub fn tokens(&self) -> Vec<SyntaxToken> {
+        self.children_with_tokens()
+            .into_iter()
+            .filter_map(|element| match element {
+                SyntaxElement::Token(token) if !token.is_trivia() => Some(token),
+                _ => None,
+            })
+            .collect()
+    }
+
+    // 按源码顺序的第一个非trivia词法单元，包括子孙节点中的
+    pub fn first_token(&self) -> Option<SyntaxToken> {
+        self.children_with_tokens()
+            .into_iter()
+            .find_map(|element| match element {
+                SyntaxElement::Node(node) => node.first_token(),
+                SyntaxElement::Token(token) if !token.is_trivia() => Some(token),
+                SyntaxElement::Token(_) => None,
+            })
+    }
+}
+
+impl fmt::Debug for SyntaxNode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}@{:?}", self.kind(), self.text_range())
+    }
+}
+
+impl SyntaxToken {
+    pub fn kind(&self) -> TokenType {
+        self.green.kind
+    }
+
+    pub fn text(&self) -> &str {
+        &self.green.text
+    }
+
+    pub fn parent(&self) -> SyntaxNode {
+        self.parent.clone()
+    }
+
+    pub fn text_range(&self) -> Range<usize> {
+        self.offset..self.offset + self.green.text.len()
+    }
+
+    pub fn is_trivia(&self) -> bool {
+        matches!(self.kind(), TokenType::WHITESPACE | TokenType::COMMENT)
+    }
+}
+
+impl fmt::Debug for SyntaxToken {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{:?}@{:?} {:?}",
+            self.kind(),
+            self.text_range(),
+            self.text()
+        )
+    }
+}
+
+// 一次解析的结果：语法树和解析错误
+pub struct Parse {
+    green: Rc<GreenNode>,
+    errors: Vec<String>,
+}
+
+impl Parse {
+    pub fn syntax(&self) -> SyntaxNode {
+        SyntaxNode::new_root(self.green.clone())
+    }
+
+    pub fn errors(&self) -> &[String] {
+        &self.errors
+    }
+
+    // 由语法树得到AST，解析失败的语句被跳过，与Parser::parse_program的结果相同
+    pub fn program(&self) -> Program {
+        lower::lower_program(&self.syntax())
+    }
+}
+
+pub fn parse(input: &str) -> Parse {
+    let mut p = Parser::new(Lexer::new(input.to_string()));
+    p.set_build_cst(true);
+    p.parse_program();
+    Parse {
+        green: p.syntax_tree().expect("syntax tree is built"),
+        errors: p.errors(),
+    }
+}
@@ -0,0 +1,272 @@
+use crate::ast::{
+    BlockStatement, Expression, FunctionLiteral, MatchArm, Parameter, Program, Statement,
+};
+use crate::evaluator::call_graph::CallGraph;
+
+// Graphviz DOT格式的AST，每个节点标出种类和字面量，需要区分的子节点在边上标出字段名，
+// 子节点按源码顺序从左到右排列。用 dot -Tsvg 等命令渲染
+pub fn program_to_dot(program: &Program) -> String {
+    let mut graph = Graph {
+        out: String::new(),
+        nodes: 0,
+    };
+    graph.out.push_str("digraph ast {\n  ordering=out;\n");
+    graph
+        .out
+        .push_str("  node [shape=box, fontname=\"monospace\"];\n");
+    let root = graph.node("Program", None);
+    for stmt in &program.statements {
+        let child = graph.statement(stmt);
+        graph.edge(root, child, None);
+    }
+    graph.out.push_str("}\n");
+    graph.out
+}
+
+// 动态调用图，节点标出被调用的次数，边上是调用次数
+pub fn call_graph_to_dot(calls: &CallGraph) -> String {
+    let mut out = String::from("digraph calls {\n");
+    out.push_str("  node [shape=ellipse, fontname=\"monospace\"];\n");
+    for function in calls.functions() {
+        let count = calls.calls(function);
+        let label = match count {
+            0 => function.to_string(),
+            1 => format!("{}\n1 call", function),
+            _ => format!("{}\n{} calls", function, count),
+        };
+        out.push_str(&format!(
+            "  {} [label={}];\n",
+            quote(function),
+            quote(&label)
+        ));
+    }
+    for edge in calls.edges() {
+        out.push_str(&format!(
+            "  {} -> {} [label=\"{}\"];\n",
+            quote(&edge.caller),
+            quote(&edge.callee),
+            edge.count
+        ));
+    }
+    out.push_str("}\n");
+    out
+}
+
+// DOT的双引号字符串
+fn quote(text: &str) -> String {
+    let mut out = String::from("\"");
+    for c in text.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => {}
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+struct Graph {
+    out: String,
+    nodes: usize,
+}
+
+impl Graph {
+    // 输出一个节点，返回它的编号
+    fn node(&mut self, kind: &str, literal: Option<&str>) -> usize {
+        let id = self.nodes;
+        self.nodes += 1;
+        let label = match literal {
+            Some(literal) => format!("{}\n{}", kind, literal),
+            None => kind.to_string(),
+        };
+        self.out
+            .push_str(&format!("  n{} [label={}];\n", id, quote(&label)));
+        id
+    }
+
+    fn edge(&mut self, from: usize, to: usize, label: Option<&str>) {
+        match label {
+            Some(label) => self.out.push_str(&format!(
+                "  n{} -> n{} [label={}];\n",
+                from,
+                to,
+                quote(label)
+            )),
+            None => self.out.push_str(&format!("  n{} -> n{};\n", from, to)),
+        }
+    }
+
+    fn statement(&mut self, stmt: &Statement) -> usize {
+        match stmt {
+            Statement::Let(let_stmt) => {
+                let id = self.node("LetStatement", Some(&let_stmt.name.value));
+                let value = self.expression(&let_stmt.value);
+                self.edge(id, value, None);
+                id
+            }
+            Statement::DestructuringLet(let_stmt) => {
+                let pattern = let_stmt.pattern.to_string();
+                let id = self.node("DestructuringLetStatement", Some(&pattern));
+                let value = self.expression(&let_stmt.value);
+                self.edge(id, value, None);
+                id
+            }
+            Statement::Return(return_stmt) => {
+                let id = self.node("ReturnStatement", None);
+                let value = self.expression(&return_stmt.return_value);
+                self.edge(id, value, None);
+                id
+            }
+            Statement::Expression(expr_stmt) => {
+                let id = self.node("ExpressionStatement", None);
+                let expr = self.expression(&expr_stmt.expression);
+                self.edge(id, expr, None);
+                id
+            }
+            Statement::Block(block) => self.block(block),
+            Statement::FunctionDeclaration(decl) => {
+                let id = self.node("FunctionDeclaration", Some(&decl.name.value));
+                self.function(id, &decl.function);
+                id
+            }
+        }
+    }
+
+    fn block(&mut self, block: &BlockStatement) -> usize {
+        let id = self.node("BlockStatement", None);
+        for stmt in &block.statements {
+            let child = self.statement(stmt);
+            self.edge(id, child, None);
+        }
+        id
+    }
+
+    // 参数和函数体挂在id下面
+    fn function(&mut self, id: usize, function: &FunctionLiteral) {
+        for param in &function.parameters {
+            let child = self.parameter(param);
+            self.edge(id, child, Some("parameter"));
+        }
+        let body = self.block(&function.body);
+        self.edge(id, body, Some("body"));
+    }
+
+    fn parameter(&mut self, param: &Parameter) -> usize {
+        match param {
+            Parameter::Plain(ident) => self.node("Identifier", Some(&ident.value)),
+            Parameter::Default(param) => {
+                let id = self.node("DefaultParameter", Some(&param.name.value));
+                let default = self.expression(&param.default);
+                self.edge(id, default, Some("default"));
+                id
+            }
+            Parameter::Rest(param) => self.node("RestParameter", Some(&param.name.value)),
+        }
+    }
+
+    fn children(&mut self, id: usize, label: &str, exprs: &[Expression]) {
+        for expr in exprs {
+            let child = self.expression(expr);
+            self.edge(id, child, Some(label));
+        }
+    }
+
+    fn expression(&mut self, expr: &Expression) -> usize {
+        match expr {
+            Expression::Identifier(ident) => self.node("Identifier", Some(&ident.value)),
+            Expression::Integer(int) => self.node("IntegerLiteral", Some(&int.value.to_string())),
+            Expression::String(string) => {
+                // 只加引号，转义统一由quote处理
+                self.node("StringLiteral", Some(&format!("\"{}\"", string.value)))
+            }
+            Expression::Boolean(boolean) => self.node("Boolean", Some(&boolean.value.to_string())),
+            Expression::Prefix(prefix) => {
+                let id = self.node("PrefixExpression", Some(&prefix.operator));
+                let right = self.expression(&prefix.right);
+                self.edge(id, right, None);
+                id
+            }
+            Expression::Infix(infix) => {
+                let id = self.node("InfixExpression", Some(&infix.operator));
+                let left = self.expression(&infix.left);
+                self.edge(id, left, Some("left"));
+                let right = self.expression(&infix.right);
+                self.edge(id, right, Some("right"));
+                id
+            }
+            Expression::If(if_expr) => {
+                let id = self.node("IfExpression", None);
+                let condition = self.expression(&if_expr.condition);
+                self.edge(id, condition, Some("condition"));
+                let consequence = self.block(&if_expr.consequence);
+                self.edge(id, consequence, Some("consequence"));
+                if let Some(alt) = &if_expr.alternative {
+                    let alternative = self.block(alt);
+                    self.edge(id, alternative, Some("alternative"));
+                }
+                id
+            }
+            Expression::Function(function) => {
+                let id = self.node("FunctionLiteral", None);
+                self.function(id, function);
+                id
+            }
+            Expression::Call(call) => {
+                let id = self.node("CallExpression", None);
+                let function = self.expression(&call.function);
+                self.edge(id, function, Some("function"));
+                self.children(id, "argument", &call.arguments);
+                id
+            }
+            Expression::Array(array) => {
+                let id = self.node("ArrayLiteral", None);
+                for element in &array.elements {
+                    let child = self.expression(element);
+                    self.edge(id, child, None);
+                }
+                id
+            }
+            Expression::Hash(hash) => {
+                let id = self.node("HashLiteral", None);
+                for (key, value) in &hash.pairs {
+                    let key = self.expression(key);
+                    self.edge(id, key, Some("key"));
+                    let value = self.expression(value);
+                    self.edge(id, value, Some("value"));
+                }
+                id
+            }
+            Expression::Match(match_expr) => {
+                let id = self.node("MatchExpression", None);
+                let subject = self.expression(&match_expr.subject);
+                self.edge(id, subject, Some("subject"));
+                for arm in &match_expr.arms {
+                    let child = self.match_arm(arm);
+                    self.edge(id, child, None);
+                }
+                id
+            }
+            Expression::Spread(spread) => {
+                let id = self.node("SpreadExpression", None);
+                let value = self.expression(&spread.value);
+                self.edge(id, value, None);
+                id
+            }
+        }
+    }
+
+    // 模式比较短，直接写在分支节点上
+    fn match_arm(&mut self, arm: &MatchArm) -> usize {
+        let id = self.node("MatchArm", Some(&arm.pattern.to_string()));
+        if let Some(guard) = &arm.guard {
+            let guard = self.expression(guard);
+            self.edge(id, guard, Some("guard"));
+        }
+        let body = self.statement(&arm.body);
+        self.edge(id, body, Some("body"));
+        id
+    }
+}
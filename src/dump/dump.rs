@@ -0,0 +1,368 @@
+use crate::ast::{
+    BlockStatement, Expression, FunctionLiteral, MatchArm, Parameter, Program, Statement,
+};
+use crate::dot::dot::program_to_dot;
+use crate::json::schema::{source_to_json, tokens_to_json};
+use crate::lexer::lexer::Lexer;
+use crate::parser::parser::Parser;
+use crate::token::token::TokenType;
+
+const INDENT: &str = "  ";
+
+// 调试前端时输出的内容
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DumpMode {
+    Tokens,
+    TokensJson,
+    Tree, // 缩进的AST树
+    Sexp, // AST的S表达式
+    Json, // 带span的AST，格式见json::schema
+    Dot,  // Graphviz格式的AST
+}
+
+impl DumpMode {
+    // 命令行参数里的名字
+    pub fn from_name(name: &str) -> Option<DumpMode> {
+        match name {
+            "tokens" => Some(DumpMode::Tokens),
+            "tree" => Some(DumpMode::Tree),
+            "sexp" => Some(DumpMode::Sexp),
+            "json" => Some(DumpMode::Json),
+            "dot" => Some(DumpMode::Dot),
+            _ => None,
+        }
+    }
+}
+
+// 按模式输出源码的词法单元或AST，解析失败时返回解析错误
+pub fn dump(input: &str, mode: DumpMode) -> Result<String, Vec<String>> {
+    match mode {
+        DumpMode::Tokens => return Ok(dump_tokens(input)),
+        DumpMode::TokensJson => return Ok(tokens_to_json(input).pretty() + "\n"),
+        DumpMode::Json => return source_to_json(input).map(|json| json.pretty() + "\n"),
+        _ => {}
+    }
+    let mut p = Parser::new(Lexer::new(input.to_string()));
+    let program = p.parse_program();
+    if !p.errors().is_empty() {
+        return Err(p.errors());
+    }
+    Ok(match mode {
+        DumpMode::Tree => dump_tree(&program),
+        DumpMode::Dot => program_to_dot(&program),
+        _ => dump_sexp(&program),
+    })
+}
+
+// 每行一个词法单元：位置、类型和字面量，最后是EOF
+pub fn dump_tokens(input: &str) -> String {
+    let mut l = Lexer::new(input.to_string());
+    let mut out = String::new();
+    loop {
+        let tok = l.next_token();
+        let position = l.token_position().to_string();
+        let token_type = format!("{:?}", tok.token_type);
+        out.push_str(&format!(
+            "{:<8}{:<12}{:?}\n",
+            position, token_type, tok.literal
+        ));
+        if tok.token_type == TokenType::EOF {
+            return out;
+        }
+    }
+}
+
+// 缩进的树，每行一个节点，子节点需要区分时带上字段名
+pub fn dump_tree(program: &Program) -> String {
+    let mut tree = Tree {
+        out: String::new(),
+        level: 0,
+    };
+    tree.line(None, "Program");
+    tree.nested(|t| {
+        for stmt in &program.statements {
+            t.statement(None, stmt);
+        }
+    });
+    tree.out
+}
+
+struct Tree {
+    out: String,
+    level: usize,
+}
+
+impl Tree {
+    fn line(&mut self, label: Option<&str>, text: &str) {
+        for _ in 0..self.level {
+            self.out.push_str(INDENT);
+        }
+        if let Some(label) = label {
+            self.out.push_str(label);
+            self.out.push_str(": ");
+        }
+        self.out.push_str(text);
+        self.out.push('\n');
+    }
+
+    fn nested(&mut self, f: impl FnOnce(&mut Tree)) {
+        self.level += 1;
+        f(self);
+        self.level -= 1;
+    }
+
+    fn statement(&mut self, label: Option<&str>, stmt: &Statement) {
+        match stmt {
+            Statement::Let(let_stmt) => {
+                self.line(label, &format!("Let {}", let_stmt.name.value));
+                self.nested(|t| t.expression(None, &let_stmt.value));
+            }
+            Statement::DestructuringLet(let_stmt) => {
+                self.line(label, &format!("DestructuringLet {}", let_stmt.pattern));
+                self.nested(|t| t.expression(None, &let_stmt.value));
+            }
+            Statement::Return(return_stmt) => {
+                self.line(label, "Return");
+                self.nested(|t| t.expression(None, &return_stmt.return_value));
+            }
+            Statement::Expression(expr_stmt) => {
+                self.line(label, "ExpressionStatement");
+                self.nested(|t| t.expression(None, &expr_stmt.expression));
+            }
+            Statement::Block(block) => self.block(label, block),
+            Statement::FunctionDeclaration(decl) => {
+                self.line(label, &format!("FunctionDeclaration {}", decl.name.value));
+                self.nested(|t| t.function(&decl.function));
+            }
+        }
+    }
+
+    fn block(&mut self, label: Option<&str>, block: &BlockStatement) {
+        self.line(label, "Block");
+        self.nested(|t| {
+            for stmt in &block.statements {
+                t.statement(None, stmt);
+            }
+        });
+    }
+
+    fn function(&mut self, function: &FunctionLiteral) {
+        self.line(None, "parameters:");
+        self.nested(|t| {
+            for param in &function.parameters {
+                t.parameter(param);
+            }
+        });
+        self.block(Some("body"), &function.body);
+    }
+
+    fn parameter(&mut self, param: &Parameter) {
+        match param {
+            Parameter::Plain(ident) => self.line(None, &format!("Identifier {}", ident.value)),
+            Parameter::Default(param) => {
+                self.line(None, &format!("DefaultParameter {}", param.name.value));
+                self.nested(|t| t.expression(None, &param.default));
+            }
+            Parameter::Rest(param) => self.line(None, &format!("Rest {}", param.name.value)),
+        }
+    }
+
+    // 空列表只输出字段名
+    fn list(&mut self, label: &str, exprs: &[Expression]) {
+        self.line(None, &format!("{}:", label));
+        self.nested(|t| {
+            for expr in exprs {
+                t.expression(None, expr);
+            }
+        });
+    }
+
+    fn expression(&mut self, label: Option<&str>, expr: &Expression) {
+        match expr {
+            Expression::Identifier(ident) => {
+                self.line(label, &format!("Identifier {}", ident.value));
+            }
+            Expression::Integer(int) => self.line(label, &format!("Integer {}", int.value)),
+            Expression::String(string) => {
+                self.line(label, &format!("String {:?}", string.value));
+            }
+            Expression::Boolean(boolean) => {
+                self.line(label, &format!("Boolean {}", boolean.value));
+            }
+            Expression::Prefix(prefix) => {
+                self.line(label, &format!("Prefix {}", prefix.operator));
+                self.nested(|t| t.expression(None, &prefix.right));
+            }
+            Expression::Infix(infix) => {
+                self.line(label, &format!("Infix {}", infix.operator));
+                self.nested(|t| {
+                    t.expression(None, &infix.left);
+                    t.expression(None, &infix.right);
+                });
+            }
+            Expression::If(if_expr) => {
+                self.line(label, "If");
+                self.nested(|t| {
+                    t.expression(Some("condition"), &if_expr.condition);
+                    t.block(Some("consequence"), &if_expr.consequence);
+                    if let Some(alt) = &if_expr.alternative {
+                        t.block(Some("alternative"), alt);
+                    }
+                });
+            }
+            Expression::Function(function) => {
+                self.line(label, "Function");
+                self.nested(|t| t.function(function));
+            }
+            Expression::Call(call) => {
+                self.line(label, "Call");
+                self.nested(|t| {
+                    t.expression(Some("function"), &call.function);
+                    t.list("arguments", &call.arguments);
+                });
+            }
+            Expression::Array(array) => {
+                self.line(label, "Array");
+                self.nested(|t| {
+                    for element in &array.elements {
+                        t.expression(None, element);
+                    }
+                });
+            }
+            Expression::Hash(hash) => {
+                self.line(label, "Hash");
+                self.nested(|t| {
+                    for (key, value) in &hash.pairs {
+                        t.expression(Some("key"), key);
+                        t.expression(Some("value"), value);
+                    }
+                });
+            }
+            Expression::Match(match_expr) => {
+                self.line(label, "Match");
+                self.nested(|t| {
+                    t.expression(Some("subject"), &match_expr.subject);
+                    for arm in &match_expr.arms {
+                        t.match_arm(arm);
+                    }
+                });
+            }
+            Expression::Spread(spread) => {
+                self.line(label, "Spread");
+                self.nested(|t| t.expression(None, &spread.value));
+            }
+        }
+    }
+
+    fn match_arm(&mut self, arm: &MatchArm) {
+        self.line(None, &format!("Arm {}", arm.pattern));
+        self.nested(|t| {
+            if let Some(guard) = &arm.guard {
+                t.expression(Some("guard"), guard);
+            }
+            t.statement(Some("body"), &arm.body);
+        });
+    }
+}
+
+// 每条顶层语句一行S表达式，表达式语句直接写成表达式
+pub fn dump_sexp(program: &Program) -> String {
+    program
+        .statements
+        .iter()
+        .map(|stmt| sexp_statement(stmt) + "\n")
+        .collect()
+}
+
+fn sexp_statement(stmt: &Statement) -> String {
+    match stmt {
+        Statement::Let(let_stmt) => {
+            format!("(let {} {})", let_stmt.name.value, sexp(&let_stmt.value))
+        }
+        Statement::DestructuringLet(let_stmt) => {
+            format!("(let {} {})", let_stmt.pattern, sexp(&let_stmt.value))
+        }
+        Statement::Return(return_stmt) => format!("(return {})", sexp(&return_stmt.return_value)),
+        Statement::Expression(expr_stmt) => sexp(&expr_stmt.expression),
+        Statement::Block(block) => sexp_block(block),
+        Statement::FunctionDeclaration(decl) => {
+            format!("(fn {} {})", decl.name.value, sexp_function(&decl.function))
+        }
+    }
+}
+
+fn sexp_block(block: &BlockStatement) -> String {
+    let statements: Vec<String> = block.statements.iter().map(sexp_statement).collect();
+    form("block", &statements)
+}
+
+fn sexp_function(function: &FunctionLiteral) -> String {
+    let parameters: Vec<String> = function.parameters.iter().map(sexp_parameter).collect();
+    format!("({}) {}", parameters.join(" "), sexp_block(&function.body))
+}
+
+fn sexp_parameter(param: &Parameter) -> String {
+    match param {
+        Parameter::Plain(ident) => ident.value.clone(),
+        Parameter::Default(param) => form("=", &[param.name.value.clone(), sexp(&param.default)]),
+        Parameter::Rest(param) => format!("(... {})", param.name.value),
+    }
+}
+
+// (head a b ...)，没有参数时是(head)
+fn form(head: &str, items: &[String]) -> String {
+    if items.is_empty() {
+        format!("({})", head)
+    } else {
+        format!("({} {})", head, items.join(" "))
+    }
+}
+
+fn sexp(expr: &Expression) -> String {
+    match expr {
+        Expression::Identifier(ident) => ident.value.clone(),
+        Expression::Integer(int) => int.value.to_string(),
+        Expression::String(string) => format!("{:?}", string.value),
+        Expression::Boolean(boolean) => boolean.value.to_string(),
+        Expression::Prefix(prefix) => form(&prefix.operator, &[sexp(&prefix.right)]),
+        Expression::Infix(infix) => form(&infix.operator, &[sexp(&infix.left), sexp(&infix.right)]),
+        Expression::If(if_expr) => {
+            let mut items = vec![sexp(&if_expr.condition), sexp_block(&if_expr.consequence)];
+            if let Some(alt) = &if_expr.alternative {
+                items.push(sexp_block(alt));
+            }
+            form("if", &items)
+        }
+        Expression::Function(function) => format!("(fn {})", sexp_function(function)),
+        Expression::Call(call) => {
+            let mut items = vec![sexp(&call.function)];
+            items.extend(call.arguments.iter().map(sexp));
+            form("call", &items)
+        }
+        Expression::Array(array) => {
+            let elements: Vec<String> = array.elements.iter().map(sexp).collect();
+            form("array", &elements)
+        }
+        Expression::Hash(hash) => {
+            let pairs: Vec<String> = hash
+                .pairs
+                .iter()
+                .map(|(key, value)| format!("({} {})", sexp(key), sexp(value)))
+                .collect();
+            form("hash", &pairs)
+        }
+        Expression::Match(match_expr) => {
+            let mut items = vec![sexp(&match_expr.subject)];
+            for arm in &match_expr.arms {
+                let mut arm_items = vec![arm.pattern.to_string()];
+                if let Some(guard) = &arm.guard {
+                    arm_items.push(form("if", &[sexp(guard)]));
+                }
+                arm_items.push(sexp_statement(&arm.body));
+                items.push(form("arm", &arm_items));
+            }
+            form("match", &items)
+        }
+        Expression::Spread(spread) => form("...", &[sexp(&spread.value)]),
+    }
+}
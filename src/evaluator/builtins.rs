@@ -0,0 +1,44 @@
+use crate::evaluator::context::EvalContext;
+use crate::evaluator::evaluator::{new_array, new_error};
+use crate::object::builtin::Builtin;
+use crate::object::{ObjectType, Value};
+
+// 执行内置函数，新建的数组照常计入分配预算
+pub(super) fn call_builtin(builtin: Builtin, args: Vec<Value>, ctx: &mut EvalContext) -> Value {
+    if args.len() != builtin.arity() {
+        return new_error(format!(
+            "wrong number of arguments to {}: want={}, got={}",
+            builtin.name(),
+            builtin.arity(),
+            args.len()
+        ));
+    }
+
+    let Value::Array(xs) = &args[0] else {
+        return expected(builtin, ObjectType::Array, &args[0]);
+    };
+    match builtin {
+        Builtin::Len => Value::Integer(xs.elements.len() as i64),
+        Builtin::Slice => {
+            let (start, end) = match (&args[1], &args[2]) {
+                (Value::Integer(start), Value::Integer(end)) => (*start, *end),
+                (Value::Integer(_), other) | (other, _) => {
+                    return expected(builtin, ObjectType::Integer, other);
+                }
+            };
+            // 下标截到数组两端，end不大于start时得到空数组
+            let clamp = |i: i64| i.clamp(0, xs.elements.len() as i64) as usize;
+            let (start, end) = (clamp(start), clamp(end));
+            new_array(xs.elements[start..end.max(start)].to_vec(), ctx)
+        }
+    }
+}
+
+fn expected(builtin: Builtin, want: ObjectType, got: &Value) -> Value {
+    new_error(format!(
+        "{}: expected {}, got {}",
+        builtin.name(),
+        want,
+        got.type_obj()
+    ))
+}
@@ -0,0 +1,102 @@
+use std::collections::HashMap;
+
+use crate::object::Function;
+use crate::object::builtin::Builtin;
+
+// 顶层代码在调用图中的名字
+pub const MAIN: &str = "<main>";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CallEdge {
+    pub caller: String,
+    pub callee: String,
+    pub count: u64,
+}
+
+// 求值时记录的动态调用图。函数按名字区分，匿名函数用参数列表作名字，
+// 所以参数相同的匿名函数会合并成一个节点
+#[derive(Debug, Default)]
+pub struct CallGraph {
+    stack: Vec<String>,   // 正在执行的函数，空时在顶层
+    edges: Vec<CallEdge>, // 按第一次调用的顺序
+    index: HashMap<(String, String), usize>,
+}
+
+impl CallGraph {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn edges(&self) -> &[CallEdge] {
+        &self.edges
+    }
+
+    // 出现过的函数，MAIN在最前，其余按第一次被调用的顺序
+    pub fn functions(&self) -> Vec<&str> {
+        let mut functions = vec![MAIN];
+        for edge in &self.edges {
+            if !functions.contains(&edge.callee.as_str()) {
+                functions.push(&edge.callee);
+            }
+        }
+        functions
+    }
+
+    // 函数被调用的总次数
+    pub fn calls(&self, function: &str) -> u64 {
+        self.edges
+            .iter()
+            .filter(|edge| edge.callee == function)
+            .map(|edge| edge.count)
+            .sum()
+    }
+
+    // 进入函数。尾调用复用调用者的栈帧，调用者就是被替换的函数
+    pub(crate) fn call(&mut self, function: &Function, tail: bool) {
+        self.enter(label(function), tail);
+    }
+
+    // 进入内置函数，用函数名作名字
+    pub(crate) fn call_builtin(&mut self, builtin: Builtin, tail: bool) {
+        self.enter(builtin.name().to_string(), tail);
+    }
+
+    fn enter(&mut self, callee: String, tail: bool) {
+        let caller = if tail {
+            self.stack.pop()
+        } else {
+            self.stack.last().cloned()
+        }
+        .unwrap_or_else(|| MAIN.to_string());
+
+        let key = (caller, callee.clone());
+        match self.index.get(&key) {
+            Some(&i) => self.edges[i].count += 1,
+            None => {
+                self.index.insert(key.clone(), self.edges.len());
+                self.edges.push(CallEdge {
+                    caller: key.0,
+                    callee: key.1,
+                    count: 1,
+                });
+            }
+        }
+        self.stack.push(callee);
+    }
+
+    pub(crate) fn depth(&self) -> usize {
+        self.stack.len()
+    }
+
+    // 函数返回（包括出错）时回到调用前的深度
+    pub(crate) fn truncate(&mut self, depth: usize) {
+        self.stack.truncate(depth);
+    }
+}
+
+fn label(function: &Function) -> String {
+    match &function.name {
+        Some(name) => name.clone(),
+        None => function.signature(),
+    }
+}
@@ -0,0 +1,263 @@
+// 求值上下文：记录递归深度、执行步数、截止时间、取消标志与内存用量等运行时状态
+use std::mem::size_of;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+use crate::evaluator::call_graph::CallGraph;
+use crate::evaluator::trace::{TraceNode, TraceSink};
+use crate::object::function::Parameter;
+use crate::object::hash::{HashKey, HashPair};
+use crate::object::{Array, Function, Hash, Value};
+
+// 命令行和 REPL 默认最多嵌套的函数调用数，尾调用不计入。
+// 需要在栈大小为 stack_size_for(DEFAULT_MAX_DEPTH) 的线程上求值，见 run_with_stack
+pub const DEFAULT_MAX_DEPTH: usize = 1000;
+
+// 命令行允许的最大 --max-depth，对应约 2.4GB 的线程栈
+pub const MAX_DEPTH_LIMIT: usize = 100_000;
+
+// 每层调用预留的栈空间。debug 构建中函数体嵌套几层表达式时每层约用 16KB
+const STACK_PER_CALL: usize = 24 * 1024;
+
+// std::thread 新线程的默认栈大小，测试线程也使用它
+pub const DEFAULT_STACK_SIZE: usize = 2 * 1024 * 1024;
+
+// 每隔多少步检查一次截止时间，避免每个节点都读取时钟
+const DEADLINE_CHECK_INTERVAL: u64 = 1024;
+
+// 求值结束后提供给宿主的内存统计
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MemoryStats {
+    // 累计分配给 Monkey 对象的字节数（估算值）
+    pub allocated_bytes: usize,
+    // 累计分配的对象个数
+    pub allocations: usize,
+    pub budget: Option<usize>,
+}
+
+pub struct EvalContext {
+    depth: usize,
+    steps: u64,
+    allocated_bytes: usize,
+    allocations: usize,
+    // 最多嵌套的函数调用数，超过后返回 Error 而不是让宿主栈溢出。
+    // 求值需要的栈随它增长，见 stack_size 和 run_with_stack。
+    // new() 的默认值在 DEFAULT_STACK_SIZE 的线程栈上是安全的
+    pub max_depth: usize,
+    // 最多求值的节点数，None 表示不限制
+    pub max_steps: Option<u64>,
+    // 截止时间，None 表示不限制
+    pub deadline: Option<Instant>,
+    // 共享的取消标志，其他线程或信号处理函数置为 true 后中止求值
+    pub interrupt: Option<Arc<AtomicBool>>,
+    // 分配预算：累计分配的字节数上限，None 表示不限制。
+    // 释放的对象不会退还预算，因此它限制的是总分配量而不是同时存活的内存
+    pub allocation_budget: Option<usize>,
+    // 开启后记录函数调用
+    call_graph: Option<CallGraph>,
+    // 求值过程的输出，默认不输出
+    trace: Option<Box<dyn TraceSink>>,
+}
+
+impl EvalContext {
+    pub fn new() -> Self {
+        EvalContext {
+            depth: 0,
+            steps: 0,
+            allocated_bytes: 0,
+            allocations: 0,
+            max_depth: max_depth_for(DEFAULT_STACK_SIZE),
+            max_steps: None,
+            deadline: None,
+            interrupt: None,
+            allocation_budget: None,
+            call_graph: None,
+            trace: None,
+        }
+    }
+
+    pub fn with_max_depth(max_depth: usize) -> Self {
+        EvalContext {
+            max_depth,
+            ..Self::new()
+        }
+    }
+
+    pub fn set_max_depth(&mut self, max_depth: usize) {
+        self.max_depth = max_depth;
+    }
+
+    // 在 max_depth 层调用内不会栈溢出所需的线程栈大小
+    pub fn stack_size(&self) -> usize {
+        stack_size_for(self.max_depth)
+    }
+
+    pub fn set_max_steps(&mut self, max_steps: u64) {
+        self.max_steps = Some(max_steps);
+    }
+
+    // 从现在开始计时，超过 timeout 后中止求值
+    pub fn set_timeout(&mut self, timeout: Duration) {
+        self.deadline = Some(Instant::now() + timeout);
+    }
+
+    pub fn set_interrupt_flag(&mut self, flag: Arc<AtomicBool>) {
+        self.interrupt = Some(flag);
+    }
+
+    pub fn set_allocation_budget(&mut self, allocation_budget: usize) {
+        self.allocation_budget = Some(allocation_budget);
+    }
+
+    // 从现在开始记录动态调用图
+    pub fn enable_call_graph(&mut self) {
+        self.call_graph = Some(CallGraph::new());
+    }
+
+    pub fn call_graph(&self) -> Option<&CallGraph> {
+        self.call_graph.as_ref()
+    }
+
+    pub(crate) fn call_graph_mut(&mut self) -> Option<&mut CallGraph> {
+        self.call_graph.as_mut()
+    }
+
+    pub fn set_trace_sink(&mut self, sink: Box<dyn TraceSink>) {
+        self.trace = Some(sink);
+    }
+
+    pub(crate) fn trace_enter(&mut self, node: TraceNode) {
+        if let Some(trace) = &mut self.trace {
+            trace.enter(node);
+        }
+    }
+
+    pub(crate) fn trace_leave(&mut self, node: TraceNode, result: &Value) {
+        if let Some(trace) = &mut self.trace {
+            trace.leave(node, result);
+        }
+    }
+
+    pub fn memory_stats(&self) -> MemoryStats {
+        MemoryStats {
+            allocated_bytes: self.allocated_bytes,
+            allocations: self.allocations,
+            budget: self.allocation_budget,
+        }
+    }
+
+    // 从分配预算中扣除一次分配，超过预算时返回错误信息。对象释放时不退还
+    pub(crate) fn charge(&mut self, bytes: usize) -> Result<(), String> {
+        self.allocated_bytes += bytes;
+        self.allocations += 1;
+        self.check_budget()
+    }
+
+    fn check_budget(&self) -> Result<(), String> {
+        match self.allocation_budget {
+            Some(budget) if self.allocated_bytes > budget => {
+                Err(format!("allocation budget of {} bytes exceeded", budget))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    // 已经求值的节点数
+    pub fn steps(&self) -> u64 {
+        self.steps
+    }
+
+    // 进入一层函数调用，超过 max_depth 时返回错误信息
+    pub(crate) fn enter_call(&mut self) -> Result<(), String> {
+        if self.depth >= self.max_depth {
+            return Err("maximum recursion depth exceeded".to_string());
+        }
+        self.depth += 1;
+        Ok(())
+    }
+
+    pub(crate) fn leave_call(&mut self) {
+        self.depth -= 1;
+    }
+
+    // 求值一个节点前计一步，超过任一限制或被取消时返回对应的错误信息
+    pub(crate) fn enter(&mut self) -> Result<(), String> {
+        if let Some(max_steps) = self.max_steps
+            && self.steps >= max_steps
+        {
+            return Err(format!("step budget of {} exceeded", max_steps));
+        }
+        self.check_budget()?;
+        if let Some(flag) = &self.interrupt
+            && flag.load(Ordering::Relaxed)
+        {
+            return Err("interrupted".to_string());
+        }
+        if let Some(deadline) = self.deadline
+            && self.steps.is_multiple_of(DEADLINE_CHECK_INTERVAL)
+            && Instant::now() >= deadline
+        {
+            return Err("evaluation timed out".to_string());
+        }
+        self.steps += 1;
+        Ok(())
+    }
+}
+
+impl Default for EvalContext {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// 支持 max_depth 层调用所需的线程栈大小，包括宿主自身使用的部分
+pub fn stack_size_for(max_depth: usize) -> usize {
+    max_depth.saturating_add(16).saturating_mul(STACK_PER_CALL)
+}
+
+// stack_size_for 的反函数：在栈大小为 stack_size 的线程上可以安全嵌套的调用数
+pub fn max_depth_for(stack_size: usize) -> usize {
+    (stack_size / STACK_PER_CALL).saturating_sub(16)
+}
+
+// 在栈大小为 stack_size 的新线程上运行 f，用于比主线程栈允许的更深的递归。
+// 环境不能跨线程传递，因此应当在 f 内部创建环境并求值。
+// 系统无法分配这么大的栈时返回 Err
+pub fn run_with_stack<T, F>(stack_size: usize, f: F) -> std::io::Result<T>
+where
+    T: Send + 'static,
+    F: FnOnce() -> T + Send + 'static,
+{
+    let handle = std::thread::Builder::new()
+        .stack_size(stack_size)
+        .spawn(f)?;
+    match handle.join() {
+        Ok(value) => Ok(value),
+        Err(panic) => std::panic::resume_unwind(panic),
+    }
+}
+
+// 估算值新分配的堆内存字节数。整数、布尔值和null不在堆上；
+// 数组和哈希的元素是共享的Rc，已经单独计算过，这里只计算容器本身
+pub fn object_size(val: &Value) -> usize {
+    // Rc的强弱引用计数
+    const RC_HEADER: usize = 2 * size_of::<usize>();
+    match val {
+        Value::String(string) => RC_HEADER + string.len(),
+        Value::Array(array) => {
+            RC_HEADER + size_of::<Array>() + array.elements.capacity() * size_of::<Value>()
+        }
+        Value::Hash(hash) => {
+            RC_HEADER
+                + size_of::<Hash>()
+                + hash.pairs.len() * (size_of::<HashKey>() + size_of::<HashPair>())
+        }
+        Value::Function(function) => {
+            RC_HEADER
+                + size_of::<Function>()
+                + function.parameters.capacity() * size_of::<Parameter>()
+        }
+        _ => 0,
+    }
+}
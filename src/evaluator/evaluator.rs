@@ -1,10 +1,17 @@
+use std::collections::BTreeMap;
+use std::rc::Rc;
+
 use crate::ast::{
-    BlockStatement, Boolean, ExpressionStatement, Identifier, IfExpression, InfixExpression,
-    IntegerLiteral, LetStatement, Node, NodeType, PrefixExpression, Program, ReturnStatement,
+    self, ArrayPattern, BlockStatement, CallExpression, Expression, FunctionLiteral, HashLiteral,
+    HashPattern, Identifier, IfExpression, MatchArm, MatchExpression, Pattern, Program, Statement,
 };
+use crate::evaluator::builtins::call_builtin;
+use crate::evaluator::context::{EvalContext, object_size};
+use crate::evaluator::trace::TraceNode;
 use crate::object::environment::Environment;
-use crate::object::integer::Integer;
-use crate::object::{self, Object, ReturnValue};
+use crate::object::function::Parameter;
+use crate::object::hash::{HashKey, HashPair};
+use crate::object::{Array, Function, Hash, ObjectType, TailCall, Value};
 
 // pub fn eval(node: &dyn Node) -> Box<dyn Object> {
 //     // 先尝试转换为 Program
@@ -71,133 +78,138 @@ use crate::object::{self, Object, ReturnValue};
 //     for statement in &block.statements {
 //         result = eval_node_type(statement);
 //     }
-use std::sync::OnceLock;
 
-// 使用OnceLock来创建单例对象
-static TRUE: OnceLock<object::Boolean> = OnceLock::new();
-static FALSE: OnceLock<object::Boolean> = OnceLock::new();
-static NULL: OnceLock<object::null::Null> = OnceLock::new();
+pub fn eval(program: &Program, env: &mut Environment) -> Value {
+    eval_with_context(program, env, &mut EvalContext::new())
+}
 
-fn native_bool_to_boolean_object(input: bool) -> Box<dyn Object> {
-    if input {
-        let boolean = TRUE.get_or_init(|| object::Boolean::new(true));
-        Box::new(boolean.clone())
-    } else {
-        let boolean = FALSE.get_or_init(|| object::Boolean::new(false));
-        Box::new(boolean.clone())
+// 带上下文求值，超过递归深度、步数预算或截止时间时返回 Error
+pub fn eval_with_context(program: &Program, env: &mut Environment, ctx: &mut EvalContext) -> Value {
+    if let Err(message) = ctx.enter() {
+        return new_error(message);
     }
+    eval_program(&program.statements, env, ctx)
 }
 
-pub fn get_null_object() -> Box<dyn Object> {
-    Box::new(NULL.get_or_init(|| object::null::Null {}).clone())
+fn eval_statement(stmt: &Statement, env: &mut Environment, ctx: &mut EvalContext) -> Value {
+    if let Err(message) = ctx.enter() {
+        return new_error(message);
+    }
+    ctx.trace_enter(TraceNode::Statement(stmt));
+    let result = eval_statement_inner(stmt, env, ctx);
+    ctx.trace_leave(TraceNode::Statement(stmt), &result);
+    result
 }
 
-pub fn eval(node: &dyn Node, env: &mut Environment) -> Box<dyn Object> {
-    // 处理Program
-    if let Some(program) = node.as_any().downcast_ref::<Program>() {
-        return eval_program(&program.statements, env);
+fn eval_expression(expr: &Expression, env: &mut Environment, ctx: &mut EvalContext) -> Value {
+    if let Err(message) = ctx.enter() {
+        return new_error(message);
     }
+    ctx.trace_enter(TraceNode::Expression(expr));
+    let result = eval_expression_inner(expr, env, ctx);
+    ctx.trace_leave(TraceNode::Expression(expr), &result);
+    result
+}
 
-    // 处理NodeType
-    if let Some(node_type) = node.as_any().downcast_ref::<NodeType>() {
-        match node_type {
-            // 处理语句
-            NodeType::Statement(stmt) => {
-                // 处理表达式语句
-                if let Some(expr_stmt) = stmt.as_any().downcast_ref::<ExpressionStatement>() {
-                    return eval(expr_stmt.expression.as_ref(), env);
-                }
-                // 其他语句类型...
-                if let Some(block) = stmt.as_any().downcast_ref::<BlockStatement>() {
-                    println!("eval block");
-                    return eval_block_statement(&block, env);
-                }
-                if let Some(return_stmt) = stmt.as_any().downcast_ref::<ReturnStatement>() {
-                    println!("eval return statement");
-                    let val = eval(return_stmt.return_value.as_ref(), env);
-                    if is_error(&val) {
-                        return val;
-                    }
-                    return Box::new(ReturnValue::new(val));
-                }
-                if let Some(let_stmt) = stmt.as_any().downcast_ref::<LetStatement>() {
-                    println!("eval let statement");
+// 运行时新建的字符串、数组、哈希和函数都经过这里，按估算的大小计入内存用量，
+// 超过内存上限时丢弃结果返回 Error
+fn alloc(value: Value, ctx: &mut EvalContext) -> Value {
+    match ctx.charge(object_size(&value)) {
+        Ok(()) => value,
+        Err(message) => new_error(message),
+    }
+}
 
-                    let val = eval(let_stmt.value.as_ref(), env);
-                    if is_error(&val) {
-                        return val;
-                    }
+pub(super) fn new_array(elements: Vec<Value>, ctx: &mut EvalContext) -> Value {
+    alloc(Value::Array(Rc::new(Array::new(elements))), ctx)
+}
 
-                    // let cloned_obj = val.clone_object();
-                    env.set(&let_stmt.name.value, val);
-                    // env.set2(&let_stmt.name.value, val2);
-                }
+fn eval_statement_inner(stmt: &Statement, env: &mut Environment, ctx: &mut EvalContext) -> Value {
+    match stmt {
+        // 处理表达式语句
+        Statement::Expression(expr_stmt) => eval_expression(&expr_stmt.expression, env, ctx),
+        Statement::Block(block) => eval_block_statement(block, env, ctx),
+        Statement::Return(return_stmt) => {
+            let val = eval_expression(&return_stmt.return_value, env, ctx);
+            if is_error(&val) {
+                return val;
             }
-            // 处理表达式
-            NodeType::Expression(expr) => {
-                // 处理整数字面量
-                if let Some(int_lit) = expr.as_any().downcast_ref::<IntegerLiteral>() {
-                    println!("INTEGER!!!!!!!!!!!!!!!!!!!");
-                    return Box::new(Integer::new(int_lit.value));
-                }
-                // Boolean
-                if let Some(bool_expr) = expr.as_any().downcast_ref::<Boolean>() {
-                    println!("Boolean!!!!!!!!!!!!!!!!!!!");
-                    return native_bool_to_boolean_object(bool_expr.value);
-                }
-                if let Some(prefix_epxr) = expr.as_any().downcast_ref::<PrefixExpression>() {
-                    println!("Prefix!!!!!!!!!!!!!!!!!!!!");
-                    let right = eval(prefix_epxr.right.as_ref(), env);
-                    if is_error(&right) {
-                        return right;
-                    }
-                    return eval_prefix_expression(&prefix_epxr.operator, right);
-                }
-                if let Some(infix_expr) = expr.as_any().downcast_ref::<InfixExpression>() {
-                    println!("Infix!!!!!!!!!!!!!!!!!!!!");
-                    let left = eval(infix_expr.left.as_ref(), env);
-                    if is_error(&left) {
-                        return left;
-                    }
-                    let right = eval(infix_expr.right.as_ref(), env);
-                    if is_error(&right) {
-                        return right;
-                    }
-                    return eval_infix_expression(&infix_expr.operator, left, right);
-                }
-                if let Some(if_expr) = expr.as_any().downcast_ref::<IfExpression>() {
-                    println!("eval if expr");
-                    return eval_if_expression(if_expr, env);
-                }
-                if let Some(identifier) = expr.as_any().downcast_ref::<Identifier>() {
-                    println!("Identifier: {}", identifier.value);
-                    return eval_identifier(identifier, env);
-                }
+            Value::ReturnValue(Box::new(val))
+        }
+        Statement::Let(let_stmt) => {
+            let val = eval_expression(&let_stmt.value, env, ctx);
+            if is_error(&val) {
+                return val;
+            }
+
+            env.set(&let_stmt.name.value, val);
+            Value::Null
+        }
+        // 具名函数在所在块开始求值前已经绑定，这里无需处理
+        Statement::FunctionDeclaration(_) => Value::Null,
+        Statement::DestructuringLet(let_stmt) => {
+            let val = eval_expression(&let_stmt.value, env, ctx);
+            if is_error(&val) {
+                return val;
             }
+            if let Some(err) = bind_pattern(&let_stmt.pattern, val, env, ctx) {
+                return err;
+            }
+            Value::Null
         }
     }
+}
 
-    // 如果没有匹配的类型，返回Null
-    // Box::new(Null {})
-    println!("GET NULL");
-    get_null_object()
+fn eval_expression_inner(expr: &Expression, env: &mut Environment, ctx: &mut EvalContext) -> Value {
+    match expr {
+        // 处理整数字面量
+        Expression::Integer(int_lit) => Value::Integer(int_lit.value),
+        Expression::Boolean(bool_expr) => Value::Boolean(bool_expr.value),
+        Expression::Prefix(prefix_epxr) => {
+            let right = eval_expression(&prefix_epxr.right, env, ctx);
+            if is_error(&right) {
+                return right;
+            }
+            eval_prefix_expression(&prefix_epxr.operator, right)
+        }
+        Expression::Infix(infix_expr) => {
+            let left = eval_expression(&infix_expr.left, env, ctx);
+            if is_error(&left) {
+                return left;
+            }
+            let right = eval_expression(&infix_expr.right, env, ctx);
+            if is_error(&right) {
+                return right;
+            }
+            eval_infix_expression(&infix_expr.operator, left, right)
+        }
+        Expression::If(if_expr) => eval_if_expression(if_expr, env, ctx),
+        Expression::String(str_lit) => alloc(Value::String(str_lit.value.as_str().into()), ctx),
+        Expression::Array(array) => match eval_expressions(&array.elements, env, ctx) {
+            Ok(elements) => new_array(elements, ctx),
+            Err(err) => err,
+        },
+        Expression::Hash(hash) => eval_hash_literal(hash, env, ctx),
+        Expression::Function(function) => alloc(new_function(None, function, env), ctx),
+        Expression::Call(call) => eval_call_expression(call, env, ctx),
+        Expression::Spread(_) => new_error("spread is only allowed in call arguments".to_string()),
+        Expression::Match(match_expr) => eval_match_expression(match_expr, env, ctx),
+        Expression::Identifier(identifier) => eval_identifier(identifier, env),
+    }
 }
 
-fn eval_program(statements: &[NodeType], env: &mut Environment) -> Box<dyn Object> {
-    let mut result = get_null_object();
+fn eval_program(statements: &[Statement], env: &mut Environment, ctx: &mut EvalContext) -> Value {
+    let mut result = Value::Null;
+    hoist_function_declarations(statements, env);
 
     for statement in statements {
-        result = eval(statement, env);
+        result = eval_statement(statement, env, ctx);
 
-        match result.type_obj().as_str() {
+        match result {
             // 如果是返回值，解包并返回内部值
-            "RETURN_VALUE" => {
-                if let Some(return_value) = result.as_any().downcast_ref::<ReturnValue>() {
-                    return return_value.take_value();
-                }
-            }
+            Value::ReturnValue(value) => return *value,
             // 如果是错误，直接返回错误对象
-            "ERROR" => {
+            Value::Error(_) => {
                 // 错误对象无需解包，直接返回
                 return result;
             }
@@ -209,14 +221,19 @@ fn eval_program(statements: &[NodeType], env: &mut Environment) -> Box<dyn Objec
     result
 }
 
-fn eval_block_statement(block: &BlockStatement, env: &mut Environment) -> Box<dyn Object> {
-    let mut result = get_null_object();
+fn eval_block_statement(
+    block: &BlockStatement,
+    env: &mut Environment,
+    ctx: &mut EvalContext,
+) -> Value {
+    let mut result = Value::Null;
+    hoist_function_declarations(&block.statements, env);
 
     for statement in &block.statements {
-        result = eval(statement, env);
+        result = eval_statement(statement, env, ctx);
 
         // 块语句中遇到返回值，不解包而是直接返回
-        if result.type_obj() == "RETURN_VALUE" || result.type_obj() == "ERROR" {
+        if matches!(result, Value::ReturnValue(_) | Value::Error(_)) {
             return result;
         }
     }
@@ -224,27 +241,596 @@ fn eval_block_statement(block: &BlockStatement, env: &mut Environment) -> Box<dy
     result
 }
 
-fn eval_identifier(node: &Identifier, env: &mut Environment) -> Box<dyn Object> {
-    if let Some(val) = env.get(&node.value) {
-        if let Some(int) = val.as_any().downcast_ref::<Integer>() {
-            println!("{} 的值是整数: {}", node.value, int.value);
-            Box::new(Integer::new(int.value))
-        } else if let Some(bool_val) = val.as_any().downcast_ref::<object::Boolean>() {
-            println!("{} 的值是布尔值: {}", node.value, bool_val.value);
-            native_bool_to_boolean_object(bool_val.value)
-        } else {
-            val.clone_object() // 通用clone方法，但是无法得到具体类型
+// 先把块中所有具名函数绑定到环境，函数体捕获的是同一个环境，
+// 所以声明顺序不影响直接递归和相互递归
+fn hoist_function_declarations(statements: &[Statement], env: &mut Environment) {
+    for statement in statements {
+        if let Statement::FunctionDeclaration(decl) = statement {
+            let function = new_function(Some(decl.name.value.clone()), &decl.function, env);
+            env.set(&decl.name.value, function);
+        }
+    }
+}
+
+fn new_function(name: Option<String>, literal: &FunctionLiteral, env: &Environment) -> Value {
+    let mut parameters = Vec::new();
+    let mut rest = None;
+    for param in &literal.parameters {
+        match param {
+            ast::Parameter::Plain(ident) => parameters.push(Parameter {
+                name: ident.clone(),
+                default: None,
+            }),
+            ast::Parameter::Default(param) => parameters.push(Parameter {
+                name: param.name.clone(),
+                default: Some(Rc::new(param.default.as_ref().clone())),
+            }),
+            ast::Parameter::Rest(param) => rest = Some(param.name.clone()),
+        }
+    }
+
+    Value::Function(Rc::new(Function {
+        name,
+        parameters,
+        rest,
+        body: Rc::new(literal.body.clone()),
+        env: env.capture(),
+    }))
+}
+
+fn eval_call_expression(
+    call: &CallExpression,
+    env: &mut Environment,
+    ctx: &mut EvalContext,
+) -> Value {
+    let function = eval_expression(&call.function, env, ctx);
+    if is_error(&function) {
+        return function;
+    }
+
+    match eval_call_arguments(&call.arguments, env, ctx) {
+        Ok(args) => apply_function(function, args, ctx),
+        Err(err) => err,
+    }
+}
+
+// 求值调用参数，...<数组> 会被展开成多个参数
+fn eval_call_arguments(
+    exps: &[Expression],
+    env: &mut Environment,
+    ctx: &mut EvalContext,
+) -> Result<Vec<Value>, Value> {
+    let mut args = Vec::with_capacity(exps.len());
+    for exp in exps {
+        if let Expression::Spread(spread) = exp {
+            let evaluated = eval_expression(&spread.value, env, ctx);
+            if is_error(&evaluated) {
+                return Err(evaluated);
+            }
+            let Value::Array(array) = &evaluated else {
+                return Err(new_error(format!(
+                    "cannot spread {}: expected {}",
+                    evaluated.type_obj(),
+                    ObjectType::Array
+                )));
+            };
+            // 展开的参数复制到新的参数列表中
+            if let Err(message) = ctx.charge(array.elements.len() * size_of::<Value>()) {
+                return Err(new_error(message));
+            }
+            args.extend(array.elements.iter().cloned());
+            continue;
+        }
+
+        let evaluated = eval_expression(exp, env, ctx);
+        if is_error(&evaluated) {
+            return Err(evaluated);
+        }
+        args.push(evaluated);
+    }
+    Ok(args)
+}
+
+// 计入调用深度。记录调用图时，函数返回后回到调用前的栈深度
+fn apply_function(func: Value, args: Vec<Value>, ctx: &mut EvalContext) -> Value {
+    if let Err(message) = ctx.enter_call() {
+        return new_error(message);
+    }
+    let depth = ctx.call_graph_mut().map(|graph| graph.depth());
+    let result = call_function(func, args, ctx);
+    ctx.leave_call();
+    if let (Some(depth), Some(graph)) = (depth, ctx.call_graph_mut()) {
+        graph.truncate(depth);
+    }
+    result
+}
+
+// 尾调用消除：函数体中处于尾位置的调用不会递归地求值，而是返回TailCall，
+// 由这里的循环继续执行，因此尾递归不会增长Rust调用栈
+fn call_function(func: Value, args: Vec<Value>, ctx: &mut EvalContext) -> Value {
+    let mut func = func;
+    let mut args = args;
+    let mut tail = false;
+
+    loop {
+        let function = match &func {
+            Value::Function(function) => function,
+            Value::Builtin(builtin) => {
+                if let Some(graph) = ctx.call_graph_mut() {
+                    graph.call_builtin(*builtin, tail);
+                }
+                return call_builtin(*builtin, args, ctx);
+            }
+            _ => return new_error(format!("not a function: {}", func.type_obj())),
+        };
+        if let Some(graph) = ctx.call_graph_mut() {
+            graph.call(function, tail);
+        }
+        tail = true;
+
+        let too_many = function.max_arity().is_some_and(|max| args.len() > max);
+        if args.len() < function.min_arity() || too_many {
+            let target = match &function.name {
+                Some(name) => format!(" to {}", name),
+                None => String::new(),
+            };
+            return new_error(format!(
+                "wrong number of arguments{}: want={}, got={}",
+                target,
+                function.arity(),
+                args.len()
+            ));
+        }
+
+        let mut extended_env = Environment::new_enclosed(&function.env);
+        let mut remaining = args.into_iter();
+        for param in &function.parameters {
+            // 缺省的参数在函数环境中求值，因此可以引用前面的参数
+            let value = match (remaining.next(), &param.default) {
+                (Some(arg), _) => arg,
+                (None, Some(default)) => {
+                    let value = eval_expression(default, &mut extended_env, ctx);
+                    if is_error(&value) {
+                        return value;
+                    }
+                    value
+                }
+                (None, None) => unreachable!("arity checked above"),
+            };
+            extended_env.set(&param.name.value, value);
+        }
+        if let Some(rest) = &function.rest {
+            let rest_array = new_array(remaining.collect(), ctx);
+            if is_error(&rest_array) {
+                return rest_array;
+            }
+            extended_env.set(&rest.value, rest_array);
+        }
+
+        let evaluated = unwrap_return_value(eval_function_block(
+            &function.body,
+            &mut extended_env,
+            true,
+            ctx,
+        ));
+
+        match evaluated {
+            Value::TailCall(tail_call) => (func, args) = (tail_call.function, tail_call.arguments),
+            _ => return evaluated,
+        }
+    }
+}
+
+// 在函数体内按语句求值。tail表示语句的值是否就是函数的返回值：
+// 尾位置上的调用只求值被调函数和参数，返回TailCall交给apply_function执行；
+// return语句总是离开函数，所以它的值也按尾位置处理。
+// 其他节点（包括表达式内部）仍然走普通的eval。
+fn eval_function_body(
+    stmt: &Statement,
+    env: &mut Environment,
+    tail: bool,
+    ctx: &mut EvalContext,
+) -> Value {
+    // 其他语句由eval_statement跟踪
+    if !matches!(
+        stmt,
+        Statement::Block(_) | Statement::Expression(_) | Statement::Return(_)
+    ) {
+        return eval_statement(stmt, env, ctx);
+    }
+    ctx.trace_enter(TraceNode::Statement(stmt));
+    let result = eval_function_body_inner(stmt, env, tail, ctx);
+    ctx.trace_leave(TraceNode::Statement(stmt), &result);
+    result
+}
+
+fn eval_function_body_inner(
+    stmt: &Statement,
+    env: &mut Environment,
+    tail: bool,
+    ctx: &mut EvalContext,
+) -> Value {
+    match stmt {
+        Statement::Block(block) => eval_function_block(block, env, tail, ctx),
+        Statement::Expression(expr_stmt) => {
+            eval_function_expression(&expr_stmt.expression, env, tail, ctx)
+        }
+        Statement::Return(return_stmt) => {
+            let val = eval_function_expression(&return_stmt.return_value, env, true, ctx);
+            if is_error(&val) {
+                return val;
+            }
+            Value::ReturnValue(Box::new(val))
+        }
+        _ => eval_statement(stmt, env, ctx),
+    }
+}
+
+fn eval_function_block(
+    block: &BlockStatement,
+    env: &mut Environment,
+    tail: bool,
+    ctx: &mut EvalContext,
+) -> Value {
+    hoist_function_declarations(&block.statements, env);
+    let mut result = Value::Null;
+    let last = block.statements.len().saturating_sub(1);
+    for (i, statement) in block.statements.iter().enumerate() {
+        result = eval_function_body(statement, env, tail && i == last, ctx);
+        if matches!(result, Value::ReturnValue(_) | Value::Error(_)) {
+            return result;
+        }
+    }
+    result
+}
+
+fn eval_function_expression(
+    expr: &Expression,
+    env: &mut Environment,
+    tail: bool,
+    ctx: &mut EvalContext,
+) -> Value {
+    // 其他表达式由eval_expression跟踪
+    let special = match expr {
+        Expression::If(_) | Expression::Match(_) => true,
+        Expression::Call(_) => tail,
+        _ => false,
+    };
+    if !special {
+        return eval_expression(expr, env, ctx);
+    }
+    ctx.trace_enter(TraceNode::Expression(expr));
+    let result = eval_function_expression_inner(expr, env, tail, ctx);
+    ctx.trace_leave(TraceNode::Expression(expr), &result);
+    result
+}
+
+fn eval_function_expression_inner(
+    expr: &Expression,
+    env: &mut Environment,
+    tail: bool,
+    ctx: &mut EvalContext,
+) -> Value {
+    match expr {
+        Expression::If(if_expr) => {
+            let condition = eval_expression(&if_expr.condition, env, ctx);
+            if is_error(&condition) {
+                return condition;
+            }
+            if is_truthy(&condition) {
+                eval_function_block(&if_expr.consequence, env, tail, ctx)
+            } else if let Some(alt) = &if_expr.alternative {
+                eval_function_block(alt, env, tail, ctx)
+            } else {
+                Value::Null
+            }
         }
+        Expression::Match(match_expr) => match select_match_arm(match_expr, env, ctx) {
+            Ok((arm, mut arm_env)) => eval_function_body(&arm.body, &mut arm_env, tail, ctx),
+            Err(err) => err,
+        },
+        Expression::Call(call) if tail => {
+            let function = eval_expression(&call.function, env, ctx);
+            if is_error(&function) {
+                return function;
+            }
+            match eval_call_arguments(&call.arguments, env, ctx) {
+                Ok(args) => Value::TailCall(Box::new(TailCall {
+                    function,
+                    arguments: args,
+                })),
+                Err(err) => err,
+            }
+        }
+        _ => eval_expression(expr, env, ctx),
+    }
+}
+
+// 函数返回时解包ReturnValue，避免return继续向外层传播
+fn unwrap_return_value(obj: Value) -> Value {
+    match obj {
+        Value::ReturnValue(value) => *value,
+        obj => obj,
+    }
+}
+
+fn eval_identifier(node: &Identifier, env: &mut Environment) -> Value {
+    if let Some(val) = env.get(&node.value) {
+        val // Environment::get已经返回副本
     } else {
         new_error(format!("identifier not found: {}", node.value))
     }
 }
 
-fn eval_prefix_expression(operator: &str, right: Box<dyn Object>) -> Box<dyn Object> {
+// 依次求值表达式列表，遇到错误立即返回该错误
+fn eval_expressions(
+    exps: &[Expression],
+    env: &mut Environment,
+    ctx: &mut EvalContext,
+) -> Result<Vec<Value>, Value> {
+    let mut result = Vec::with_capacity(exps.len());
+    for exp in exps {
+        let evaluated = eval_expression(exp, env, ctx);
+        if is_error(&evaluated) {
+            return Err(evaluated);
+        }
+        result.push(evaluated);
+    }
+    Ok(result)
+}
+
+fn eval_hash_literal(node: &HashLiteral, env: &mut Environment, ctx: &mut EvalContext) -> Value {
+    let mut pairs = BTreeMap::new();
+
+    for (key_node, value_node) in &node.pairs {
+        let key = eval_expression(key_node, env, ctx);
+        if is_error(&key) {
+            return key;
+        }
+
+        let Some(hash_key) = HashKey::from_value(&key) else {
+            return new_error(format!("unusable as hash key: {}", key.type_obj()));
+        };
+
+        let value = eval_expression(value_node, env, ctx);
+        if is_error(&value) {
+            return value;
+        }
+
+        pairs.insert(hash_key, HashPair { key, value });
+    }
+
+    alloc(Value::Hash(Rc::new(Hash { pairs })), ctx)
+}
+
+// 按模式把值绑定到环境中，形状不匹配时返回错误对象
+fn bind_pattern(
+    pattern: &Pattern,
+    val: Value,
+    env: &mut Environment,
+    ctx: &mut EvalContext,
+) -> Option<Value> {
+    match pattern {
+        Pattern::Wildcard => None,
+        Pattern::Integer(_) | Pattern::String(_) | Pattern::Boolean(_) => {
+            if literal_pattern_matches(pattern, &val) {
+                None
+            } else {
+                Some(new_error(format!(
+                    "pattern {} does not match {}",
+                    pattern,
+                    val.inspect()
+                )))
+            }
+        }
+        Pattern::Identifier(ident) => {
+            env.set(&ident.value, val);
+            None
+        }
+        Pattern::Array(array_pattern) => bind_array_pattern(array_pattern, &val, env, ctx),
+        Pattern::Hash(hash_pattern) => bind_hash_pattern(hash_pattern, &val, env, ctx),
+    }
+}
+
+fn bind_array_pattern(
+    pattern: &ArrayPattern,
+    val: &Value,
+    env: &mut Environment,
+    ctx: &mut EvalContext,
+) -> Option<Value> {
+    let Value::Array(array) = val else {
+        return Some(new_error(format!(
+            "cannot destructure {} with array pattern {}",
+            val.type_obj(),
+            pattern
+        )));
+    };
+
+    let want = pattern.elements.len();
+    let got = array.elements.len();
+    if pattern.rest.is_none() && got != want {
+        return Some(new_error(format!(
+            "array pattern {} length mismatch: want={}, got={}",
+            pattern, want, got
+        )));
+    }
+    if got < want {
+        return Some(new_error(format!(
+            "array pattern {} length mismatch: want at least {}, got={}",
+            pattern, want, got
+        )));
+    }
+
+    for (element_pattern, element) in pattern.elements.iter().zip(&array.elements) {
+        if let Some(err) = bind_pattern(element_pattern, element.clone(), env, ctx) {
+            return Some(err);
+        }
+    }
+
+    if let Some(rest) = &pattern.rest {
+        let rest_array = new_array(array.elements[want..].to_vec(), ctx);
+        if is_error(&rest_array) {
+            return Some(rest_array);
+        }
+        env.set(&rest.value, rest_array);
+    }
+
+    None
+}
+
+fn bind_hash_pattern(
+    pattern: &HashPattern,
+    val: &Value,
+    env: &mut Environment,
+    ctx: &mut EvalContext,
+) -> Option<Value> {
+    let Value::Hash(hash) = val else {
+        return Some(new_error(format!(
+            "cannot destructure {} with hash pattern {}",
+            val.type_obj(),
+            pattern
+        )));
+    };
+
+    for (key, value_pattern) in &pattern.pairs {
+        let Some(value) = hash.get(&Value::String(key.as_str().into())) else {
+            return Some(new_error(format!("key not found in hash: {}", key)));
+        };
+        if let Some(err) = bind_pattern(value_pattern, value.clone(), env, ctx) {
+            return Some(err);
+        }
+    }
+
+    None
+}
+
+fn eval_match_expression(
+    me: &MatchExpression,
+    env: &mut Environment,
+    ctx: &mut EvalContext,
+) -> Value {
+    match select_match_arm(me, env, ctx) {
+        Ok((arm, mut arm_env)) => eval_statement(&arm.body, &mut arm_env, ctx),
+        Err(err) => err,
+    }
+}
+
+// 找到第一个匹配（且守卫为真）的分支，返回该分支和绑定了模式变量的子作用域，
+// 调用方在子作用域中求值分支体，模式变量不会影响外层的同名绑定
+fn select_match_arm<'a>(
+    me: &'a MatchExpression,
+    env: &mut Environment,
+    ctx: &mut EvalContext,
+) -> Result<(&'a MatchArm, Environment), Value> {
+    let subject = eval_expression(&me.subject, env, ctx);
+    if is_error(&subject) {
+        return Err(subject);
+    }
+
+    for arm in &me.arms {
+        let mut bindings = Vec::new();
+        if !match_pattern(&arm.pattern, &subject, &mut bindings, ctx)? {
+            continue;
+        }
+
+        // 守卫可以引用模式绑定的变量，所以也在子作用域中求值
+        let mut arm_env = Environment::new_enclosed(env);
+        for (name, value) in bindings {
+            arm_env.set(&name, value);
+        }
+
+        if let Some(guard) = &arm.guard {
+            let condition = eval_expression(guard, &mut arm_env, ctx);
+            if is_error(&condition) {
+                return Err(condition);
+            }
+            if !is_truthy(&condition) {
+                continue;
+            }
+        }
+
+        return Ok((arm, arm_env));
+    }
+
+    Err(new_error(format!(
+        "no match arm for value: {}",
+        subject.inspect()
+    )))
+}
+
+// 检查值是否匹配模式，匹配成功时把需要绑定的变量放入bindings；
+// 为剩余元素新建数组超过内存上限时返回Err
+fn match_pattern(
+    pattern: &Pattern,
+    val: &Value,
+    bindings: &mut Vec<(String, Value)>,
+    ctx: &mut EvalContext,
+) -> Result<bool, Value> {
+    match pattern {
+        Pattern::Wildcard => Ok(true),
+        Pattern::Integer(_) | Pattern::String(_) | Pattern::Boolean(_) => {
+            Ok(literal_pattern_matches(pattern, val))
+        }
+        Pattern::Identifier(ident) => {
+            bindings.push((ident.value.clone(), val.clone()));
+            Ok(true)
+        }
+        Pattern::Array(array_pattern) => {
+            let Value::Array(array) = val else {
+                return Ok(false);
+            };
+            let want = array_pattern.elements.len();
+            let got = array.elements.len();
+            if got < want || (array_pattern.rest.is_none() && got != want) {
+                return Ok(false);
+            }
+            for (element_pattern, element) in array_pattern.elements.iter().zip(&array.elements) {
+                if !match_pattern(element_pattern, element, bindings, ctx)? {
+                    return Ok(false);
+                }
+            }
+            if let Some(rest) = &array_pattern.rest {
+                let rest_array = new_array(array.elements[want..].to_vec(), ctx);
+                if is_error(&rest_array) {
+                    return Err(rest_array);
+                }
+                bindings.push((rest.value.clone(), rest_array));
+            }
+            Ok(true)
+        }
+        Pattern::Hash(hash_pattern) => {
+            let Value::Hash(hash) = val else {
+                return Ok(false);
+            };
+            for (key, value_pattern) in &hash_pattern.pairs {
+                match hash.get(&Value::String(key.as_str().into())) {
+                    Some(value) => {
+                        if !match_pattern(value_pattern, value, bindings, ctx)? {
+                            return Ok(false);
+                        }
+                    }
+                    None => return Ok(false),
+                }
+            }
+            Ok(true)
+        }
+    }
+}
+
+fn literal_pattern_matches(pattern: &Pattern, val: &Value) -> bool {
+    match pattern {
+        Pattern::Integer(expected) => matches!(val, Value::Integer(i) if i == expected),
+        Pattern::String(expected) => matches!(val, Value::String(s) if **s == **expected),
+        Pattern::Boolean(expected) => matches!(val, Value::Boolean(b) if b == expected),
+        _ => false,
+    }
+}
+
+fn eval_prefix_expression(operator: &str, right: Value) -> Value {
     match operator {
         "!" => eval_bang_operator_expression(right),
         "-" => eval_minus_prefix_operator_expression(right),
-        // _ => get_null_object(),
+        // _ => Value::Null,
         _ => {
             let error_msg = format!("unknown operator: {}{}", operator, right.type_obj());
             new_error(error_msg)
@@ -252,166 +838,116 @@ fn eval_prefix_expression(operator: &str, right: Box<dyn Object>) -> Box<dyn Obj
     }
 }
 
-fn eval_bang_operator_expression(right: Box<dyn Object>) -> Box<dyn Object> {
+fn eval_bang_operator_expression(right: Value) -> Value {
     // 处理!运算符的逻辑
-    match right.type_obj().as_str() {
-        "BOOLEAN" => {
-            if let Some(bool_obj) = right.as_any().downcast_ref::<object::Boolean>() {
-                return native_bool_to_boolean_object(!bool_obj.value);
-            }
-            get_null_object()
-        }
-        "NULL" => native_bool_to_boolean_object(true), // NULL取反为true
-        _ => native_bool_to_boolean_object(false),     // 其他类型取反为false
+    match right {
+        Value::Boolean(value) => Value::Boolean(!value),
+        Value::Null => Value::Boolean(true), // NULL取反为true
+        _ => Value::Boolean(false),          // 其他类型取反为false
     }
 }
 
-fn eval_minus_prefix_operator_expression(right: Box<dyn Object>) -> Box<dyn Object> {
+fn eval_minus_prefix_operator_expression(right: Value) -> Value {
     // 处理-运算符的逻辑
-    if right.type_obj() != "INTEGER" {
-        return new_error(format!("unknown operator: -{}", right.type_obj()));
+    match right {
+        Value::Integer(value) => match value.checked_neg() {
+            Some(value) => Value::Integer(value),
+            None => new_error("integer overflow".to_string()),
+        },
+        _ => new_error(format!("unknown operator: -{}", right.type_obj())),
     }
+}
 
-    if let Some(int_obj) = right.as_any().downcast_ref::<Integer>() {
-        return Box::new(Integer::new(-int_obj.value));
+fn eval_infix_expression(operator: &str, left: Value, right: Value) -> Value {
+    match (&left, &right) {
+        (Value::Integer(l), Value::Integer(r)) => eval_integer_infix_expression(operator, *l, *r),
+        (Value::Boolean(l), Value::Boolean(r)) => eval_boolean_infix_expression(operator, *l, *r),
+        _ if left.type_obj() != right.type_obj() => {
+            let error_msg = format!(
+                "type mismatch: {} {} {}",
+                left.type_obj(),
+                operator,
+                right.type_obj()
+            );
+            new_error(error_msg)
+        }
+        _ => {
+            let error_msg = format!(
+                "unknown operator: {} {} {}",
+                left.type_obj(),
+                operator,
+                right.type_obj()
+            );
+            new_error(error_msg)
+        }
     }
-
-    get_null_object()
 }
 
-fn eval_infix_expression(
-    operator: &str,
-    left: Box<dyn Object>,
-    right: Box<dyn Object>,
-) -> Box<dyn Object> {
-    if left.type_obj() == "INTEGER" && right.type_obj() == "INTEGER" {
-        return eval_integer_infix_expression(operator, left, right);
-    } else if left.type_obj() == "BOOLEAN" && right.type_obj() == "BOOLEAN" {
-        return eval_boolean_infix_expression(operator, left, right);
-    } else if left.type_obj() != right.type_obj() {
-        let error_msg = format!(
-            "type mismatch: {} {} {}",
-            left.type_obj(),
-            operator,
-            right.type_obj()
-        );
-        return new_error(error_msg);
-    } else {
-        let error_msg = format!(
-            "unknown operator: {} {} {}",
-            left.type_obj(),
-            operator,
-            right.type_obj()
-        );
-        return new_error(error_msg);
-    }
-}
-
-fn eval_boolean_infix_expression(
-    operator: &str,
-    left: Box<dyn Object>,
-    right: Box<dyn Object>,
-) -> Box<dyn Object> {
-    let left_val = left
-        .as_any()
-        .downcast_ref::<object::Boolean>()
-        .expect("Left operand is not a Boolean")
-        .value;
-    let right_val = right
-        .as_any()
-        .downcast_ref::<object::Boolean>()
-        .expect("Right operand is not a Boolean")
-        .value;
-
+fn eval_boolean_infix_expression(operator: &str, left_val: bool, right_val: bool) -> Value {
     match operator {
-        "==" => native_bool_to_boolean_object(left_val == right_val),
-        "!=" => native_bool_to_boolean_object(left_val != right_val),
+        "==" => Value::Boolean(left_val == right_val),
+        "!=" => Value::Boolean(left_val != right_val),
         _ => new_error(format!(
             "unknown operator: {} {} {}",
-            "BOOLEAN", operator, "BOOLEAN"
+            ObjectType::Boolean,
+            operator,
+            ObjectType::Boolean
         )),
     }
 }
 
-fn eval_integer_infix_expression(
-    operator: &str,
-    left: Box<dyn Object>,
-    right: Box<dyn Object>,
-) -> Box<dyn Object> {
-    let left_val = left
-        .as_any()
-        .downcast_ref::<object::Integer>()
-        .expect("左操作数不是整数");
-
-    let right_val = right
-        .as_any()
-        .downcast_ref::<object::Integer>()
-        .expect("右操作数不是整数");
-
+fn eval_integer_infix_expression(operator: &str, left_val: i64, right_val: i64) -> Value {
+    // 溢出和除以零返回Error而不是panic
+    let checked = |result: Option<i64>| match result {
+        Some(value) => Value::Integer(value),
+        None => new_error("integer overflow".to_string()),
+    };
     match operator {
-        "+" => Box::new(Integer::new(left_val.value + right_val.value)),
-        "-" => Box::new(Integer::new(left_val.value - right_val.value)),
-        "*" => Box::new(Integer::new(left_val.value * right_val.value)),
-        "/" => Box::new(Integer::new(left_val.value / right_val.value)),
-        "<" => native_bool_to_boolean_object(left_val.value < right_val.value),
-        ">" => native_bool_to_boolean_object(left_val.value > right_val.value),
-        "==" => native_bool_to_boolean_object(left_val.value == right_val.value),
-        "!=" => native_bool_to_boolean_object(left_val.value != right_val.value),
-        // _ => get_null_object(),
+        "+" => checked(left_val.checked_add(right_val)),
+        "-" => checked(left_val.checked_sub(right_val)),
+        "*" => checked(left_val.checked_mul(right_val)),
+        "/" if right_val == 0 => new_error("division by zero".to_string()),
+        "/" => checked(left_val.checked_div(right_val)),
+        "<" => Value::Boolean(left_val < right_val),
+        ">" => Value::Boolean(left_val > right_val),
+        "==" => Value::Boolean(left_val == right_val),
+        "!=" => Value::Boolean(left_val != right_val),
+        // _ => Value::Null,
         _ => new_error(format!(
             "unknown operator:{} {} {}",
-            left.type_obj(),
+            ObjectType::Integer,
             operator,
-            right.type_obj()
+            ObjectType::Integer
         )),
     }
 }
 
-fn eval_if_expression(ie: &IfExpression, env: &mut Environment) -> Box<dyn Object> {
-    let condition = eval(ie.condition.as_ref(), env);
+fn eval_if_expression(ie: &IfExpression, env: &mut Environment, ctx: &mut EvalContext) -> Value {
+    let condition = eval_expression(&ie.condition, env, ctx);
     if is_error(&condition) {
         return condition;
     }
-    println!("got condition:{:?}", condition.inspect());
 
     if is_truthy(&condition) {
-        println!("condition is true");
-        if let Some(block) = ie.consequence.as_any().downcast_ref::<BlockStatement>() {
-            return eval_block_statement(&block, env);
-        }
-        return eval(ie.consequence.as_ref(), env);
+        return eval_block_statement(&ie.consequence, env, ctx);
     } else if let Some(alt) = &ie.alternative {
-        println!("condition is false");
-        if let Some(block) = alt.as_any().downcast_ref::<BlockStatement>() {
-            return eval_block_statement(&block, env);
-        }
-        return eval(alt.as_ref(), env);
-    }
-    println!("if expression return none");
-    get_null_object()
-}
-fn is_truthy(obj: &Box<dyn Object>) -> bool {
-    match obj.type_obj().as_str() {
-        "NULL" => false,
-        "BOOLEAN" => {
-            // 对于布尔对象，获取其值
-            if let Some(bool_obj) = obj.as_any().downcast_ref::<object::Boolean>() {
-                bool_obj.value
-            } else {
-                false
-            }
-        }
-        _ => {
-            println!("other all return true");
-            true
-        }
+        return eval_block_statement(alt, env, ctx);
+    }
+    Value::Null
+}
+fn is_truthy(obj: &Value) -> bool {
+    match obj {
+        Value::Null => false,
+        // 对于布尔对象，获取其值
+        Value::Boolean(value) => *value,
+        _ => true,
     }
 }
 
-fn new_error(message: String) -> Box<dyn Object> {
-    Box::new(object::error::Error { message })
+pub(super) fn new_error(message: String) -> Value {
+    Value::Error(message.into())
 }
 
-fn is_error(obj: &Box<dyn Object>) -> bool {
-    obj.type_obj() == "ERROR"
+fn is_error(obj: &Value) -> bool {
+    matches!(obj, Value::Error(_))
 }
@@ -1 +1,5 @@
+pub mod builtins;
+pub mod call_graph;
+pub mod context;
 pub mod evaluator;
+pub mod trace;
@@ -0,0 +1,134 @@
+use std::io::Write;
+
+use crate::ast::{Expression, Statement};
+use crate::object::Value;
+
+// 正在求值的节点
+#[derive(Debug, Clone, Copy)]
+pub enum TraceNode<'a> {
+    Statement(&'a Statement),
+    Expression(&'a Expression),
+}
+
+impl TraceNode<'_> {
+    // 与AST的结构体同名
+    pub fn kind(&self) -> &'static str {
+        match self {
+            TraceNode::Statement(stmt) => match stmt {
+                Statement::Let(_) => "LetStatement",
+                Statement::DestructuringLet(_) => "DestructuringLetStatement",
+                Statement::Return(_) => "ReturnStatement",
+                Statement::Expression(_) => "ExpressionStatement",
+                Statement::Block(_) => "BlockStatement",
+                Statement::FunctionDeclaration(_) => "FunctionDeclaration",
+            },
+            TraceNode::Expression(expr) => match expr {
+                Expression::Identifier(_) => "Identifier",
+                Expression::Integer(_) => "IntegerLiteral",
+                Expression::String(_) => "StringLiteral",
+                Expression::Boolean(_) => "Boolean",
+                Expression::Prefix(_) => "PrefixExpression",
+                Expression::Infix(_) => "InfixExpression",
+                Expression::If(_) => "IfExpression",
+                Expression::Function(_) => "FunctionLiteral",
+                Expression::Call(_) => "CallExpression",
+                Expression::Array(_) => "ArrayLiteral",
+                Expression::Hash(_) => "HashLiteral",
+                Expression::Match(_) => "MatchExpression",
+                Expression::Spread(_) => "SpreadExpression",
+            },
+        }
+    }
+
+    // 名字、字面量或运算符，没有时为None
+    pub fn detail(&self) -> Option<String> {
+        match self {
+            TraceNode::Statement(stmt) => match stmt {
+                Statement::Let(let_stmt) => Some(let_stmt.name.value.clone()),
+                Statement::DestructuringLet(let_stmt) => Some(let_stmt.pattern.to_string()),
+                Statement::FunctionDeclaration(decl) => Some(decl.name.value.clone()),
+                _ => None,
+            },
+            TraceNode::Expression(expr) => match expr {
+                Expression::Identifier(ident) => Some(ident.value.clone()),
+                Expression::Integer(int) => Some(int.value.to_string()),
+                Expression::String(string) => Some(format!("{:?}", string.value)),
+                Expression::Boolean(boolean) => Some(boolean.value.to_string()),
+                Expression::Prefix(prefix) => Some(prefix.operator.clone()),
+                Expression::Infix(infix) => Some(infix.operator.clone()),
+                _ => None,
+            },
+        }
+    }
+}
+
+// 求值器在每个节点开始和结束时调用，enter和leave总是成对出现
+pub trait TraceSink {
+    fn enter(&mut self, node: TraceNode);
+    fn leave(&mut self, node: TraceNode, result: &Value);
+}
+
+// 缩进的求值过程，每层两个空格。没有子节点的节点和它的值写在同一行：
+//   LetStatement x
+//     InfixExpression +
+//       IntegerLiteral 1 => 1
+//       IntegerLiteral 2 => 2
+//     => 3
+//   => null
+pub struct IndentedTrace<W: Write> {
+    out: W,
+    level: usize,
+    pending: Option<String>, // 还没有遇到子节点的节点
+}
+
+impl<W: Write> IndentedTrace<W> {
+    pub fn new(out: W) -> Self {
+        IndentedTrace {
+            out,
+            level: 0,
+            pending: None,
+        }
+    }
+
+    fn indent(&self) -> String {
+        "  ".repeat(self.level)
+    }
+
+    // 跟踪输出失败（例如管道关闭）不影响求值
+    fn flush_pending(&mut self) {
+        if let Some(line) = self.pending.take() {
+            let _ = writeln!(self.out, "{}", line);
+        }
+    }
+}
+
+impl<W: Write> TraceSink for IndentedTrace<W> {
+    fn enter(&mut self, node: TraceNode) {
+        self.flush_pending();
+        let mut line = self.indent() + node.kind();
+        if let Some(detail) = node.detail() {
+            line.push(' ');
+            line.push_str(&detail);
+        }
+        self.pending = Some(line);
+        self.level += 1;
+    }
+
+    fn leave(&mut self, _node: TraceNode, result: &Value) {
+        self.level -= 1;
+        let _ = match self.pending.take() {
+            Some(line) => writeln!(self.out, "{} => {}", line, summary(result)),
+            None => writeln!(self.out, "{}=> {}", self.indent(), summary(result)),
+        };
+    }
+}
+
+// 函数只写签名，不写函数体
+fn summary(value: &Value) -> String {
+    match value {
+        Value::Function(function) => function.signature(),
+        Value::Builtin(builtin) => builtin.signature(),
+        Value::TailCall(call) => format!("tail call {}", summary(&call.function)),
+        value => value.inspect(),
+    }
+}
@@ -0,0 +1,283 @@
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+
+use crate::ast::printer::{DEFAULT_WIDTH, Printer};
+use crate::cli::cli::strip_shebang;
+use crate::lexer::lexer::Lexer;
+use crate::parser::parser::Parser;
+
+const USAGE: &str = "usage: monkey fmt [--check] [--width N] [path ...]";
+
+// 格式化一段源码，保留注释和语句之间的空行。有语法错误时返回解析错误，
+// 注释在表达式内部、放不回原处时（比如运算符两侧）也返回错误而不是把它挪走。
+// 第一行的 #! 原样保留
+pub fn format_source(input: &str, width: usize) -> Result<String, Vec<String>> {
+    let Some(shebang) = input.lines().next().filter(|line| line.starts_with("#!")) else {
+        return format_program(input, width);
+    };
+    // 解析时保留换行，错误的行号与原文件一致
+    let body = format_program(&strip_shebang(input), width)?;
+    Ok(format!("{}\n{}", shebang, body))
+}
+
+fn format_program(input: &str, width: usize) -> Result<String, Vec<String>> {
+    let mut l = Lexer::new(input.to_string());
+    l.set_keep_comments(true);
+    let mut p = Parser::new(l);
+    let program = p.parse_program();
+    if !p.errors().is_empty() {
+        return Err(p.errors());
+    }
+    let misplaced = &p.comments().misplaced;
+    if !misplaced.is_empty() {
+        return Err(misplaced
+            .iter()
+            .map(|c| format!("{}: cannot keep comment in place: {}", c.position, c.text))
+            .collect());
+    }
+
+    let mut printer = Printer::new();
+    printer.set_width(width);
+    printer.set_comments(p.comments());
+    let mut out = printer.print_program(&program);
+    if !out.is_empty() {
+        out.push('\n');
+    }
+    Ok(out)
+}
+
+// 按行比较的统一格式diff，上下文三行，内容相同时返回空字符串
+pub fn diff(path: &str, old: &str, new: &str) -> String {
+    const CONTEXT: usize = 3;
+
+    let old_lines: Vec<&str> = old.lines().collect();
+    let new_lines: Vec<&str> = new.lines().collect();
+
+    // 先去掉相同的开头和结尾，只对中间改动的部分建LCS表，
+    // 格式化通常只改几行，大文件也不需要行数平方的内存
+    let prefix = old_lines
+        .iter()
+        .zip(&new_lines)
+        .take_while(|(x, y)| x == y)
+        .count();
+    let suffix = old_lines[prefix..]
+        .iter()
+        .rev()
+        .zip(new_lines[prefix..].iter().rev())
+        .take_while(|(x, y)| x == y)
+        .count();
+    let a = &old_lines[prefix..old_lines.len() - suffix];
+    let b = &new_lines[prefix..new_lines.len() - suffix];
+
+    // lcs[i][j]是a[i..]和b[j..]的最长公共子序列长度
+    let mut lcs = vec![vec![0usize; b.len() + 1]; a.len() + 1];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            lcs[i][j] = if a[i] == b[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    // (标记, 旧行号, 新行号, 内容)
+    let mut ops: Vec<_> = (0..prefix).map(|i| (' ', i, i, old_lines[i])).collect();
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() || j < b.len() {
+        if i < a.len() && j < b.len() && a[i] == b[j] {
+            ops.push((' ', prefix + i, prefix + j, a[i]));
+            i += 1;
+            j += 1;
+        } else if i < a.len() && (j == b.len() || lcs[i + 1][j] >= lcs[i][j + 1]) {
+            ops.push(('-', prefix + i, prefix + j, a[i]));
+            i += 1;
+        } else {
+            ops.push(('+', prefix + i, prefix + j, b[j]));
+            j += 1;
+        }
+    }
+    let (old_end, new_end) = (prefix + a.len(), prefix + b.len());
+    ops.extend((0..suffix).map(|k| (' ', old_end + k, new_end + k, old_lines[old_end + k])));
+
+    let mut out = String::new();
+    let mut k = 0;
+    while k < ops.len() {
+        if ops[k].0 == ' ' {
+            k += 1;
+            continue;
+        }
+        // 把相距不超过两倍上下文的修改合并成一个hunk
+        let start = k.saturating_sub(CONTEXT);
+        let mut end = k;
+        let mut unchanged = 0;
+        while end < ops.len() && unchanged <= 2 * CONTEXT {
+            if ops[end].0 == ' ' {
+                unchanged += 1;
+            } else {
+                unchanged = 0;
+            }
+            end += 1;
+        }
+        let end = (end - unchanged + CONTEXT).min(ops.len());
+
+        if out.is_empty() {
+            out.push_str(&format!("--- {}\n+++ {} (formatted)\n", path, path));
+        }
+        let hunk = &ops[start..end];
+        let old_len = hunk.iter().filter(|op| op.0 != '+').count();
+        let new_len = hunk.iter().filter(|op| op.0 != '-').count();
+        // 统一diff的行号从1开始，长度为0时写前一行的行号
+        let old_start = if old_len == 0 {
+            hunk[0].1
+        } else {
+            hunk[0].1 + 1
+        };
+        let new_start = if new_len == 0 {
+            hunk[0].2
+        } else {
+            hunk[0].2 + 1
+        };
+        out.push_str(&format!(
+            "@@ -{},{} +{},{} @@\n",
+            old_start, old_len, new_start, new_len
+        ));
+        for (tag, _, _, line) in hunk {
+            out.push(*tag);
+            out.push_str(line);
+            out.push('\n');
+        }
+        k = end;
+    }
+    out
+}
+
+// monkey fmt的入口，返回进程退出码：0成功，1表示--check发现未格式化的文件，2表示用法、解析或读写错误
+pub fn run(args: &[String]) -> i32 {
+    let mut check = false;
+    let mut width = DEFAULT_WIDTH;
+    let mut paths = Vec::new();
+
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        let value = match arg.as_str() {
+            "--check" => {
+                check = true;
+                continue;
+            }
+            "--width" => iter.next().map(String::as_str),
+            _ if arg.starts_with("--width=") => Some(&arg["--width=".len()..]),
+            _ if arg.starts_with('-') && arg != "-" => {
+                eprintln!("unknown option: {}\n{}", arg, USAGE);
+                return 2;
+            }
+            _ => {
+                paths.push(PathBuf::from(arg));
+                continue;
+            }
+        };
+        match value.and_then(|v| v.parse::<usize>().ok()) {
+            Some(w) if w > 0 => width = w,
+            _ => {
+                eprintln!("--width expects a positive number\n{}", USAGE);
+                return 2;
+            }
+        }
+    }
+
+    // 没有给路径时从标准输入读，结果写到标准输出
+    if paths.is_empty() || paths.iter().all(|p| p.as_os_str() == "-") {
+        let mut input = String::new();
+        if let Err(err) = io::stdin().read_to_string(&mut input) {
+            eprintln!("<stdin>: {}", err);
+            return 2;
+        }
+        return match format_source(&input, width) {
+            Ok(formatted) if check => {
+                if formatted == input {
+                    0
+                } else {
+                    print!("{}", diff("<stdin>", &input, &formatted));
+                    1
+                }
+            }
+            Ok(formatted) => {
+                print!("{}", formatted);
+                0
+            }
+            Err(errors) => {
+                print_errors("<stdin>", &errors);
+                2
+            }
+        };
+    }
+
+    let mut files = Vec::new();
+    for path in &paths {
+        if let Err(err) = collect_files(path, &mut files) {
+            eprintln!("{}: {}", path.display(), err);
+            return 2;
+        }
+    }
+
+    let mut status = 0;
+    for file in &files {
+        let name = file.display().to_string();
+        let input = match fs::read_to_string(file) {
+            Ok(input) => input,
+            Err(err) => {
+                eprintln!("{}: {}", name, err);
+                status = 2;
+                continue;
+            }
+        };
+        let formatted = match format_source(&input, width) {
+            Ok(formatted) => formatted,
+            Err(errors) => {
+                print_errors(&name, &errors);
+                status = 2;
+                continue;
+            }
+        };
+        if formatted == input {
+            continue;
+        }
+        if check {
+            print!("{}", diff(&name, &input, &formatted));
+            status = status.max(1);
+        } else if let Err(err) = fs::write(file, formatted) {
+            eprintln!("{}: {}", name, err);
+            status = 2;
+        }
+    }
+    let _ = io::stdout().flush();
+    status
+}
+
+// 目录递归查找*.mk文件，按路径排序；直接给出的文件不检查扩展名
+fn collect_files(path: &Path, files: &mut Vec<PathBuf>) -> io::Result<()> {
+    if !path.is_dir() {
+        fs::metadata(path)?;
+        files.push(path.to_path_buf());
+        return Ok(());
+    }
+    let mut entries = fs::read_dir(path)?
+        .map(|entry| entry.map(|e| e.path()))
+        .collect::<io::Result<Vec<_>>>()?;
+    entries.sort();
+    for entry in entries {
+        if entry.is_dir() {
+            collect_files(&entry, files)?;
+        } else if entry.extension().is_some_and(|ext| ext == "mk") {
+            files.push(entry);
+        }
+    }
+    Ok(())
+}
+
+fn print_errors(name: &str, errors: &[String]) {
+    for error in errors {
+        eprintln!("{}: {}", name, error);
+    }
+}
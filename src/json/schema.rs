@@ -0,0 +1,927 @@
+use std::ops::Range;
+
+use crate::ast::{
+    ArrayLiteral, ArrayPattern, BlockStatement, Boolean, CallExpression, DefaultParameter,
+    DestructuringLetStatement, Expression, ExpressionStatement, FunctionDeclaration,
+    FunctionLiteral, HashLiteral, HashPattern, Identifier, IfExpression, InfixExpression,
+    IntegerLiteral, LetStatement, MatchArm, MatchExpression, Parameter, Pattern, PrefixExpression,
+    Program, RestParameter, ReturnStatement, SpreadExpression, Statement, StringLiteral,
+};
+use crate::cst::{self, SyntaxKind, SyntaxNode};
+use crate::lexer::lexer::Lexer;
+use crate::token::token::{Token, TokenType};
+
+use super::Json;
+
+// 给外部工具使用的JSON格式。顶层是 {"schema", "version", ...}，字段增删或改名时增加版本号。
+// AST节点是 {"kind": 结构体名, 与结构体同名的字段..., "span"}，词法单元是 {"type", "literal"}。
+// span是 {"start", "end"}，源码中的UTF-8字节偏移，左闭右开；只有从源码生成时才有
+pub const AST_SCHEMA: &str = "monkey-ast";
+pub const TOKENS_SCHEMA: &str = "monkey-tokens";
+pub const SCHEMA_VERSION: i64 = 2;
+
+const TOKEN_TYPES: [TokenType; 36] = [
+    TokenType::LET,
+    TokenType::FN,
+    TokenType::ILLEGAL,
+    TokenType::EOF,
+    TokenType::IDENT,
+    TokenType::INT,
+    TokenType::STRING,
+    TokenType::ASSIGN,
+    TokenType::PLUS,
+    TokenType::MINUS,
+    TokenType::ASTERISK,
+    TokenType::SLASH,
+    TokenType::COMMA,
+    TokenType::COLON,
+    TokenType::SEMICOLON,
+    TokenType::LPAREN,
+    TokenType::RPAREN,
+    TokenType::LBRACE,
+    TokenType::RBRACE,
+    TokenType::LBRACKET,
+    TokenType::RBRACKET,
+    TokenType::ELLIPSIS,
+    TokenType::FATARROW,
+    TokenType::LT,
+    TokenType::GT,
+    TokenType::EQ,
+    TokenType::NOTEQ,
+    TokenType::IF,
+    TokenType::ELSE,
+    TokenType::RETURN,
+    TokenType::MATCH,
+    TokenType::TRUE,
+    TokenType::FALSE,
+    TokenType::BANG,
+    TokenType::COMMENT,
+    TokenType::WHITESPACE,
+];
+
+// 不带span的AST
+pub fn program_to_json(program: &Program) -> Json {
+    envelope(AST_SCHEMA, "program", write_program(program, None))
+}
+
+// 解析源码得到带span的AST，解析失败时返回解析错误
+pub fn source_to_json(input: &str) -> Result<Json, Vec<String>> {
+    let parse = cst::parse(input);
+    if !parse.errors().is_empty() {
+        return Err(parse.errors().to_vec());
+    }
+    let root = parse.syntax();
+    Ok(envelope(
+        AST_SCHEMA,
+        "program",
+        write_program(&parse.program(), Some(&root)),
+    ))
+}
+
+// 词法单元流，最后是EOF。除了span还带上从1开始的行号和列号
+pub fn tokens_to_json(input: &str) -> Json {
+    // 词法分析器的范围按字符计，换算成字节偏移
+    let mut offsets: Vec<usize> = input.char_indices().map(|(i, _)| i).collect();
+    offsets.push(input.len());
+    let byte = |i: usize| offsets[i.min(offsets.len() - 1)];
+
+    let mut l = Lexer::new(input.to_string());
+    let mut tokens = Vec::new();
+    loop {
+        let tok = l.next_token();
+        let range = l.token_range();
+        let position = l.token_position();
+        let Json::Object(mut fields) = token_to_json(&tok) else {
+            unreachable!()
+        };
+        fields.push(("span".to_string(), span(byte(range.start)..byte(range.end))));
+        fields.push(("line".to_string(), Json::Int(position.line as i64)));
+        fields.push(("column".to_string(), Json::Int(position.column as i64)));
+        tokens.push(Json::Object(fields));
+        if tok.token_type == TokenType::EOF {
+            return envelope(TOKENS_SCHEMA, "tokens", Json::Array(tokens));
+        }
+    }
+}
+
+pub fn token_to_json(token: &Token) -> Json {
+    Json::Object(vec![
+        (
+            "type".to_string(),
+            Json::String(format!("{:?}", token.token_type)),
+        ),
+        ("literal".to_string(), Json::String(token.literal.clone())),
+    ])
+}
+
+// 由program_to_json或source_to_json的结果还原AST，span被忽略
+pub fn program_from_json(json: &Json) -> Result<Program, String> {
+    check_envelope(json, AST_SCHEMA)?;
+    read_program(field(json, "program")?)
+}
+
+// 由tokens_to_json的结果还原词法单元
+pub fn tokens_from_json(json: &Json) -> Result<Vec<Token>, String> {
+    check_envelope(json, TOKENS_SCHEMA)?;
+    array(json, "tokens")?.iter().map(token_from_json).collect()
+}
+
+pub fn token_from_json(json: &Json) -> Result<Token, String> {
+    let name = string(json, "type")?;
+    let token_type = TOKEN_TYPES
+        .into_iter()
+        .find(|t| format!("{:?}", t) == name)
+        .ok_or_else(|| format!("unknown token type: {}", name))?;
+    Ok(Token {
+        token_type,
+        literal: string(json, "literal")?,
+    })
+}
+
+fn envelope(schema: &str, key: &str, value: Json) -> Json {
+    Json::Object(vec![
+        ("schema".to_string(), Json::String(schema.to_string())),
+        ("version".to_string(), Json::Int(SCHEMA_VERSION)),
+        (key.to_string(), value),
+    ])
+}
+
+fn check_envelope(json: &Json, schema: &str) -> Result<(), String> {
+    if string(json, "schema")? != schema {
+        return Err(format!("expected schema {}", schema));
+    }
+    match field(json, "version")?.as_i64() {
+        Some(SCHEMA_VERSION) => Ok(()),
+        _ => Err(format!(
+            "unsupported schema version, expected {}",
+            SCHEMA_VERSION
+        )),
+    }
+}
+
+fn span(range: Range<usize>) -> Json {
+    Json::Object(vec![
+        ("start".to_string(), Json::Int(range.start as i64)),
+        ("end".to_string(), Json::Int(range.end as i64)),
+    ])
+}
+
+// {"kind": kind, fields..., "span"}
+fn node(kind: &str, range: Option<Range<usize>>, fields: Vec<(&str, Json)>) -> Json {
+    let mut object = vec![("kind".to_string(), Json::String(kind.to_string()))];
+    object.extend(fields.into_iter().map(|(k, v)| (k.to_string(), v)));
+    if let Some(range) = range {
+        object.push(("span".to_string(), span(range)));
+    }
+    Json::Object(object)
+}
+
+// 下面的函数同时遍历AST和对应的语法树节点，节点只用来取span，
+// 与AST对不上时（或者没有语法树时）省略span。对应关系与cst::lower一致
+
+fn range(n: Option<&SyntaxNode>) -> Option<Range<usize>> {
+    n.map(SyntaxNode::text_range)
+}
+
+// 节点的第i个直接子词法单元的范围
+fn token_range(n: Option<&SyntaxNode>, i: usize) -> Option<Range<usize>> {
+    n?.tokens().get(i).map(|t| t.text_range())
+}
+
+// 第i个子节点，去掉AST中没有的括号
+fn child(n: Option<&SyntaxNode>, i: usize) -> Option<SyntaxNode> {
+    let mut child = n?.children().get(i).cloned()?;
+    while child.kind() == SyntaxKind::Paren {
+        child = child.children().first()?.clone();
+    }
+    Some(child)
+}
+
+// 从第skip个开始的len个子节点，数量不符时全部为None
+fn children(n: Option<&SyntaxNode>, skip: usize, len: usize) -> Vec<Option<SyntaxNode>> {
+    let count = n.map_or(0, |n| n.children().len());
+    if count == skip + len {
+        (skip..count).map(|i| child(n, i)).collect()
+    } else {
+        vec![None; len]
+    }
+}
+
+fn write_program(program: &Program, n: Option<&SyntaxNode>) -> Json {
+    // 解析成功时除了错误节点，顶层子节点与语句一一对应
+    let nodes: Vec<SyntaxNode> = n.map_or(Vec::new(), |n| {
+        n.children()
+            .into_iter()
+            .filter(|c| c.kind() != SyntaxKind::Error)
+            .collect()
+    });
+    let statements = program
+        .statements
+        .iter()
+        .enumerate()
+        .map(|(i, stmt)| {
+            let stmt_node = nodes
+                .get(i)
+                .filter(|_| nodes.len() == program.statements.len());
+            write_statement(stmt, stmt_node)
+        })
+        .collect();
+    node(
+        "Program",
+        range(n),
+        vec![("statements", Json::Array(statements))],
+    )
+}
+
+fn write_statement(stmt: &Statement, n: Option<&SyntaxNode>) -> Json {
+    match stmt {
+        Statement::Let(let_stmt) => node(
+            "LetStatement",
+            range(n),
+            vec![
+                ("token", token_to_json(&let_stmt.token)),
+                ("name", write_identifier(&let_stmt.name, token_range(n, 1))),
+                (
+                    "value",
+                    write_expression(&let_stmt.value, child(n, 0).as_ref()),
+                ),
+            ],
+        ),
+        Statement::DestructuringLet(let_stmt) => node(
+            "DestructuringLetStatement",
+            range(n),
+            vec![
+                ("token", token_to_json(&let_stmt.token)),
+                (
+                    "pattern",
+                    write_pattern(&let_stmt.pattern, child(n, 0).as_ref()),
+                ),
+                (
+                    "value",
+                    write_expression(&let_stmt.value, child(n, 1).as_ref()),
+                ),
+            ],
+        ),
+        Statement::Return(return_stmt) => node(
+            "ReturnStatement",
+            range(n),
+            vec![
+                ("token", token_to_json(&return_stmt.token)),
+                (
+                    "return_value",
+                    write_expression(&return_stmt.return_value, child(n, 0).as_ref()),
+                ),
+            ],
+        ),
+        Statement::Expression(expr_stmt) => {
+            // match分支中的表达式语句没有单独的节点
+            let expr = match n {
+                Some(n) if n.kind() == SyntaxKind::ExpressionStatement => child(Some(n), 0),
+                _ => n.cloned(),
+            };
+            node(
+                "ExpressionStatement",
+                range(n),
+                vec![
+                    ("token", token_to_json(&expr_stmt.token)),
+                    (
+                        "expression",
+                        write_expression(&expr_stmt.expression, expr.as_ref()),
+                    ),
+                ],
+            )
+        }
+        Statement::Block(block) => write_block(block, n),
+        Statement::FunctionDeclaration(decl) => node(
+            "FunctionDeclaration",
+            range(n),
+            vec![
+                ("token", token_to_json(&decl.token)),
+                ("name", write_identifier(&decl.name, token_range(n, 1))),
+                ("function", write_function(&decl.function, n)),
+            ],
+        ),
+    }
+}
+
+fn write_block(block: &BlockStatement, n: Option<&SyntaxNode>) -> Json {
+    let nodes = children(n, 0, block.statements.len());
+    let statements = block
+        .statements
+        .iter()
+        .zip(&nodes)
+        .map(|(stmt, n)| write_statement(stmt, n.as_ref()))
+        .collect();
+    node(
+        "BlockStatement",
+        range(n),
+        vec![
+            ("token", token_to_json(&block.token)),
+            ("statements", Json::Array(statements)),
+        ],
+    )
+}
+
+// 函数声明中的函数字面量没有单独的节点，与声明共用一个
+fn write_function(function: &FunctionLiteral, n: Option<&SyntaxNode>) -> Json {
+    let params = child(n, 0);
+    let nodes = children(params.as_ref(), 0, function.parameters.len());
+    let parameters = function
+        .parameters
+        .iter()
+        .zip(&nodes)
+        .map(|(param, n)| write_parameter(param, n.as_ref()))
+        .collect();
+    node(
+        "FunctionLiteral",
+        range(n),
+        vec![
+            ("token", token_to_json(&function.token)),
+            ("parameters", Json::Array(parameters)),
+            ("body", write_block(&function.body, child(n, 1).as_ref())),
+        ],
+    )
+}
+
+// 参数的名字是词法单元，不是子节点；默认值是唯一的子节点
+fn write_parameter(param: &Parameter, n: Option<&SyntaxNode>) -> Json {
+    match param {
+        Parameter::Plain(ident) => write_identifier(ident, range(n)),
+        Parameter::Default(param) => node(
+            "DefaultParameter",
+            range(n),
+            vec![
+                ("token", token_to_json(&param.token)),
+                ("name", write_identifier(&param.name, token_range(n, 0))),
+                (
+                    "default",
+                    write_expression(&param.default, child(n, 0).as_ref()),
+                ),
+            ],
+        ),
+        Parameter::Rest(param) => node(
+            "RestParameter",
+            range(n),
+            vec![
+                ("token", token_to_json(&param.token)),
+                ("name", write_identifier(&param.name, token_range(n, 1))),
+            ],
+        ),
+    }
+}
+
+fn write_identifier(ident: &Identifier, range: Option<Range<usize>>) -> Json {
+    node(
+        "Identifier",
+        range,
+        vec![
+            ("token", token_to_json(&ident.token)),
+            ("value", Json::String(ident.value.clone())),
+        ],
+    )
+}
+
+fn write_expressions(exprs: &[Expression], nodes: &[Option<SyntaxNode>]) -> Json {
+    Json::Array(
+        exprs
+            .iter()
+            .zip(nodes)
+            .map(|(expr, n)| write_expression(expr, n.as_ref()))
+            .collect(),
+    )
+}
+
+fn write_expression(expr: &Expression, n: Option<&SyntaxNode>) -> Json {
+    match expr {
+        Expression::Identifier(ident) => write_identifier(ident, range(n)),
+        Expression::Integer(int) => node(
+            "IntegerLiteral",
+            range(n),
+            vec![
+                ("token", token_to_json(&int.token)),
+                ("value", Json::Int(int.value)),
+            ],
+        ),
+        Expression::String(string) => node(
+            "StringLiteral",
+            range(n),
+            vec![
+                ("token", token_to_json(&string.token)),
+                ("value", Json::String(string.value.clone())),
+            ],
+        ),
+        Expression::Boolean(boolean) => node(
+            "Boolean",
+            range(n),
+            vec![
+                ("token", token_to_json(&boolean.token)),
+                ("value", Json::Bool(boolean.value)),
+            ],
+        ),
+        Expression::Prefix(prefix) => node(
+            "PrefixExpression",
+            range(n),
+            vec![
+                ("token", token_to_json(&prefix.token)),
+                ("operator", Json::String(prefix.operator.clone())),
+                (
+                    "right",
+                    write_expression(&prefix.right, child(n, 0).as_ref()),
+                ),
+            ],
+        ),
+        Expression::Infix(infix) => node(
+            "InfixExpression",
+            range(n),
+            vec![
+                ("token", token_to_json(&infix.token)),
+                ("left", write_expression(&infix.left, child(n, 0).as_ref())),
+                ("operator", Json::String(infix.operator.clone())),
+                (
+                    "right",
+                    write_expression(&infix.right, child(n, 1).as_ref()),
+                ),
+            ],
+        ),
+        Expression::If(if_expr) => node(
+            "IfExpression",
+            range(n),
+            vec![
+                ("token", token_to_json(&if_expr.token)),
+                (
+                    "condition",
+                    write_expression(&if_expr.condition, child(n, 0).as_ref()),
+                ),
+                (
+                    "consequence",
+                    write_block(&if_expr.consequence, child(n, 1).as_ref()),
+                ),
+                (
+                    "alternative",
+                    match &if_expr.alternative {
+                        Some(alt) => write_block(alt, child(n, 2).as_ref()),
+                        None => Json::Null,
+                    },
+                ),
+            ],
+        ),
+        Expression::Function(function) => write_function(function, n),
+        Expression::Call(call) => node(
+            "CallExpression",
+            range(n),
+            vec![
+                ("token", token_to_json(&call.token)),
+                (
+                    "function",
+                    write_expression(&call.function, child(n, 0).as_ref()),
+                ),
+                (
+                    "arguments",
+                    write_expressions(&call.arguments, &children(n, 1, call.arguments.len())),
+                ),
+            ],
+        ),
+        Expression::Array(array) => node(
+            "ArrayLiteral",
+            range(n),
+            vec![
+                ("token", token_to_json(&array.token)),
+                (
+                    "elements",
+                    write_expressions(&array.elements, &children(n, 0, array.elements.len())),
+                ),
+            ],
+        ),
+        Expression::Hash(hash) => {
+            let nodes = children(n, 0, hash.pairs.len() * 2);
+            let pairs = hash
+                .pairs
+                .iter()
+                .zip(nodes.chunks(2))
+                .map(|((key, value), n)| {
+                    Json::Object(vec![
+                        ("key".to_string(), write_expression(key, n[0].as_ref())),
+                        ("value".to_string(), write_expression(value, n[1].as_ref())),
+                    ])
+                })
+                .collect();
+            node(
+                "HashLiteral",
+                range(n),
+                vec![
+                    ("token", token_to_json(&hash.token)),
+                    ("pairs", Json::Array(pairs)),
+                ],
+            )
+        }
+        Expression::Match(match_expr) => {
+            let nodes = children(n, 1, match_expr.arms.len());
+            let arms = match_expr
+                .arms
+                .iter()
+                .zip(&nodes)
+                .map(|(arm, n)| write_match_arm(arm, n.as_ref()))
+                .collect();
+            node(
+                "MatchExpression",
+                range(n),
+                vec![
+                    ("token", token_to_json(&match_expr.token)),
+                    (
+                        "subject",
+                        write_expression(&match_expr.subject, child(n, 0).as_ref()),
+                    ),
+                    ("arms", Json::Array(arms)),
+                ],
+            )
+        }
+        Expression::Spread(spread) => node(
+            "SpreadExpression",
+            range(n),
+            vec![
+                ("token", token_to_json(&spread.token)),
+                (
+                    "value",
+                    write_expression(&spread.value, child(n, 0).as_ref()),
+                ),
+            ],
+        ),
+    }
+}
+
+fn write_match_arm(arm: &MatchArm, n: Option<&SyntaxNode>) -> Json {
+    let count = n.map_or(0, |n| n.children().len());
+    node(
+        "MatchArm",
+        range(n),
+        vec![
+            ("pattern", write_pattern(&arm.pattern, child(n, 0).as_ref())),
+            (
+                "guard",
+                match &arm.guard {
+                    Some(guard) => write_expression(guard, child(n, 1).as_ref()),
+                    None => Json::Null,
+                },
+            ),
+            (
+                "body",
+                write_statement(&arm.body, child(n, count.wrapping_sub(1)).as_ref()),
+            ),
+        ],
+    )
+}
+
+fn write_pattern(pattern: &Pattern, n: Option<&SyntaxNode>) -> Json {
+    match pattern {
+        Pattern::Wildcard => node("WildcardPattern", range(n), vec![]),
+        Pattern::Integer(value) => node(
+            "IntegerPattern",
+            range(n),
+            vec![("value", Json::Int(*value))],
+        ),
+        Pattern::String(value) => node(
+            "StringPattern",
+            range(n),
+            vec![("value", Json::String(value.clone()))],
+        ),
+        Pattern::Boolean(value) => node(
+            "BooleanPattern",
+            range(n),
+            vec![("value", Json::Bool(*value))],
+        ),
+        Pattern::Identifier(ident) => node(
+            "IdentifierPattern",
+            range(n),
+            vec![("name", write_identifier(ident, range(n)))],
+        ),
+        Pattern::Array(array) => {
+            let nodes = children(n, 0, array.elements.len());
+            let elements = array
+                .elements
+                .iter()
+                .zip(&nodes)
+                .map(|(element, n)| write_pattern(element, n.as_ref()))
+                .collect();
+            // 剩余绑定是...后面的词法单元
+            let rest_range = n.and_then(|n| {
+                let tokens = n.tokens();
+                let i = tokens
+                    .iter()
+                    .position(|t| t.kind() == TokenType::ELLIPSIS)?;
+                tokens.get(i + 1).map(|t| t.text_range())
+            });
+            node(
+                "ArrayPattern",
+                range(n),
+                vec![
+                    ("token", token_to_json(&array.token)),
+                    ("elements", Json::Array(elements)),
+                    (
+                        "rest",
+                        match &array.rest {
+                            Some(rest) => write_identifier(rest, rest_range),
+                            None => Json::Null,
+                        },
+                    ),
+                ],
+            )
+        }
+        Pattern::Hash(hash) => {
+            let fields = children(n, 0, hash.pairs.len());
+            let pairs = hash
+                .pairs
+                .iter()
+                .zip(&fields)
+                .map(|((key, value), field)| {
+                    // 简写形式 {name} 的值就是字段本身
+                    let value_node = match child(field.as_ref(), 0) {
+                        Some(value) => Some(value),
+                        None => field.clone(),
+                    };
+                    Json::Object(vec![
+                        ("key".to_string(), Json::String(key.clone())),
+                        (
+                            "value".to_string(),
+                            write_pattern(value, value_node.as_ref()),
+                        ),
+                    ])
+                })
+                .collect();
+            node(
+                "HashPattern",
+                range(n),
+                vec![
+                    ("token", token_to_json(&hash.token)),
+                    ("pairs", Json::Array(pairs)),
+                ],
+            )
+        }
+    }
+}
+
+// 读取字段，错误信息带上节点种类
+
+fn field<'a>(json: &'a Json, key: &str) -> Result<&'a Json, String> {
+    if !matches!(json, Json::Object(_)) {
+        return Err(format!("expected an object with field `{}`", key));
+    }
+    json.get(key).ok_or_else(|| {
+        let kind = json.get("kind").and_then(Json::as_str).unwrap_or("object");
+        format!("{}: missing field `{}`", kind, key)
+    })
+}
+
+fn invalid(json: &Json, key: &str, expected: &str) -> String {
+    let kind = json.get("kind").and_then(Json::as_str).unwrap_or("object");
+    format!("{}: field `{}` should be {}", kind, key, expected)
+}
+
+fn string(json: &Json, key: &str) -> Result<String, String> {
+    field(json, key)?
+        .as_str()
+        .map(str::to_string)
+        .ok_or_else(|| invalid(json, key, "a string"))
+}
+
+fn integer(json: &Json, key: &str) -> Result<i64, String> {
+    field(json, key)?
+        .as_i64()
+        .ok_or_else(|| invalid(json, key, "an integer"))
+}
+
+fn boolean(json: &Json, key: &str) -> Result<bool, String> {
+    field(json, key)?
+        .as_bool()
+        .ok_or_else(|| invalid(json, key, "a boolean"))
+}
+
+fn array<'a>(json: &'a Json, key: &str) -> Result<&'a [Json], String> {
+    field(json, key)?
+        .as_array()
+        .ok_or_else(|| invalid(json, key, "an array"))
+}
+
+// 可以为null的字段
+fn optional<'a>(json: &'a Json, key: &str) -> Result<Option<&'a Json>, String> {
+    match field(json, key)? {
+        Json::Null => Ok(None),
+        value => Ok(Some(value)),
+    }
+}
+
+fn token(json: &Json) -> Result<Token, String> {
+    token_from_json(field(json, "token")?)
+}
+
+fn expression(json: &Json, key: &str) -> Result<Box<Expression>, String> {
+    read_expression(field(json, key)?).map(Box::new)
+}
+
+fn kind(json: &Json) -> Result<String, String> {
+    string(json, "kind")
+}
+
+fn read_program(json: &Json) -> Result<Program, String> {
+    if kind(json)? != "Program" {
+        return Err("expected a Program node".to_string());
+    }
+    Ok(Program {
+        statements: array(json, "statements")?
+            .iter()
+            .map(read_statement)
+            .collect::<Result<_, _>>()?,
+    })
+}
+
+fn read_statement(json: &Json) -> Result<Statement, String> {
+    let stmt = match kind(json)?.as_str() {
+        "LetStatement" => Statement::Let(LetStatement {
+            token: token(json)?,
+            name: Box::new(read_identifier(field(json, "name")?)?),
+            value: expression(json, "value")?,
+        }),
+        "DestructuringLetStatement" => Statement::DestructuringLet(DestructuringLetStatement {
+            token: token(json)?,
+            pattern: read_pattern(field(json, "pattern")?)?,
+            value: expression(json, "value")?,
+        }),
+        "ReturnStatement" => Statement::Return(ReturnStatement {
+            token: token(json)?,
+            return_value: expression(json, "return_value")?,
+        }),
+        "ExpressionStatement" => Statement::Expression(ExpressionStatement {
+            token: token(json)?,
+            expression: expression(json, "expression")?,
+        }),
+        "BlockStatement" => Statement::Block(read_block(json)?),
+        "FunctionDeclaration" => Statement::FunctionDeclaration(FunctionDeclaration {
+            token: token(json)?,
+            name: read_identifier(field(json, "name")?)?,
+            function: read_function(field(json, "function")?)?,
+        }),
+        other => return Err(format!("unknown statement kind: {}", other)),
+    };
+    Ok(stmt)
+}
+
+fn read_block(json: &Json) -> Result<BlockStatement, String> {
+    if kind(json)? != "BlockStatement" {
+        return Err("expected a BlockStatement node".to_string());
+    }
+    Ok(BlockStatement {
+        token: token(json)?,
+        statements: array(json, "statements")?
+            .iter()
+            .map(read_statement)
+            .collect::<Result<_, _>>()?,
+    })
+}
+
+fn read_function(json: &Json) -> Result<FunctionLiteral, String> {
+    if kind(json)? != "FunctionLiteral" {
+        return Err("expected a FunctionLiteral node".to_string());
+    }
+    Ok(FunctionLiteral {
+        token: token(json)?,
+        parameters: array(json, "parameters")?
+            .iter()
+            .map(read_parameter)
+            .collect::<Result<_, _>>()?,
+        body: read_block(field(json, "body")?)?,
+    })
+}
+
+fn read_parameter(json: &Json) -> Result<Parameter, String> {
+    let param = match kind(json)?.as_str() {
+        "Identifier" => Parameter::Plain(read_identifier(json)?),
+        "DefaultParameter" => Parameter::Default(DefaultParameter {
+            token: token(json)?,
+            name: read_identifier(field(json, "name")?)?,
+            default: expression(json, "default")?,
+        }),
+        "RestParameter" => Parameter::Rest(RestParameter {
+            token: token(json)?,
+            name: read_identifier(field(json, "name")?)?,
+        }),
+        other => return Err(format!("unknown parameter kind: {}", other)),
+    };
+    Ok(param)
+}
+
+fn read_identifier(json: &Json) -> Result<Identifier, String> {
+    if kind(json)? != "Identifier" {
+        return Err("expected an Identifier node".to_string());
+    }
+    Ok(Identifier {
+        token: token(json)?,
+        value: string(json, "value")?,
+    })
+}
+
+fn read_expressions(json: &Json, key: &str) -> Result<Vec<Expression>, String> {
+    array(json, key)?.iter().map(read_expression).collect()
+}
+
+fn read_expression(json: &Json) -> Result<Expression, String> {
+    let expr = match kind(json)?.as_str() {
+        "Identifier" => Expression::Identifier(read_identifier(json)?),
+        "IntegerLiteral" => Expression::Integer(IntegerLiteral {
+            token: token(json)?,
+            value: integer(json, "value")?,
+        }),
+        "StringLiteral" => Expression::String(StringLiteral {
+            token: token(json)?,
+            value: string(json, "value")?,
+        }),
+        "Boolean" => Expression::Boolean(Boolean {
+            token: token(json)?,
+            value: boolean(json, "value")?,
+        }),
+        "PrefixExpression" => Expression::Prefix(PrefixExpression {
+            token: token(json)?,
+            operator: string(json, "operator")?,
+            right: expression(json, "right")?,
+        }),
+        "InfixExpression" => Expression::Infix(InfixExpression {
+            token: token(json)?,
+            left: expression(json, "left")?,
+            operator: string(json, "operator")?,
+            right: expression(json, "right")?,
+        }),
+        "IfExpression" => Expression::If(IfExpression {
+            token: token(json)?,
+            condition: expression(json, "condition")?,
+            consequence: read_block(field(json, "consequence")?)?,
+            alternative: optional(json, "alternative")?.map(read_block).transpose()?,
+        }),
+        "FunctionLiteral" => Expression::Function(read_function(json)?),
+        "CallExpression" => Expression::Call(CallExpression {
+            token: token(json)?,
+            function: expression(json, "function")?,
+            arguments: read_expressions(json, "arguments")?,
+        }),
+        "ArrayLiteral" => Expression::Array(ArrayLiteral {
+            token: token(json)?,
+            elements: read_expressions(json, "elements")?,
+        }),
+        "HashLiteral" => Expression::Hash(HashLiteral {
+            token: token(json)?,
+            pairs: array(json, "pairs")?
+                .iter()
+                .map(|pair| Ok((*expression(pair, "key")?, *expression(pair, "value")?)))
+                .collect::<Result<_, String>>()?,
+        }),
+        "MatchExpression" => Expression::Match(MatchExpression {
+            token: token(json)?,
+            subject: expression(json, "subject")?,
+            arms: array(json, "arms")?
+                .iter()
+                .map(read_match_arm)
+                .collect::<Result<_, _>>()?,
+        }),
+        "SpreadExpression" => Expression::Spread(SpreadExpression {
+            token: token(json)?,
+            value: expression(json, "value")?,
+        }),
+        other => return Err(format!("unknown expression kind: {}", other)),
+    };
+    Ok(expr)
+}
+
+fn read_match_arm(json: &Json) -> Result<MatchArm, String> {
+    if kind(json)? != "MatchArm" {
+        return Err("expected a MatchArm node".to_string());
+    }
+    Ok(MatchArm {
+        pattern: read_pattern(field(json, "pattern")?)?,
+        guard: optional(json, "guard")?.map(read_expression).transpose()?,
+        body: read_statement(field(json, "body")?)?,
+    })
+}
+
+fn read_pattern(json: &Json) -> Result<Pattern, String> {
+    let pattern = match kind(json)?.as_str() {
+        "WildcardPattern" => Pattern::Wildcard,
+        "IntegerPattern" => Pattern::Integer(integer(json, "value")?),
+        "StringPattern" => Pattern::String(string(json, "value")?),
+        "BooleanPattern" => Pattern::Boolean(boolean(json, "value")?),
+        "IdentifierPattern" => Pattern::Identifier(read_identifier(field(json, "name")?)?),
+        "ArrayPattern" => Pattern::Array(ArrayPattern {
+            token: token(json)?,
+            elements: array(json, "elements")?
+                .iter()
+                .map(read_pattern)
+                .collect::<Result<_, _>>()?,
+            rest: optional(json, "rest")?.map(read_identifier).transpose()?,
+        }),
+        "HashPattern" => Pattern::Hash(HashPattern {
+            token: token(json)?,
+            pairs: array(json, "pairs")?
+                .iter()
+                .map(|pair| Ok((string(pair, "key")?, read_pattern(field(pair, "value")?)?)))
+                .collect::<Result<_, String>>()?,
+        }),
+        other => return Err(format!("unknown pattern kind: {}", other)),
+    };
+    Ok(pattern)
+}
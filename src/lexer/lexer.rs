@@ -1,40 +1,99 @@
-use crate::token::token::{Token, TokenType};
+use crate::token::token::{Position, Token, TokenType};
 use std::collections::HashMap;
+use std::ops::Range;
+
+// 关键字表
+const KEYWORDS: [(&str, TokenType); 8] = [
+    ("fn", TokenType::FN),
+    ("let", TokenType::LET),
+    ("if", TokenType::IF),
+    ("else", TokenType::ELSE),
+    ("return", TokenType::RETURN),
+    ("true", TokenType::TRUE),
+    ("false", TokenType::FALSE),
+    ("match", TokenType::MATCH),
+];
 
 pub struct Lexer {
     keywords: HashMap<String, TokenType>,
-    input: String,
+    input: Vec<char>,
     position: usize,
     read_position: usize,
     ch: char,
+    // 为true时把 // 注释作为COMMENT词法单元返回，否则当作空白跳过
+    keep_comments: bool,
+    // 最近返回的词法单元之前跳过的换行数
+    newlines_before: usize,
+    // 最近返回的词法单元的起始位置
+    token_start: usize,
+    // 当前字符所在的行号和这一行开始的位置，用于计算行列号
+    line: usize,
+    line_start: usize,
+    token_position: Position,
 }
 
 impl Lexer {
     pub fn new(input: String) -> Lexer {
-        let mut keywords = HashMap::new();
-        keywords.insert("fn".to_string(), TokenType::FN);
-        keywords.insert("let".to_string(), TokenType::LET);
-        keywords.insert("if".to_string(), TokenType::IF);
-        keywords.insert("else".to_string(), TokenType::ELSE);
-        keywords.insert("return".to_string(), TokenType::RETURN);
-        keywords.insert("true".to_string(), TokenType::TRUE);
-        keywords.insert("false".to_string(), TokenType::FALSE);
+        let keywords = KEYWORDS
+            .iter()
+            .map(|(name, token_type)| (name.to_string(), *token_type))
+            .collect();
 
         let mut l = Lexer {
-            input,
+            input: input.chars().collect(),
             position: 0,
             read_position: 0,
             ch: '\0',
             keywords,
+            keep_comments: false,
+            newlines_before: 0,
+            token_start: 0,
+            line: 1,
+            line_start: 0,
+            token_position: Position { line: 1, column: 1 },
         };
         l.read_char();
         l
     }
+
+    // 所有关键字，REPL补全时使用
+    pub fn keywords() -> impl Iterator<Item = &'static str> {
+        KEYWORDS.iter().map(|(name, _)| *name)
+    }
+
+    pub fn set_keep_comments(&mut self, keep: bool) {
+        self.keep_comments = keep;
+    }
+
+    pub fn newlines_before(&self) -> usize {
+        self.newlines_before
+    }
+
+    // 最近返回的词法单元在输入中的范围，按字符计，不包含前面的空白和注释
+    pub fn token_range(&self) -> Range<usize> {
+        let len = self.input.len();
+        self.token_start.min(len)..self.position.min(len)
+    }
+
+    // 最近返回的词法单元的行列号
+    pub fn token_position(&self) -> Position {
+        self.token_position
+    }
+
+    // 按字符范围取输入的原文
+    pub fn slice(&self, range: Range<usize>) -> String {
+        self.input[range].iter().collect()
+    }
+
     fn read_char(&mut self) {
+        if self.ch == '\n' {
+            self.line += 1;
+            self.line_start = self.read_position;
+        }
         if self.read_position >= self.input.len() {
             self.ch = '\0';
         } else {
-            self.ch = self.input.chars().nth(self.read_position).unwrap_or('\0');
+            self.ch = self.input[self.read_position];
         }
         self.position = self.read_position;
         self.read_position += 1;
@@ -52,7 +111,7 @@ impl Lexer {
         if self.read_position >= self.input.len() {
             '\0'
         } else {
-            self.input.chars().nth(self.read_position).unwrap_or('\0')
+            self.input[self.read_position]
         }
     }
 
@@ -61,7 +120,9 @@ impl Lexer {
         while Self::is_letter(self.ch) {
             self.read_char();
         }
-        self.input[identifier_start_position..self.position].to_string()
+        self.input[identifier_start_position..self.position]
+            .iter()
+            .collect()
     }
 
     fn read_number(&mut self) -> String {
@@ -69,13 +130,39 @@ impl Lexer {
         while Self::is_digit(self.ch) {
             self.read_char();
         }
-        self.input[number_start_position..self.position].to_string()
+        self.input[number_start_position..self.position]
+            .iter()
+            .collect()
     }
 
+    // 跳过空白并统计换行数，不保留注释时一并跳过注释
     fn skip_whitespace(&mut self) {
-        while self.ch.is_whitespace() {
+        self.newlines_before = 0;
+        loop {
+            while self.ch.is_whitespace() {
+                if self.ch == '\n' {
+                    self.newlines_before += 1;
+                }
+                self.read_char();
+            }
+            if !self.keep_comments && self.ch == '/' && self.peek_char() == '/' {
+                self.read_comment();
+                continue;
+            }
+            break;
+        }
+    }
+
+    // 从 // 读到行尾，不包含换行符
+    fn read_comment(&mut self) -> String {
+        let comment_start_position = self.position;
+        while self.ch != '\n' && self.ch != '\0' {
             self.read_char();
         }
+        let comment: String = self.input[comment_start_position..self.position]
+            .iter()
+            .collect();
+        comment.trim_end().to_string()
     }
 
     fn read_string(&mut self) -> String {
@@ -84,13 +171,20 @@ impl Lexer {
         while self.ch != '"' && self.ch != '\0' {
             self.read_char();
         }
-        let str = self.input[string_start_position..self.position].to_string();
+        let str = self.input[string_start_position..self.position]
+            .iter()
+            .collect();
         // 跳过结束的引号
         self.read_char();
         str
     }
     pub fn next_token(&mut self) -> Token {
         self.skip_whitespace();
+        self.token_start = self.position;
+        self.token_position = Position {
+            line: self.line,
+            column: self.position - self.line_start + 1,
+        };
 
         let tok: Token;
 
@@ -102,6 +196,12 @@ impl Lexer {
                         token_type: TokenType::EQ,
                         literal: "==".to_string(),
                     };
+                } else if self.peek_char() == '>' {
+                    self.read_char();
+                    tok = Token {
+                        token_type: TokenType::FATARROW,
+                        literal: "=>".to_string(),
+                    };
                 } else {
                     tok = Token {
                         token_type: TokenType::ASSIGN,
@@ -177,6 +277,12 @@ impl Lexer {
                     literal: "*".to_string(),
                 };
             }
+            '/' if self.peek_char() == '/' => {
+                return Token {
+                    token_type: TokenType::COMMENT,
+                    literal: self.read_comment(),
+                };
+            }
             '/' => {
                 tok = Token {
                     token_type: TokenType::SLASH,
@@ -207,6 +313,29 @@ impl Lexer {
                     literal: "]".to_string(),
                 };
             }
+            '.' => {
+                // 只有连续三个点才是合法的展开符
+                if self.peek_char() == '.' {
+                    self.read_char();
+                    if self.peek_char() == '.' {
+                        self.read_char();
+                        tok = Token {
+                            token_type: TokenType::ELLIPSIS,
+                            literal: "...".to_string(),
+                        };
+                    } else {
+                        tok = Token {
+                            token_type: TokenType::ILLEGAL,
+                            literal: "..".to_string(),
+                        };
+                    }
+                } else {
+                    tok = Token {
+                        token_type: TokenType::ILLEGAL,
+                        literal: ".".to_string(),
+                    };
+                }
+            }
             ':' => {
                 tok = Token {
                     token_type: TokenType::COLON,
@@ -1,8 +1,17 @@
+#![allow(clippy::module_inception)]
+
 pub mod ast;
+pub mod cli;
+pub mod cst;
+pub mod dot;
+pub mod dump;
 pub mod evaluator;
+pub mod formatter;
+pub mod json;
 pub mod lexer;
 pub mod object;
 pub mod parser;
+pub mod prelude;
 pub mod repl;
 pub mod tests;
 pub mod token;
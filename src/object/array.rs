@@ -0,0 +1,37 @@
+use super::Value;
+use super::value::{MAX_INSPECT_DEPTH, defer_all, drop_values};
+
+pub struct Array {
+    pub elements: Vec<Value>,
+}
+
+impl Array {
+    pub fn new(elements: Vec<Value>) -> Self {
+        Array { elements }
+    }
+
+    pub fn inspect(&self) -> String {
+        self.inspect_nested(0)
+    }
+
+    pub(super) fn inspect_nested(&self, depth: usize) -> String {
+        if depth >= MAX_INSPECT_DEPTH {
+            return "[...]".to_string();
+        }
+        let elements: Vec<String> = self
+            .elements
+            .iter()
+            .map(|e| e.inspect_nested(depth + 1))
+            .collect();
+        format!("[{}]", elements.join(", "))
+    }
+}
+
+// 嵌套的数组逐层释放，不递归
+impl Drop for Array {
+    fn drop(&mut self) {
+        let mut values = Vec::new();
+        defer_all(self.elements.drain(..), &mut values);
+        drop_values(values);
+    }
+}
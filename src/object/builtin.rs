@@ -0,0 +1,38 @@
+// 用Rust实现的基本函数，载入prelude时绑定到环境中，prelude中的其他函数用它们写成。
+// Monkey没有下标表达式，数组的长度和切片只能由宿主提供
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Builtin {
+    Len,
+    Slice,
+}
+
+impl Builtin {
+    pub const ALL: [Builtin; 2] = [Builtin::Len, Builtin::Slice];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Builtin::Len => "len",
+            Builtin::Slice => "slice",
+        }
+    }
+
+    fn parameters(&self) -> &'static [&'static str] {
+        match self {
+            Builtin::Len => &["xs"],
+            Builtin::Slice => &["xs", "start", "end"],
+        }
+    }
+
+    pub fn arity(&self) -> usize {
+        self.parameters().len()
+    }
+
+    // 与Function::signature相同的形式，如 fn slice(xs, start, end)
+    pub fn signature(&self) -> String {
+        format!("fn {}({})", self.name(), self.parameters().join(", "))
+    }
+
+    pub fn inspect(&self) -> String {
+        format!("{} {{ <builtin> }}", self.signature())
+    }
+}
@@ -1,25 +1,353 @@
+use std::cell::RefCell;
 use std::collections::HashMap;
+use std::mem;
+use std::rc::{Rc, Weak};
 
-use crate::ast::Identifier;
+use super::value::{defer, drop_values};
+use super::{Array, Function, Hash, Value};
 
-use super::{Object, ObjectType};
+struct Scope {
+    store: HashMap<String, Value>,
+    outer: Option<Environment>,
+    captured: bool, // 已经被函数捕获并登记
+}
 
+// Environment是作用域的共享句柄，clone后指向同一个作用域，
+// 这样函数对象可以捕获定义时的环境（闭包）
+#[derive(Clone)]
 pub struct Environment {
-    store: HashMap<String, Box<dyn Object>>,
+    scope: Rc<RefCell<Scope>>,
+}
+
+// 不阻止作用域释放的句柄
+#[derive(Clone)]
+pub struct WeakEnvironment {
+    scope: Weak<RefCell<Scope>>,
+}
+
+impl WeakEnvironment {
+    pub fn upgrade(&self) -> Option<Environment> {
+        self.scope.upgrade().map(|scope| Environment { scope })
+    }
 }
+
 impl Environment {
     pub fn new() -> Self {
-        Environment {
+        Self::with_outer(None)
+    }
+
+    // 创建以outer为外层作用域的新环境，用于函数调用
+    pub fn new_enclosed(outer: &Environment) -> Self {
+        Self::with_outer(Some(outer.clone()))
+    }
+
+    fn with_outer(outer: Option<Environment>) -> Self {
+        let scope = Rc::new(RefCell::new(Scope {
             store: HashMap::new(),
+            outer,
+            captured: false,
+        }));
+        Environment { scope }
+    }
+
+    // 函数捕获这个环境时调用，返回共享同一作用域的句柄。
+    // 只有被捕获的作用域可能处在环中，第一次捕获时登记以便回收
+    pub fn capture(&self) -> Environment {
+        let first = !mem::replace(&mut self.scope.borrow_mut().captured, true);
+        if first {
+            register(&self.scope);
+        }
+        self.clone()
+    }
+
+    pub fn downgrade(&self) -> WeakEnvironment {
+        WeakEnvironment {
+            scope: Rc::downgrade(&self.scope),
+        }
+    }
+
+    // 从内向外查找变量，堆上的值只复制Rc
+    pub fn get(&self, name: &str) -> Option<Value> {
+        let scope = self.scope.borrow();
+        match scope.store.get(name) {
+            Some(val) => Some(val.clone()),
+            None => scope.outer.as_ref().and_then(|outer| outer.get(name)),
+        }
+    }
+
+    // 总是绑定在当前作用域
+    pub fn set(&mut self, name: &str, val: Value) {
+        self.scope.borrow_mut().store.insert(name.to_string(), val);
+    }
+
+    // 当前作用域中的绑定，按名字排序
+    pub fn bindings(&self) -> Vec<(String, Value)> {
+        let mut bindings: Vec<(String, Value)> = self
+            .scope
+            .borrow()
+            .store
+            .iter()
+            .map(|(name, value)| (name.clone(), value.clone()))
+            .collect();
+        bindings.sort_by(|a, b| a.0.cmp(&b.0));
+        bindings
+    }
+
+    // 只移除当前作用域中的绑定
+    pub fn remove(&mut self, name: &str) -> Option<Value> {
+        self.scope.borrow_mut().store.remove(name)
+    }
+}
+
+// 调用链很长的作用域和闭包逐层释放，不递归
+impl Drop for Scope {
+    fn drop(&mut self) {
+        let mut values = Vec::new();
+        for (_, value) in self.store.drain() {
+            defer(value, &mut values);
+        }
+        if let Some(outer) = self.outer.take() {
+            outer.release(&mut values);
+        }
+        drop_values(values);
+    }
+}
+
+impl Environment {
+    // 释放这个句柄。它是作用域的最后一个引用时，把作用域中的值放进values交给调用者释放，
+    // 再沿外层作用域继续
+    pub(super) fn release(self, values: &mut Vec<Value>) {
+        let mut env = Some(self);
+        while let Some(Environment { scope }) = env.take() {
+            let Some(scope) = Rc::into_inner(scope) else {
+                return;
+            };
+            let mut scope = scope.into_inner();
+            for (_, value) in scope.store.drain() {
+                defer(value, values);
+            }
+            env = scope.outer.take();
+        }
+    }
+}
+
+impl Default for Environment {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// 作用域中的函数捕获这个作用域时形成Rc环（例如函数内定义的闭包），引用计数无法释放。
+// 被函数捕获过的作用域登记在这里，环中至少有一个这样的作用域。上次回收之后登记的数量
+// 达到上次遍历中存活的节点数时再回收一次，回收的开销分摊到每次登记上
+thread_local! {
+    static REGISTRY: RefCell<Registry> = const {
+        RefCell::new(Registry {
+            scopes: Vec::new(),
+            registered: 0,
+            threshold: MIN_THRESHOLD,
+        })
+    };
+}
+
+const MIN_THRESHOLD: usize = 1024;
+
+struct Registry {
+    scopes: Vec<Weak<RefCell<Scope>>>,
+    registered: usize, // 上次回收之后登记的作用域数
+    threshold: usize,  // registered达到这个数量时回收
+}
+
+fn register(scope: &Rc<RefCell<Scope>>) {
+    let full = REGISTRY.with(|registry| {
+        let mut registry = registry.borrow_mut();
+        registry.scopes.push(Rc::downgrade(scope));
+        registry.registered += 1;
+        registry.registered >= registry.threshold
+    });
+    if full {
+        collect_cycles();
+    }
+}
+
+// 当前线程中被函数捕获过、还没有释放的作用域数
+pub fn live_scopes() -> usize {
+    REGISTRY.with(|registry| {
+        let mut registry = registry.borrow_mut();
+        registry.scopes.retain(|scope| scope.strong_count() > 0);
+        registry.scopes.len()
+    })
+}
+
+// 释放只被环引用的作用域，返回释放的数量。
+// 从登记的作用域出发，把能到达的作用域、函数、数组和哈希看作图中的节点，
+// 数出每个节点被其他节点引用的次数，引用计数比它大的节点还被求值器或宿主持有。
+// 从这些节点出发到达不了的作用域清空绑定以断开环
+pub fn collect_cycles() -> usize {
+    let scopes: Vec<Rc<RefCell<Scope>>> = REGISTRY.with(|registry| {
+        let mut registry = registry.borrow_mut();
+        registry.scopes.retain(|scope| scope.strong_count() > 0);
+        registry.scopes.iter().filter_map(Weak::upgrade).collect()
+    });
+
+    let mut graph = Graph::default();
+    for scope in scopes {
+        graph.add(Node::Scope(scope));
+    }
+    // 新发现的节点追加在后面，按编号依次展开，不会递归
+    let mut roots = Vec::new();
+    let mut i = 0;
+    while i < graph.nodes.len() {
+        // 正在修改的作用域无法遍历，当作被外部持有
+        if !graph.scan(i) {
+            roots.push(i);
+        }
+        i += 1;
+    }
+
+    roots.extend((0..graph.nodes.len()).filter(|&i| graph.strong[i] > graph.internal[i]));
+    let mut live = vec![false; graph.nodes.len()];
+    while let Some(i) = roots.pop() {
+        if !mem::replace(&mut live[i], true) {
+            roots.extend(graph.edges[i].iter().copied());
+        }
+    }
+
+    let mut garbage = Vec::new();
+    for (node, live) in graph.nodes.iter().zip(&live) {
+        if let (Node::Scope(scope), false) = (node, live) {
+            let mut scope = scope.borrow_mut();
+            garbage.push((mem::take(&mut scope.store), scope.outer.take()));
         }
     }
+    let freed = garbage.len();
+    let survived = live.iter().filter(|&&live| live).count();
+    drop(graph);
+    drop(garbage);
 
-    pub fn get(&self, name: &str) -> Option<&Box<dyn Object>> {
-        self.store.get(name)
+    REGISTRY.with(|registry| {
+        let mut registry = registry.borrow_mut();
+        registry.registered = 0;
+        registry.threshold = survived.max(MIN_THRESHOLD);
+    });
+    freed
+}
+
+// 图中的节点，持有一个引用使遍历期间节点不会被释放
+enum Node {
+    Scope(Rc<RefCell<Scope>>),
+    Function(Rc<Function>),
+    Array(Rc<Array>),
+    Hash(Rc<Hash>),
+}
+
+impl Node {
+    fn ptr(&self) -> *const () {
+        match self {
+            Node::Scope(scope) => Rc::as_ptr(scope) as *const (),
+            Node::Function(function) => Rc::as_ptr(function) as *const (),
+            Node::Array(array) => Rc::as_ptr(array) as *const (),
+            Node::Hash(hash) => Rc::as_ptr(hash) as *const (),
+        }
+    }
+
+    fn strong_count(&self) -> usize {
+        match self {
+            Node::Scope(scope) => Rc::strong_count(scope),
+            Node::Function(function) => Rc::strong_count(function),
+            Node::Array(array) => Rc::strong_count(array),
+            Node::Hash(hash) => Rc::strong_count(hash),
+        }
+    }
+}
+
+// 引用图
+#[derive(Default)]
+struct Graph {
+    ids: HashMap<*const (), usize>,
+    nodes: Vec<Node>,
+    strong: Vec<usize>,   // 引用计数，不含nodes持有的引用
+    internal: Vec<usize>, // 被图中其他节点引用的次数
+    edges: Vec<Vec<usize>>,
+}
+
+impl Graph {
+    // 返回节点编号，新节点追加到最后
+    fn add(&mut self, node: Node) -> usize {
+        if let Some(&id) = self.ids.get(&node.ptr()) {
+            return id;
+        }
+        let id = self.nodes.len();
+        self.ids.insert(node.ptr(), id);
+        self.strong.push(node.strong_count() - 1);
+        self.internal.push(0);
+        self.edges.push(Vec::new());
+        self.nodes.push(node);
+        id
+    }
+
+    fn edge(&mut self, from: usize, to: usize) {
+        self.edges[from].push(to);
+        self.internal[to] += 1;
     }
 
-    pub fn set(&mut self, name: &str, val: Box<dyn Object>) -> Option<&Box<dyn Object>> {
-        self.store.insert(name.to_string(), val);
-        self.store.get(name)
+    // 记下节点i引用的节点，作用域正在被修改时返回false
+    fn scan(&mut self, i: usize) -> bool {
+        match &self.nodes[i] {
+            Node::Scope(scope) => {
+                let scope = scope.clone();
+                let Ok(scope) = scope.try_borrow() else {
+                    return false;
+                };
+                if let Some(outer) = &scope.outer {
+                    let id = self.add(Node::Scope(outer.scope.clone()));
+                    self.edge(i, id);
+                }
+                for value in scope.store.values() {
+                    self.value_edges(i, value);
+                }
+            }
+            Node::Function(function) => {
+                let id = self.add(Node::Scope(function.env.scope.clone()));
+                self.edge(i, id);
+            }
+            Node::Array(array) => {
+                let array = array.clone();
+                for element in &array.elements {
+                    self.value_edges(i, element);
+                }
+            }
+            Node::Hash(hash) => {
+                let hash = hash.clone();
+                for pair in hash.pairs.values() {
+                    self.value_edges(i, &pair.value);
+                }
+            }
+        }
+        true
+    }
+
+    // 记下value引用的节点，ReturnValue和TailCall里的值也算
+    fn value_edges(&mut self, from: usize, value: &Value) {
+        let mut values = vec![value];
+        while let Some(value) = values.pop() {
+            let node = match value {
+                Value::Function(function) => Node::Function(function.clone()),
+                Value::Array(array) => Node::Array(array.clone()),
+                Value::Hash(hash) => Node::Hash(hash.clone()),
+                Value::ReturnValue(value) => {
+                    values.push(value);
+                    continue;
+                }
+                Value::TailCall(call) => {
+                    values.push(&call.function);
+                    values.extend(&call.arguments);
+                    continue;
+                }
+                _ => continue,
+            };
+            let id = self.add(node);
+            self.edge(from, id);
+        }
     }
 }
@@ -0,0 +1,87 @@
+use std::rc::Rc;
+
+use crate::ast::{BlockStatement, Expression, Identifier, Node};
+
+use super::environment::Environment;
+
+#[derive(Clone)]
+pub struct Parameter {
+    pub name: Identifier,
+    pub default: Option<Rc<Expression>>, // 默认值表达式，调用时求值
+}
+
+pub struct Function {
+    pub name: Option<String>, // 具名函数声明的名字，匿名函数为None
+    pub parameters: Vec<Parameter>,
+    pub rest: Option<Identifier>, // ...rest，把多余的参数收集成数组
+    pub body: Rc<BlockStatement>,
+    pub env: Environment, // 定义函数时的环境
+}
+
+impl Function {
+    pub fn inspect(&self) -> String {
+        let mut params: Vec<String> = self
+            .parameters
+            .iter()
+            .map(|p| match &p.default {
+                Some(default) => format!("{} = {}", p.name.value, default.to_string()),
+                None => p.name.value.clone(),
+            })
+            .collect();
+        if let Some(rest) = &self.rest {
+            params.push(format!("...{}", rest.value));
+        }
+        let name = match &self.name {
+            Some(name) => format!(" {}", name),
+            None => String::new(),
+        };
+        format!(
+            "fn{}({}) {{ {} }}",
+            name,
+            params.join(", "),
+            self.body.to_string()
+        )
+    }
+
+    // 不含默认值和函数体的签名，如 fn add(a, b) 或 fn(x, ...rest)
+    pub fn signature(&self) -> String {
+        let mut params: Vec<String> = self
+            .parameters
+            .iter()
+            .map(|p| p.name.value.clone())
+            .collect();
+        if let Some(rest) = &self.rest {
+            params.push(format!("...{}", rest.value));
+        }
+        match &self.name {
+            Some(name) => format!("fn {}({})", name, params.join(", ")),
+            None => format!("fn({})", params.join(", ")),
+        }
+    }
+
+    // 至少需要的参数个数
+    pub fn min_arity(&self) -> usize {
+        self.parameters
+            .iter()
+            .take_while(|p| p.default.is_none())
+            .count()
+    }
+
+    // 最多接受的参数个数，有rest参数时没有上限
+    pub fn max_arity(&self) -> Option<usize> {
+        match self.rest {
+            Some(_) => None,
+            None => Some(self.parameters.len()),
+        }
+    }
+
+    // 形如 2、2..3、2+ 的参数个数描述，用于错误信息
+    pub fn arity(&self) -> String {
+        let min = self.min_arity();
+        match self.max_arity() {
+            Some(max) if max == min => min.to_string(),
+            Some(max) => format!("{}..{}", min, max),
+            None => format!("{}+", min),
+        }
+    }
+}
@@ -0,0 +1,81 @@
+use std::collections::BTreeMap;
+use std::hash::{DefaultHasher, Hash as _, Hasher};
+use std::mem;
+
+use super::value::{MAX_INSPECT_DEPTH, defer_all, drop_values};
+use super::{ObjectType, Value};
+
+// 哈希表的键：对象类型 + 哈希值，只有整数、布尔值和字符串可以作为键
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct HashKey {
+    pub obj_type: ObjectType,
+    pub value: u64,
+}
+
+impl HashKey {
+    // 值不可哈希时返回None
+    pub fn from_value(val: &Value) -> Option<HashKey> {
+        let value = match val {
+            Value::Integer(int) => *int as u64,
+            Value::Boolean(b) => *b as u64,
+            Value::String(s) => {
+                let mut hasher = DefaultHasher::new();
+                s.hash(&mut hasher);
+                hasher.finish()
+            }
+            _ => return None,
+        };
+        Some(HashKey {
+            obj_type: val.type_obj(),
+            value,
+        })
+    }
+}
+
+pub struct HashPair {
+    pub key: Value,
+    pub value: Value,
+}
+
+pub struct Hash {
+    pub pairs: BTreeMap<HashKey, HashPair>,
+}
+
+impl Hash {
+    pub fn get(&self, key: &Value) -> Option<&Value> {
+        let hash_key = HashKey::from_value(key)?;
+        self.pairs.get(&hash_key).map(|pair| &pair.value)
+    }
+
+    pub fn inspect(&self) -> String {
+        self.inspect_nested(0)
+    }
+
+    pub(super) fn inspect_nested(&self, depth: usize) -> String {
+        if depth >= MAX_INSPECT_DEPTH {
+            return "{...}".to_string();
+        }
+        let pairs: Vec<String> = self
+            .pairs
+            .values()
+            .map(|pair| {
+                format!(
+                    "{}: {}",
+                    pair.key.inspect(),
+                    pair.value.inspect_nested(depth + 1)
+                )
+            })
+            .collect();
+        format!("{{{}}}", pairs.join(", "))
+    }
+}
+
+// 嵌套的哈希逐层释放，不递归
+impl Drop for Hash {
+    fn drop(&mut self) {
+        let mut values = Vec::new();
+        let pairs = mem::take(&mut self.pairs).into_values();
+        defer_all(pairs.flat_map(|pair| [pair.key, pair.value]), &mut values);
+        drop_values(values);
+    }
+}
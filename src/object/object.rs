@@ -1,13 +1,54 @@
-pub type ObjectType = String;
+use std::fmt;
 
-pub trait Object: std::any::Any {
-    fn as_any(&self) -> &dyn std::any::Any;
-    fn type_obj(&self) -> ObjectType;
-    fn inspect(&self) -> String;
-    fn clone_object(&self) -> Box<dyn Object>;
+// 值的类型。新增类型时 match 必须处理，拼写错误在编译期就能发现
+#[non_exhaustive]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ObjectType {
+    Integer,
+    Boolean,
+    Null,
+    ReturnValue,
+    Error,
+    String,
+    Array,
+    Hash,
+    Function,
+    TailCall,
+}
+
+impl ObjectType {
+    // REPL中显示的类型名
+    pub fn name(&self) -> &'static str {
+        match self {
+            ObjectType::Integer => "Integer",
+            ObjectType::Boolean => "Boolean",
+            ObjectType::Null => "Null",
+            ObjectType::ReturnValue => "ReturnValue",
+            ObjectType::Error => "Error",
+            ObjectType::String => "String",
+            ObjectType::Array => "Array",
+            ObjectType::Hash => "Hash",
+            ObjectType::Function => "Function",
+            ObjectType::TailCall => "TailCall",
+        }
+    }
+}
+
+// 错误信息中使用的类型名
+impl fmt::Display for ObjectType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            ObjectType::Integer => "INTEGER",
+            ObjectType::Boolean => "BOOLEAN",
+            ObjectType::Null => "NULL",
+            ObjectType::ReturnValue => "RETURN_VALUE",
+            ObjectType::Error => "ERROR",
+            ObjectType::String => "STRING",
+            ObjectType::Array => "ARRAY",
+            ObjectType::Hash => "HASH",
+            ObjectType::Function => "FUNCTION",
+            ObjectType::TailCall => "TAIL_CALL",
+        };
+        write!(f, "{}", name)
+    }
 }
-pub const INTEGER_OBJ: &str = "INTEGER";
-pub const BOOLEAN_OBJ: &str = "BOOLEAN";
-pub const NULL_OBJ: &str = "NULL";
-pub const RETURN_VALUE_OBJ: &str = "RETURN_VALUE";
-pub const ERROR_OBJ: &str = "ERROR";
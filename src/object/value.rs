@@ -0,0 +1,122 @@
+use std::mem;
+use std::rc::Rc;
+
+use super::builtin::Builtin;
+use super::{Array, Function, Hash, ObjectType};
+
+// 求值得到的值：整数、布尔值和null直接保存，
+// 字符串、数组等堆上的值用Rc共享，clone只增加引用计数
+#[derive(Clone)]
+pub enum Value {
+    Integer(i64),
+    Boolean(bool),
+    Null,
+    String(Rc<str>),
+    Array(Rc<Array>),
+    Hash(Rc<Hash>),
+    Function(Rc<Function>),
+    Builtin(Builtin),
+    // return语句的值，在函数或程序边界解包
+    ReturnValue(Box<Value>),
+    Error(Rc<str>),
+    // 尾位置上尚未执行的函数调用，只在求值器内部使用，不会出现在求值结果中
+    TailCall(Box<TailCall>),
+}
+
+#[derive(Clone)]
+pub struct TailCall {
+    pub function: Value,
+    pub arguments: Vec<Value>,
+}
+
+impl Value {
+    pub fn type_obj(&self) -> ObjectType {
+        match self {
+            Value::Integer(_) => ObjectType::Integer,
+            Value::Boolean(_) => ObjectType::Boolean,
+            Value::Null => ObjectType::Null,
+            Value::String(_) => ObjectType::String,
+            Value::Array(_) => ObjectType::Array,
+            Value::Hash(_) => ObjectType::Hash,
+            Value::Function(_) | Value::Builtin(_) => ObjectType::Function,
+            Value::ReturnValue(_) => ObjectType::ReturnValue,
+            Value::Error(_) => ObjectType::Error,
+            Value::TailCall(_) => ObjectType::TailCall,
+        }
+    }
+
+    pub fn inspect(&self) -> String {
+        self.inspect_nested(0)
+    }
+
+    // depth是外层数组和哈希的层数，超过MAX_INSPECT_DEPTH的部分省略
+    pub(super) fn inspect_nested(&self, depth: usize) -> String {
+        match self {
+            Value::Integer(value) => value.to_string(),
+            Value::Boolean(value) => value.to_string(),
+            Value::Null => "null".to_string(),
+            Value::String(value) => value.to_string(),
+            Value::Array(array) => array.inspect_nested(depth),
+            Value::Hash(hash) => hash.inspect_nested(depth),
+            Value::Function(function) => function.inspect(),
+            Value::Builtin(builtin) => builtin.inspect(),
+            Value::ReturnValue(value) => value.inspect_nested(depth),
+            Value::Error(message) => format!("ERROR: {}", message),
+            Value::TailCall(call) => format!("<tail call {}>", call.function.inspect()),
+        }
+    }
+}
+
+// 嵌套超过这个层数的数组和哈希显示成[...]和{...}，inspect的递归深度有上限
+pub const MAX_INSPECT_DEPTH: usize = 200;
+
+// 释放一组值。嵌套很深的数组、哈希和闭包逐层递归释放会耗尽栈，
+// 这里把最后一个引用持有的子值先取出来放进工作列表，外层释放时已经没有子值
+pub(super) fn drop_values(mut values: Vec<Value>) {
+    while let Some(value) = values.pop() {
+        match value {
+            Value::Array(array) => {
+                if let Some(mut array) = Rc::into_inner(array) {
+                    defer_all(array.elements.drain(..), &mut values);
+                }
+            }
+            Value::Hash(hash) => {
+                if let Some(mut hash) = Rc::into_inner(hash) {
+                    let pairs = mem::take(&mut hash.pairs).into_values();
+                    defer_all(pairs.flat_map(|pair| [pair.key, pair.value]), &mut values);
+                }
+            }
+            Value::Function(function) => {
+                if let Some(function) = Rc::into_inner(function) {
+                    function.env.release(&mut values);
+                }
+            }
+            Value::ReturnValue(value) => defer(*value, &mut values),
+            Value::TailCall(call) => {
+                defer(call.function, &mut values);
+                defer_all(call.arguments, &mut values);
+            }
+            _ => {}
+        }
+    }
+}
+
+// 释放这个引用会连带释放其他值时放进values，否则直接释放
+pub(super) fn defer(value: Value, values: &mut Vec<Value>) {
+    let last = match &value {
+        Value::Array(array) => Rc::strong_count(array) == 1,
+        Value::Hash(hash) => Rc::strong_count(hash) == 1,
+        Value::Function(function) => Rc::strong_count(function) == 1,
+        Value::ReturnValue(_) | Value::TailCall(_) => true,
+        _ => false,
+    };
+    if last {
+        values.push(value);
+    }
+}
+
+pub(super) fn defer_all(items: impl IntoIterator<Item = Value>, values: &mut Vec<Value>) {
+    for value in items {
+        defer(value, values);
+    }
+}
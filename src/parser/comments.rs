@@ -0,0 +1,166 @@
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, HashSet};
+
+use crate::token::token::Position;
+
+// 解析时收集的注释和空行。语句、块和列表按在源码中开始的先后编号，
+// 打印时按同样的顺序遍历AST即可取回对应的注释。
+// 列表包括数组、哈希、调用参数、参数列表和match的分支，元素按(列表编号, 下标)索引
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Comments {
+    pub leading: HashMap<usize, Vec<Comment>>, // 语句之前单独成行的注释
+    pub trailing: HashMap<usize, String>,      // 与语句末尾在同一行的注释
+    pub block_end: HashMap<usize, Vec<Comment>>, // 块中最后一条语句之后、}之前的注释
+    pub item_leading: HashMap<(usize, usize), Vec<Comment>>, // 列表元素之前的注释
+    pub item_trailing: HashMap<(usize, usize), String>, // 与元素或其后的逗号在同一行的注释
+    pub list_end: HashMap<usize, Vec<Comment>>, // 最后一个元素之后、右括号之前的注释
+    pub end: Vec<Comment>,                     // 程序末尾的注释
+    pub blank_line_before: HashSet<usize>,     // 前面有空行的语句
+    pub misplaced: Vec<Comment>,               // 表达式内部的其他注释，格式化时无法放回原处
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Comment {
+    pub text: String, // 包含开头的 //
+    pub blank_line_before: bool,
+    pub position: Position,
+}
+
+impl Comments {
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    // 注释总数，用于检查格式化没有丢失注释
+    pub fn len(&self) -> usize {
+        self.leading.values().map(Vec::len).sum::<usize>()
+            + self.trailing.len()
+            + self.block_end.values().map(Vec::len).sum::<usize>()
+            + self.item_leading.values().map(Vec::len).sum::<usize>()
+            + self.item_trailing.len()
+            + self.list_end.values().map(Vec::len).sum::<usize>()
+            + self.end.len()
+            + self.misplaced.len()
+    }
+}
+
+// 解析器移动词法单元时维护的注释状态
+#[derive(Debug, Default)]
+pub(crate) struct CommentCollector {
+    pub(crate) comments: Comments,
+    cur: Vec<Comment>, // cur_token之前的注释
+    cur_newlines: usize,
+    peek: Vec<Comment>,  // peek_token之前的注释
+    peek_trailing: bool, // peek中的第一条注释与cur_token在同一行
+    peek_newlines: usize,
+    statements: usize,
+    blocks: usize,
+    lists: usize,
+}
+
+impl CommentCollector {
+    // 前移一个词法单元。cur_token之前的注释没有被语句、块或列表元素认领，
+    // 说明它在表达式内部
+    pub(crate) fn advance(&mut self) {
+        self.comments.misplaced.append(&mut self.cur);
+        self.cur = std::mem::take(&mut self.peek);
+        self.cur_newlines = self.peek_newlines;
+        self.peek_trailing = false;
+    }
+
+    // newlines是注释之前的换行数
+    pub(crate) fn push_peek(&mut self, text: String, newlines: usize, position: Position) {
+        if self.peek.is_empty() && newlines == 0 {
+            self.peek_trailing = true;
+        }
+        self.peek.push(Comment {
+            text,
+            blank_line_before: newlines > 1,
+            position,
+        });
+    }
+
+    pub(crate) fn set_peek_newlines(&mut self, newlines: usize) {
+        self.peek_newlines = newlines;
+    }
+
+    // cur_token是语句的第一个词法单元，之前的注释都归这条语句
+    pub(crate) fn start_statement(&mut self) -> usize {
+        let index = self.statements;
+        self.statements += 1;
+        let leading = std::mem::take(&mut self.cur);
+        if !leading.is_empty() {
+            self.comments.leading.insert(index, leading);
+        }
+        if self.cur_newlines > 1 {
+            self.comments.blank_line_before.insert(index);
+        }
+        index
+    }
+
+    // cur_token是语句的最后一个词法单元，同一行后面的注释归这条语句
+    pub(crate) fn end_statement(&mut self, index: usize) {
+        if self.peek_trailing {
+            let comment = self.peek.remove(0);
+            self.comments.trailing.insert(index, comment.text);
+            self.peek_trailing = false;
+        }
+    }
+
+    pub(crate) fn start_block(&mut self) -> usize {
+        self.blocks += 1;
+        self.blocks - 1
+    }
+
+    // cur_token是块的}，之前剩下的注释留在块的末尾
+    pub(crate) fn end_block(&mut self, index: usize) {
+        let comments = std::mem::take(&mut self.cur);
+        if !comments.is_empty() {
+            self.comments.block_end.insert(index, comments);
+        }
+    }
+
+    // cur_token是列表的左括号
+    pub(crate) fn start_list(&mut self) -> usize {
+        self.lists += 1;
+        self.lists - 1
+    }
+
+    // cur_token是元素的第一个词法单元，之前的注释都归这个元素
+    pub(crate) fn start_item(&mut self, list: usize, item: usize) {
+        let leading = std::mem::take(&mut self.cur);
+        if !leading.is_empty() {
+            self.comments.item_leading.insert((list, item), leading);
+        }
+    }
+
+    // cur_token是元素的最后一个词法单元或其后的逗号，同一行后面的注释归这个元素
+    pub(crate) fn end_item(&mut self, list: usize, item: usize) {
+        if !self.peek_trailing {
+            return;
+        }
+        let comment = self.peek.remove(0);
+        self.peek_trailing = false;
+        // 元素和逗号后面各有一条行尾注释时，只能保留一条
+        match self.comments.item_trailing.entry((list, item)) {
+            Entry::Vacant(entry) => {
+                entry.insert(comment.text);
+            }
+            Entry::Occupied(_) => self.comments.misplaced.push(comment),
+        }
+    }
+
+    // cur_token是列表的右括号，之前剩下的注释留在列表的末尾
+    pub(crate) fn end_list(&mut self, list: usize) {
+        let comments = std::mem::take(&mut self.cur);
+        if !comments.is_empty() {
+            self.comments.list_end.insert(list, comments);
+        }
+    }
+
+    pub(crate) fn finish(&mut self) {
+        let mut comments = std::mem::take(&mut self.cur);
+        comments.append(&mut self.peek);
+        self.comments.end.append(&mut comments);
+    }
+}
@@ -1 +1,2 @@
+pub mod comments;
 pub mod parser;
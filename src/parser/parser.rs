@@ -1,11 +1,17 @@
 use crate::ast::{
-    BlockStatement, Boolean, CallExpression, ExpressionStatement, FunctionLiteral, Identifier,
-    IfExpression, InfixExpression, IntegerLiteral, LetStatement, Node, NodeType, PrefixExpression,
-    Program, ReturnStatement, Statement,
+    ArrayLiteral, ArrayPattern, BlockStatement, Boolean, CallExpression, DefaultParameter,
+    DestructuringLetStatement, Expression, ExpressionStatement, FunctionDeclaration,
+    FunctionLiteral, HashLiteral, HashPattern, Identifier, IfExpression, InfixExpression,
+    IntegerLiteral, LetStatement, MatchArm, MatchExpression, Parameter, Pattern, PrefixExpression,
+    Program, RestParameter, ReturnStatement, SpreadExpression, Statement, StringLiteral,
 };
+use crate::cst::builder::CstBuilder;
+use crate::cst::{GreenNode, SyntaxKind};
 use crate::lexer::lexer::Lexer;
-use crate::token::token::{Token, TokenType};
+use crate::parser::comments::{CommentCollector, Comments};
+use crate::token::token::{Position, Token, TokenType};
 use std::collections::HashMap;
+use std::rc::Rc;
 
 #[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
 pub enum Precedence {
@@ -19,19 +25,44 @@ pub enum Precedence {
 }
 
 // 前缀解析函数：不需要其他参数，直接返回一个表达式
-type PrefixParseFn = fn(&mut Parser) -> Option<NodeType>;
+type PrefixParseFn = fn(&mut Parser) -> Option<Expression>;
 
 // 中缀解析函数：接收左侧表达式作为参数，返回一个新表达式
-type InfixParseFn = fn(&mut Parser, NodeType) -> Option<NodeType>;
+type InfixParseFn = fn(&mut Parser, Expression) -> Option<Expression>;
 pub struct Parser {
     l: Lexer,
     cur_token: Token,
     peek_token: Token,
+    // cur_token和peek_token的行列号，用于错误信息
+    cur_position: Position,
+    peek_position: Position,
     errors: Vec<String>,
     // 前缀解析函数映射表
     prefix_parse_fns: HashMap<TokenType, PrefixParseFn>,
     // 中缀解析函数映射表
     infix_parse_fns: HashMap<TokenType, InfixParseFn>,
+    // 当前嵌套深度与上限，超过上限时报错并停止解析，避免栈溢出
+    depth: usize,
+    max_depth: usize,
+    aborted: bool,
+    // 词法分析器保留注释时收集注释，供格式化工具使用
+    comments: CommentCollector,
+    // 需要时同时构建无损的具体语法树
+    cst: CstBuilder,
+}
+
+pub const DEFAULT_MAX_DEPTH: usize = 256;
+
+// 中缀词法单元的优先级，其他词法单元为LOWEST
+pub fn token_precedence(token_type: TokenType) -> Precedence {
+    match token_type {
+        TokenType::EQ | TokenType::NOTEQ => Precedence::EQUALS,
+        TokenType::LT | TokenType::GT => Precedence::LESSGREATER,
+        TokenType::PLUS | TokenType::MINUS => Precedence::SUM,
+        TokenType::SLASH | TokenType::ASTERISK => Precedence::PRODUCT,
+        TokenType::LPAREN => Precedence::CALL,
+        _ => Precedence::LOWEST,
+    }
 }
 
 impl Parser {
@@ -46,9 +77,16 @@ impl Parser {
                 token_type: TokenType::ILLEGAL,
                 literal: String::new(),
             },
+            cur_position: Position { line: 1, column: 1 },
+            peek_position: Position { line: 1, column: 1 },
             errors: Vec::new(),
             prefix_parse_fns: HashMap::new(),
             infix_parse_fns: HashMap::new(),
+            depth: 0,
+            max_depth: DEFAULT_MAX_DEPTH,
+            aborted: false,
+            comments: CommentCollector::default(),
+            cst: CstBuilder::default(),
         };
 
         // 读取两个词法单元，以设置cur_token和peek_token
@@ -64,6 +102,10 @@ impl Parser {
         p.register_prefix(TokenType::LPAREN, Parser::parse_grouped_expression);
         p.register_prefix(TokenType::IF, Parser::parse_if_expression);
         p.register_prefix(TokenType::FN, Parser::parse_function_literal);
+        p.register_prefix(TokenType::STRING, Parser::parse_string_literal);
+        p.register_prefix(TokenType::LBRACKET, Parser::parse_array_literal);
+        p.register_prefix(TokenType::LBRACE, Parser::parse_hash_literal);
+        p.register_prefix(TokenType::MATCH, Parser::parse_match_expression);
 
         // 注册中缀解析函数
         p.register_infix(TokenType::PLUS, Parser::parse_infix_expression);
@@ -81,30 +123,19 @@ impl Parser {
 
     // 将当前词法单元和字面量提供给Identifier的token和value字段
     // 该方法不会调用next_token()方法
-    fn parse_identifier(&mut self) -> Option<NodeType> {
-        Some(NodeType::Expression(Box::new(Identifier {
+    fn parse_identifier(&mut self) -> Option<Expression> {
+        Some(Expression::Identifier(Identifier {
             token: self.cur_token.clone(),
             value: self.cur_token.literal.clone(),
-        })))
-    }
-
-    fn token_precedence(&self, token_type: TokenType) -> Precedence {
-        match token_type {
-            TokenType::EQ | TokenType::NOTEQ => Precedence::EQUALS,
-            TokenType::LT | TokenType::GT => Precedence::LESSGREATER,
-            TokenType::PLUS | TokenType::MINUS => Precedence::SUM,
-            TokenType::SLASH | TokenType::ASTERISK => Precedence::PRODUCT,
-            TokenType::LPAREN => Precedence::CALL,
-            _ => Precedence::LOWEST,
-        }
+        }))
     }
 
     fn cur_precedence(&self) -> Precedence {
-        self.token_precedence(self.cur_token.token_type)
+        token_precedence(self.cur_token.token_type)
     }
 
     fn peek_precedence(&self) -> Precedence {
-        self.token_precedence(self.peek_token.token_type)
+        token_precedence(self.peek_token.token_type)
     }
 
     // 注册前缀解析函数
@@ -118,19 +149,59 @@ impl Parser {
     }
 
     fn next_token(&mut self) {
+        self.comments.advance();
         self.cur_token = self.peek_token.clone();
+        self.cur_position = self.peek_position;
         self.peek_token = self.l.next_token();
+        while self.peek_token.token_type == TokenType::COMMENT {
+            let text = std::mem::take(&mut self.peek_token.literal);
+            self.comments
+                .push_peek(text, self.l.newlines_before(), self.l.token_position());
+            self.peek_token = self.l.next_token();
+        }
+        self.comments.set_peek_newlines(self.l.newlines_before());
+        self.peek_position = self.l.token_position();
+        self.cst.advance(&self.l, self.peek_token.token_type);
+    }
+
+    // 具体语法树的检查点，在节点的第一个词法单元成为cur_token时调用
+    fn checkpoint(&mut self) -> usize {
+        self.cst.checkpoint(&self.l)
+    }
+
+    // 节点的最后一个词法单元是cur_token时，把检查点之后的内容包成节点
+    fn finish_node(&mut self, checkpoint: usize, kind: SyntaxKind) {
+        self.cst.finish_node(&self.l, checkpoint, kind);
     }
 
     pub fn errors(&self) -> Vec<String> {
         self.errors.clone()
     }
 
+    pub fn set_max_depth(&mut self, max_depth: usize) {
+        self.max_depth = max_depth;
+    }
+
+    // 在parse_program之前调用，解析时同时构建具体语法树
+    pub fn set_build_cst(&mut self, build: bool) {
+        self.cst.set_enabled(build);
+    }
+
+    // parse_program构建的具体语法树，没有开启时为None
+    pub fn syntax_tree(&self) -> Option<Rc<GreenNode>> {
+        self.cst.root()
+    }
+
+    // 解析过程中收集到的注释，只有词法分析器保留注释时才非空
+    pub fn comments(&self) -> &Comments {
+        &self.comments.comments
+    }
+
     pub fn parse_program(&mut self) -> Program {
         let mut program = Program::new();
 
-        // 循环直到遇到 EOF token
-        while !self.cur_token_is(TokenType::EOF) {
+        // 循环直到遇到 EOF token，超过嵌套上限后直接停止
+        while !self.cur_token_is(TokenType::EOF) && !self.aborted {
             if let Some(stmt) = self.parse_statement() {
                 // 打印Statement
                 // println!("Parsed statement: {:#?}", stmt);
@@ -138,26 +209,62 @@ impl Parser {
             }
             self.next_token();
         }
+        self.comments.finish();
+
+        // 嵌套过深中止时剩下的词法单元放进错误节点，语法树仍然覆盖全部源码
+        if self.cst.is_enabled() {
+            let checkpoint = self.checkpoint();
+            if !self.cur_token_is(TokenType::EOF) {
+                while !self.peek_token_is(TokenType::EOF) {
+                    self.next_token();
+                }
+                self.finish_node(checkpoint, SyntaxKind::Error);
+                self.next_token();
+            }
+        }
+        self.cst.finish(&self.l);
 
         program
     }
 
-    fn parse_statement(&mut self) -> Option<NodeType> {
-        match self.cur_token.token_type {
+    fn parse_statement(&mut self) -> Option<Statement> {
+        if matches!(
+            self.cur_token.token_type,
+            TokenType::EOF | TokenType::ILLEGAL
+        ) && self.cur_token.literal.trim().is_empty()
+        {
+            self.next_token();
+            return None;
+        }
+
+        let index = self.comments.start_statement();
+        let checkpoint = self.checkpoint();
+        let stmt = match self.cur_token.token_type {
             TokenType::LET => self.parse_let_statement(),
             TokenType::RETURN => self.parse_return_statement(),
-            TokenType::EOF | TokenType::ILLEGAL if self.cur_token.literal.trim().is_empty() => {
-                self.next_token();
-                None
+            TokenType::FN if self.peek_token_is(TokenType::IDENT) => {
+                self.parse_function_declaration()
             }
             _ => {
                 // println!("parse_statement get None !");
                 self.parse_expression_statement()
             }
-        }
+        };
+        self.comments.end_statement(index);
+        let kind = match &stmt {
+            Some(Statement::Let(_)) => SyntaxKind::LetStatement,
+            Some(Statement::DestructuringLet(_)) => SyntaxKind::DestructuringLetStatement,
+            Some(Statement::Return(_)) => SyntaxKind::ReturnStatement,
+            Some(Statement::Expression(_)) => SyntaxKind::ExpressionStatement,
+            Some(Statement::Block(_)) => SyntaxKind::Block,
+            Some(Statement::FunctionDeclaration(_)) => SyntaxKind::FunctionDeclaration,
+            None => SyntaxKind::Error,
+        };
+        self.finish_node(checkpoint, kind);
+        stmt
     }
 
-    fn parse_return_statement(&mut self) -> Option<NodeType> {
+    fn parse_return_statement(&mut self) -> Option<Statement> {
         let token = self.cur_token.clone();
 
         // 跳过 return 关键字
@@ -170,13 +277,18 @@ impl Parser {
             self.next_token();
         }
 
-        Some(NodeType::Statement(Box::new(ReturnStatement {
+        Some(Statement::Return(ReturnStatement {
             token,
             return_value: Box::new(value),
-        })))
+        }))
     }
 
-    fn parse_let_statement(&mut self) -> Option<NodeType> {
+    fn parse_let_statement(&mut self) -> Option<Statement> {
+        // let [a, b] = ... 或 let {a, b} = ... 走解构分支
+        if self.peek_token_is(TokenType::LBRACKET) || self.peek_token_is(TokenType::LBRACE) {
+            return self.parse_destructuring_let_statement();
+        }
+
         if !self.expect_peek(TokenType::IDENT) {
             return None;
         }
@@ -198,30 +310,246 @@ impl Parser {
             self.next_token();
         }
 
-        Some(NodeType::Statement(Box::new(LetStatement {
+        Some(Statement::Let(LetStatement {
             token: Token {
                 token_type: TokenType::LET,
                 literal: "let".to_string(),
             },
             name: Box::new(name),
             value: Box::new(value),
-        })))
+        }))
+    }
+
+    fn parse_destructuring_let_statement(&mut self) -> Option<Statement> {
+        let token = self.cur_token.clone();
+        self.next_token();
+
+        let pattern = self.parse_pattern()?;
+
+        if !self.expect_peek(TokenType::ASSIGN) {
+            return None;
+        }
+
+        self.next_token();
+
+        let value = self.parse_expression(Precedence::LOWEST)?;
+
+        if self.peek_token_is(TokenType::SEMICOLON) {
+            self.next_token();
+        }
+
+        Some(Statement::DestructuringLet(DestructuringLetStatement {
+            token,
+            pattern,
+            value: Box::new(value),
+        }))
+    }
+
+    // 解析当前词法单元开始的模式，结束时cur_token停在模式的最后一个词法单元上
+    fn parse_pattern(&mut self) -> Option<Pattern> {
+        if !self.enter() {
+            return None;
+        }
+        let checkpoint = self.checkpoint();
+        let pattern = self.parse_pattern_inner();
+        self.depth -= 1;
+        if let Some(pattern) = &pattern {
+            let kind = match pattern {
+                Pattern::Wildcard => SyntaxKind::WildcardPattern,
+                Pattern::Integer(_) => SyntaxKind::IntegerPattern,
+                Pattern::String(_) => SyntaxKind::StringPattern,
+                Pattern::Boolean(_) => SyntaxKind::BooleanPattern,
+                Pattern::Identifier(_) => SyntaxKind::IdentifierPattern,
+                Pattern::Array(_) => SyntaxKind::ArrayPattern,
+                Pattern::Hash(_) => SyntaxKind::HashPattern,
+            };
+            self.finish_node(checkpoint, kind);
+        }
+        pattern
+    }
+
+    fn parse_pattern_inner(&mut self) -> Option<Pattern> {
+        match self.cur_token.token_type {
+            TokenType::IDENT if self.cur_token.literal == "_" => Some(Pattern::Wildcard),
+            TokenType::INT => self.parse_integer_pattern(false),
+            TokenType::MINUS => {
+                if !self.expect_peek(TokenType::INT) {
+                    return None;
+                }
+                self.parse_integer_pattern(true)
+            }
+            TokenType::STRING => Some(Pattern::String(self.cur_token.literal.clone())),
+            TokenType::TRUE | TokenType::FALSE => {
+                Some(Pattern::Boolean(self.cur_token_is(TokenType::TRUE)))
+            }
+            TokenType::IDENT => Some(Pattern::Identifier(Identifier {
+                token: self.cur_token.clone(),
+                value: self.cur_token.literal.clone(),
+            })),
+            TokenType::LBRACKET => self.parse_array_pattern().map(Pattern::Array),
+            TokenType::LBRACE => self.parse_hash_pattern().map(Pattern::Hash),
+            t => {
+                self.errors
+                    .push(format!("expected pattern, got {:?} instead", t));
+                None
+            }
+        }
+    }
+
+    fn parse_integer_pattern(&mut self, negative: bool) -> Option<Pattern> {
+        match self.cur_token.literal.parse::<i64>() {
+            Ok(value) => Some(Pattern::Integer(if negative { -value } else { value })),
+            Err(_) => {
+                let msg = format!("could not parse {} as integer", self.cur_token.literal);
+                self.errors.push(msg);
+                None
+            }
+        }
+    }
+
+    // [<模式>, <模式>, ...<标识符>]
+    fn parse_array_pattern(&mut self) -> Option<ArrayPattern> {
+        let token = self.cur_token.clone();
+        let mut elements = Vec::<Pattern>::new();
+        let mut rest = None;
+
+        while !self.peek_token_is(TokenType::RBRACKET) {
+            self.next_token();
+
+            if self.cur_token_is(TokenType::ELLIPSIS) {
+                if !self.expect_peek(TokenType::IDENT) {
+                    return None;
+                }
+                rest = Some(Identifier {
+                    token: self.cur_token.clone(),
+                    value: self.cur_token.literal.clone(),
+                });
+                // 剩余绑定必须是最后一个元素
+                break;
+            }
+
+            elements.push(self.parse_pattern()?);
+
+            if !self.peek_token_is(TokenType::RBRACKET) && !self.expect_peek(TokenType::COMMA) {
+                return None;
+            }
+        }
+
+        if !self.expect_peek(TokenType::RBRACKET) {
+            return None;
+        }
+
+        Some(ArrayPattern {
+            token,
+            elements,
+            rest,
+        })
+    }
+
+    // {<键>, <键>: <模式>, ...}，键可以是标识符或字符串
+    fn parse_hash_pattern(&mut self) -> Option<HashPattern> {
+        let token = self.cur_token.clone();
+        let mut pairs = Vec::<(String, Pattern)>::new();
+
+        while !self.peek_token_is(TokenType::RBRACE) {
+            self.next_token();
+
+            let checkpoint = self.checkpoint();
+            let key_token = self.cur_token.clone();
+            if !self.cur_token_is(TokenType::IDENT) && !self.cur_token_is(TokenType::STRING) {
+                self.errors.push(format!(
+                    "expected hash pattern key, got {:?} instead",
+                    key_token.token_type
+                ));
+                return None;
+            }
+
+            let pattern = if self.peek_token_is(TokenType::COLON) {
+                self.next_token();
+                self.next_token();
+                self.parse_pattern()?
+            } else if key_token.token_type == TokenType::IDENT {
+                // 简写形式 {name} 等价于 {name: name}
+                Pattern::Identifier(Identifier {
+                    token: key_token.clone(),
+                    value: key_token.literal.clone(),
+                })
+            } else {
+                self.peek_error(TokenType::COLON);
+                return None;
+            };
+            pairs.push((key_token.literal, pattern));
+            self.finish_node(checkpoint, SyntaxKind::HashPatternField);
+
+            if !self.peek_token_is(TokenType::RBRACE) && !self.expect_peek(TokenType::COMMA) {
+                return None;
+            }
+        }
+
+        if !self.expect_peek(TokenType::RBRACE) {
+            return None;
+        }
+
+        Some(HashPattern { token, pairs })
     }
 
     fn no_prefix_parse_fn_error(&mut self, token: TokenType) {
+        // ... 只能出现在调用参数和参数列表中，由这两处单独解析
+        if token == TokenType::ELLIPSIS {
+            self.errors.push(format!(
+                "{}: spread is only allowed in call arguments and parameter lists",
+                self.cur_position
+            ));
+            return;
+        }
         let msg = format!("no prefix parse function for {:?} found", token);
         self.errors.push(msg);
     }
 
-    fn parse_expression(&mut self, precedence: Precedence) -> Option<NodeType> {
+    // 进入一层嵌套，超过上限时记录错误并中止解析
+    fn enter(&mut self) -> bool {
+        if self.aborted {
+            return false;
+        }
+        if self.depth >= self.max_depth {
+            self.errors.push(format!(
+                "maximum nesting depth of {} exceeded",
+                self.max_depth
+            ));
+            self.aborted = true;
+            return false;
+        }
+        self.depth += 1;
+        true
+    }
+
+    fn parse_expression(&mut self, precedence: Precedence) -> Option<Expression> {
+        if !self.enter() {
+            return None;
+        }
+        let expression = self.parse_expression_inner(precedence);
+        self.depth -= 1;
+        expression
+    }
+
+    fn parse_expression_inner(&mut self, precedence: Precedence) -> Option<Expression> {
         // 查找当前token对应的前缀解析函数
         if let Some(&prefix) = self.prefix_parse_fns.get(&self.cur_token.token_type) {
+            let checkpoint = self.checkpoint();
+            let grouped = self.cur_token_is(TokenType::LPAREN);
             let mut left_exp = prefix(self)?;
+            let kind = if grouped {
+                SyntaxKind::Paren
+            } else {
+                expression_kind(&left_exp)
+            };
+            self.finish_node(checkpoint, kind);
 
             while !self.peek_token_is(TokenType::SEMICOLON) && precedence < self.peek_precedence() {
                 if let Some(&infix) = self.infix_parse_fns.get(&self.peek_token.token_type) {
                     self.next_token();
                     left_exp = infix(self, left_exp)?;
+                    self.finish_node(checkpoint, expression_kind(&left_exp));
                 } else {
                     return Some(left_exp);
                 }
@@ -253,6 +581,10 @@ impl Parser {
     }
 
     fn peek_error(&mut self, t: TokenType) {
+        // 已因嵌套过深中止时不再追加后续错误
+        if self.aborted {
+            return;
+        }
         let msg = format!(
             "expected next token to be {:?}, got {:?} instead",
             t, self.peek_token.token_type
@@ -260,7 +592,7 @@ impl Parser {
         self.errors.push(msg);
     }
 
-    fn parse_expression_statement(&mut self) -> Option<NodeType> {
+    fn parse_expression_statement(&mut self) -> Option<Statement> {
         let token = self.cur_token.clone();
 
         if let Some(expression) = self.parse_expression(Precedence::LOWEST) {
@@ -273,20 +605,17 @@ impl Parser {
                 expression: Box::new(expression),
             };
 
-            Some(NodeType::Statement(Box::new(stmt)))
+            Some(Statement::Expression(stmt))
         } else {
             None
         }
     }
 
-    fn parse_integer_literal(&mut self) -> Option<NodeType> {
+    fn parse_integer_literal(&mut self) -> Option<Expression> {
         let token = self.cur_token.clone();
 
         match token.literal.parse::<i64>() {
-            Ok(value) => Some(NodeType::Expression(Box::new(IntegerLiteral {
-                token,
-                value,
-            }))),
+            Ok(value) => Some(Expression::Integer(IntegerLiteral { token, value })),
             Err(_) => {
                 let msg = format!("could not parse {} as integer", self.cur_token.literal);
                 self.errors.push(msg);
@@ -295,7 +624,75 @@ impl Parser {
         }
     }
 
-    fn parse_prefix_expression(&mut self) -> Option<NodeType> {
+    fn parse_string_literal(&mut self) -> Option<Expression> {
+        Some(Expression::String(StringLiteral {
+            token: self.cur_token.clone(),
+            value: self.cur_token.literal.clone(),
+        }))
+    }
+
+    // 调用参数，可以是 ...<表达式>
+    fn parse_call_argument(&mut self) -> Option<Expression> {
+        if !self.cur_token_is(TokenType::ELLIPSIS) {
+            return self.parse_expression(Precedence::LOWEST);
+        }
+        let checkpoint = self.checkpoint();
+        let token = self.cur_token.clone();
+        self.next_token();
+        let value = self.parse_expression(Precedence::PREFIX)?;
+        self.finish_node(checkpoint, SyntaxKind::Spread);
+
+        Some(Expression::Spread(SpreadExpression {
+            token,
+            value: Box::new(value),
+        }))
+    }
+
+    fn parse_array_literal(&mut self) -> Option<Expression> {
+        let token = self.cur_token.clone();
+        let elements = self.parse_expression_list(TokenType::RBRACKET)?;
+
+        Some(Expression::Array(ArrayLiteral { token, elements }))
+    }
+
+    //{<表达式>: <表达式>, ...}
+    fn parse_hash_literal(&mut self) -> Option<Expression> {
+        let token = self.cur_token.clone();
+        let mut pairs = Vec::<(Expression, Expression)>::new();
+        let list = self.comments.start_list();
+
+        while !self.peek_token_is(TokenType::RBRACE) {
+            self.next_token();
+            let i = pairs.len();
+            self.comments.start_item(list, i);
+            let key = self.parse_expression(Precedence::LOWEST)?;
+
+            if !self.expect_peek(TokenType::COLON) {
+                return None;
+            }
+
+            self.next_token();
+            let value = self.parse_expression(Precedence::LOWEST)?;
+            pairs.push((key, value));
+            self.comments.end_item(list, i);
+
+            if !self.peek_token_is(TokenType::RBRACE) {
+                if !self.expect_peek(TokenType::COMMA) {
+                    return None;
+                }
+                self.comments.end_item(list, i);
+            }
+        }
+
+        if !self.expect_peek(TokenType::RBRACE) {
+            return None;
+        }
+        self.comments.end_list(list);
+
+        Some(Expression::Hash(HashLiteral { token, pairs }))
+    }
+
+    fn parse_prefix_expression(&mut self) -> Option<Expression> {
         let token = self.cur_token.clone();
         let operator = self.cur_token.literal.clone();
 
@@ -303,14 +700,14 @@ impl Parser {
 
         let right = self.parse_expression(Precedence::PREFIX)?;
 
-        Some(NodeType::Expression(Box::new(PrefixExpression {
+        Some(Expression::Prefix(PrefixExpression {
             token,
             operator,
             right: Box::new(right),
-        })))
+        }))
     }
 
-    fn parse_infix_expression(&mut self, left: NodeType) -> Option<NodeType> {
+    fn parse_infix_expression(&mut self, left: Expression) -> Option<Expression> {
         let token = self.cur_token.clone();
         let operator = self.cur_token.literal.clone();
 
@@ -318,23 +715,23 @@ impl Parser {
         self.next_token();
         let right = self.parse_expression(precedence)?;
 
-        Some(NodeType::Expression(Box::new(InfixExpression {
+        Some(Expression::Infix(InfixExpression {
             token,
             left: Box::new(left),
             operator,
             right: Box::new(right),
-        })))
+        }))
     }
 
-    fn parse_boolean(&mut self) -> Option<NodeType> {
+    fn parse_boolean(&mut self) -> Option<Expression> {
         let token = self.cur_token.clone();
-        Some(NodeType::Expression(Box::new(Boolean {
+        Some(Expression::Boolean(Boolean {
             token,
             value: self.cur_token_is(TokenType::TRUE),
-        })))
+        }))
     }
 
-    fn parse_grouped_expression(&mut self) -> Option<NodeType> {
+    fn parse_grouped_expression(&mut self) -> Option<Expression> {
         self.next_token();
         // 只要提高括号内部表达式的优先级
         let exp = self.parse_expression(Precedence::LOWEST);
@@ -345,7 +742,7 @@ impl Parser {
         exp
     }
 
-    fn parse_if_expression(&mut self) -> Option<NodeType> {
+    fn parse_if_expression(&mut self) -> Option<Expression> {
         let token = self.cur_token.clone();
 
         if !self.expect_peek(TokenType::LPAREN) {
@@ -372,21 +769,35 @@ impl Parser {
                 return None;
             }
 
-            Some(Box::new(self.parse_block_statement()?))
+            Some(self.parse_block_statement()?)
         } else {
             None
         };
-        Some(NodeType::Expression(Box::new(IfExpression {
+        Some(Expression::If(IfExpression {
             token,
             condition: Box::new(condition),
-            consequence: Box::new(consequence),
+            consequence,
             alternative,
-        })))
+        }))
     }
 
-    fn parse_block_statement(&mut self) -> Option<NodeType> {
+    fn parse_block_statement(&mut self) -> Option<BlockStatement> {
+        if !self.enter() {
+            return None;
+        }
+        let checkpoint = self.checkpoint();
+        let block = self.parse_block_statement_inner();
+        self.depth -= 1;
+        if block.is_some() {
+            self.finish_node(checkpoint, SyntaxKind::Block);
+        }
+        block
+    }
+
+    fn parse_block_statement_inner(&mut self) -> Option<BlockStatement> {
         let token = self.cur_token.clone();
-        let mut statements = Vec::<NodeType>::new();
+        let mut statements = Vec::<Statement>::new();
+        let index = self.comments.start_block();
         self.next_token();
 
         while !self.cur_token_is(TokenType::RBRACE) && !self.cur_token_is(TokenType::EOF) {
@@ -397,15 +808,92 @@ impl Parser {
             }
             self.next_token();
         }
+        self.comments.end_block(index);
+
+        Some(BlockStatement { token, statements })
+    }
+
+    // match (<表达式>) { <模式> [if <守卫>] => <表达式或块语句>, ... }
+    // 分支体以{开头时按块语句解析，块语句后面的逗号可以省略
+    fn parse_match_expression(&mut self) -> Option<Expression> {
+        let token = self.cur_token.clone();
+
+        if !self.expect_peek(TokenType::LPAREN) {
+            return None;
+        }
+        self.next_token();
+        let subject = self.parse_expression(Precedence::LOWEST)?;
+        if !self.expect_peek(TokenType::RPAREN) {
+            return None;
+        }
+
+        if !self.expect_peek(TokenType::LBRACE) {
+            return None;
+        }
+
+        let mut arms = Vec::<MatchArm>::new();
+        let list = self.comments.start_list();
+        while !self.peek_token_is(TokenType::RBRACE) {
+            self.next_token();
+            let i = arms.len();
+            self.comments.start_item(list, i);
+            let checkpoint = self.checkpoint();
+            let pattern = self.parse_pattern()?;
+
+            let guard = if self.peek_token_is(TokenType::IF) {
+                self.next_token();
+                self.next_token();
+                Some(self.parse_expression(Precedence::LOWEST)?)
+            } else {
+                None
+            };
+
+            if !self.expect_peek(TokenType::FATARROW) {
+                return None;
+            }
+            self.next_token();
+
+            let is_block = self.cur_token_is(TokenType::LBRACE);
+            let body = if is_block {
+                Statement::Block(self.parse_block_statement()?)
+            } else {
+                let token = self.cur_token.clone();
+                Statement::Expression(ExpressionStatement {
+                    token,
+                    expression: Box::new(self.parse_expression(Precedence::LOWEST)?),
+                })
+            };
+            arms.push(MatchArm {
+                pattern,
+                guard,
+                body,
+            });
+            self.finish_node(checkpoint, SyntaxKind::MatchArm);
+            self.comments.end_item(list, i);
+
+            if self.peek_token_is(TokenType::COMMA) {
+                self.next_token();
+                self.comments.end_item(list, i);
+            } else if !is_block && !self.peek_token_is(TokenType::RBRACE) {
+                self.peek_error(TokenType::COMMA);
+                return None;
+            }
+        }
 
-        Some(NodeType::Statement(Box::new(BlockStatement {
+        if !self.expect_peek(TokenType::RBRACE) {
+            return None;
+        }
+        self.comments.end_list(list);
+
+        Some(Expression::Match(MatchExpression {
             token,
-            statements,
-        })))
+            subject: Box::new(subject),
+            arms,
+        }))
     }
 
     //fn <(<参数1>, <参数2>, <参数3>, ...)> <块语句>
-    fn parse_function_literal(&mut self) -> Option<NodeType> {
+    fn parse_function_literal(&mut self) -> Option<Expression> {
         let token = self.cur_token.clone();
 
         if !self.expect_peek(TokenType::LPAREN) {
@@ -420,88 +908,212 @@ impl Parser {
 
         let body = self.parse_block_statement()?;
 
-        Some(NodeType::Expression(Box::new(FunctionLiteral {
+        Some(Expression::Function(FunctionLiteral {
             token,
-            body: Box::new(body),
+            body,
             parameters,
-        })))
+        }))
     }
 
-    fn parse_function_parameters(&mut self) -> Option<Vec<NodeType>> {
-        let mut identifiers = Vec::<NodeType>::new();
-
-        if self.peek_token_is(TokenType::RPAREN) {
-            self.next_token();
-            return Some(identifiers);
-        }
-
+    //fn <名字>(<参数1>, <参数2>, ...) <块语句>
+    fn parse_function_declaration(&mut self) -> Option<Statement> {
+        let token = self.cur_token.clone();
         self.next_token();
-        let ident = Some(NodeType::Expression(Box::new(Identifier {
+
+        let name = Identifier {
             token: self.cur_token.clone(),
             value: self.cur_token.literal.clone(),
-        })));
-        if let Some(id) = ident {
-            identifiers.push(id);
-        } else {
+        };
+
+        if !self.expect_peek(TokenType::LPAREN) {
             return None;
         }
 
-        // 处理逗号分隔的参数列表
-        while self.peek_token_is(TokenType::COMMA) {
+        let parameters = self.parse_function_parameters()?;
+
+        if !self.expect_peek(TokenType::LBRACE) {
+            return None;
+        }
+
+        let body = self.parse_block_statement()?;
+
+        Some(Statement::FunctionDeclaration(FunctionDeclaration {
+            token: token.clone(),
+            name,
+            function: FunctionLiteral {
+                token,
+                parameters,
+                body,
+            },
+        }))
+    }
+
+    // (<参数>, <参数> = <默认值>, ...<rest参数>)
+    // 有默认值的参数之后不能再出现必填参数，rest参数只能是最后一个
+    fn parse_function_parameters(&mut self) -> Option<Vec<Parameter>> {
+        let checkpoint = self.checkpoint();
+        let parameters = self.parse_function_parameters_inner()?;
+        self.finish_node(checkpoint, SyntaxKind::ParameterList);
+        Some(parameters)
+    }
+
+    fn parse_function_parameters_inner(&mut self) -> Option<Vec<Parameter>> {
+        let mut identifiers = Vec::<Parameter>::new();
+        let list = self.comments.start_list();
+
+        if self.peek_token_is(TokenType::RPAREN) {
             self.next_token();
+            self.comments.end_list(list);
+            return Some(identifiers);
+        }
+
+        let mut seen_default = false;
+        loop {
             self.next_token();
+            let i = identifiers.len();
+            self.comments.start_item(list, i);
+            let checkpoint = self.checkpoint();
+
+            if self.cur_token_is(TokenType::ELLIPSIS) {
+                let token = self.cur_token.clone();
+                if !self.expect_peek(TokenType::IDENT) {
+                    return None;
+                }
+                identifiers.push(Parameter::Rest(RestParameter {
+                    token,
+                    name: Identifier {
+                        token: self.cur_token.clone(),
+                        value: self.cur_token.literal.clone(),
+                    },
+                }));
+                if self.peek_token_is(TokenType::COMMA) {
+                    self.errors
+                        .push("rest parameter must be the last parameter".to_string());
+                    return None;
+                }
+                self.finish_node(checkpoint, SyntaxKind::Parameter);
+                self.comments.end_item(list, i);
+                break;
+            }
 
-            let ident = Some(NodeType::Expression(Box::new(Identifier {
+            if !self.cur_token_is(TokenType::IDENT) {
+                self.errors.push(format!(
+                    "expected parameter name, got {:?} instead",
+                    self.cur_token.token_type
+                ));
+                return None;
+            }
+            let ident = Identifier {
                 token: self.cur_token.clone(),
                 value: self.cur_token.literal.clone(),
-            })));
+            };
 
-            if let Some(id) = ident {
-                identifiers.push(id);
-            } else {
+            if self.peek_token_is(TokenType::ASSIGN) {
+                self.next_token();
+                let token = self.cur_token.clone();
+                self.next_token();
+                let default = self.parse_expression(Precedence::LOWEST)?;
+                seen_default = true;
+                identifiers.push(Parameter::Default(DefaultParameter {
+                    token,
+                    name: ident,
+                    default: Box::new(default),
+                }));
+            } else if seen_default {
+                self.errors.push(format!(
+                    "required parameter {} cannot follow a parameter with a default value",
+                    ident.value
+                ));
                 return None;
+            } else {
+                identifiers.push(Parameter::Plain(ident));
+            }
+            self.finish_node(checkpoint, SyntaxKind::Parameter);
+            self.comments.end_item(list, i);
+
+            // 处理逗号分隔的参数列表
+            if !self.peek_token_is(TokenType::COMMA) {
+                break;
             }
+            self.next_token();
+            self.comments.end_item(list, i);
         }
 
         if !self.expect_peek(TokenType::RPAREN) {
             return None;
         }
+        self.comments.end_list(list);
         Some(identifiers)
     }
 
     //<表达式>(<以逗号分隔的表达式列表>)
-    fn parse_call_expression(&mut self, function: NodeType) -> Option<NodeType> {
+    fn parse_call_expression(&mut self, function: Expression) -> Option<Expression> {
         let token = self.cur_token.clone();
         let function = Box::new(function);
-        let arguments = self.parse_call_arguments()?;
+        let arguments = self.parse_list(TokenType::RPAREN, Parser::parse_call_argument)?;
 
-        Some(NodeType::Expression(Box::new(CallExpression {
+        Some(Expression::Call(CallExpression {
             token,
             function,
             arguments,
-        })))
+        }))
     }
 
-    fn parse_call_arguments(&mut self) -> Option<Vec<NodeType>> {
-        let mut args = Vec::<NodeType>::new();
-        if self.peek_token_is(TokenType::RPAREN) {
+    // 解析以逗号分隔、以end结尾的表达式列表，用于数组字面量
+    fn parse_expression_list(&mut self, end: TokenType) -> Option<Vec<Expression>> {
+        self.parse_list(end, |p| p.parse_expression(Precedence::LOWEST))
+    }
+
+    // 解析以逗号分隔、以end结尾的列表，每一项由parse_item解析
+    fn parse_list(
+        &mut self,
+        end: TokenType,
+        parse_item: fn(&mut Parser) -> Option<Expression>,
+    ) -> Option<Vec<Expression>> {
+        let mut args = Vec::<Expression>::new();
+        let list = self.comments.start_list();
+        if self.peek_token_is(end) {
             self.next_token();
+            self.comments.end_list(list);
             return Some(args);
         }
 
-        self.next_token();
-        args.push(self.parse_expression(Precedence::LOWEST)?);
-
-        while self.peek_token_is(TokenType::COMMA) {
+        loop {
             self.next_token();
+            let i = args.len();
+            self.comments.start_item(list, i);
+            args.push(parse_item(self)?);
+            self.comments.end_item(list, i);
+            if !self.peek_token_is(TokenType::COMMA) {
+                break;
+            }
             self.next_token();
-
-            args.push(self.parse_expression(Precedence::LOWEST)?);
+            self.comments.end_item(list, i);
         }
-        if !self.expect_peek(TokenType::RPAREN) {
+        if !self.expect_peek(end) {
             return None;
         }
+        self.comments.end_list(list);
 
         Some(args)
     }
 }
+
+// 表达式对应的具体语法树节点种类
+fn expression_kind(expr: &Expression) -> SyntaxKind {
+    match expr {
+        Expression::Identifier(_) => SyntaxKind::Identifier,
+        Expression::Integer(_) => SyntaxKind::Integer,
+        Expression::String(_) => SyntaxKind::String,
+        Expression::Boolean(_) => SyntaxKind::Boolean,
+        Expression::Prefix(_) => SyntaxKind::Prefix,
+        Expression::Infix(_) => SyntaxKind::Infix,
+        Expression::If(_) => SyntaxKind::If,
+        Expression::Function(_) => SyntaxKind::Function,
+        Expression::Call(_) => SyntaxKind::Call,
+        Expression::Array(_) => SyntaxKind::Array,
+        Expression::Hash(_) => SyntaxKind::Hash,
+        Expression::Match(_) => SyntaxKind::Match,
+        Expression::Spread(_) => SyntaxKind::Spread,
+    }
+}
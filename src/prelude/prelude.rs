@@ -0,0 +1,64 @@
+use std::env;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use crate::evaluator::context::EvalContext;
+use crate::evaluator::evaluator::eval_with_context;
+use crate::lexer::lexer::Lexer;
+use crate::object::Value;
+use crate::object::builtin::Builtin;
+use crate::object::environment::Environment;
+use crate::parser::parser::Parser;
+
+// 编译进可执行文件的标准函数，用Monkey写成，可以使用内置函数
+pub const PRELUDE: &str = include_str!("prelude.monkey");
+
+const RC_FILE: &str = ".monkeyrc";
+
+// 用户的启动脚本，$MONKEYRC 优先，否则是主目录下的 .monkeyrc
+pub fn rc_path() -> Option<PathBuf> {
+    if let Some(path) = env::var_os("MONKEYRC") {
+        return Some(PathBuf::from(path));
+    }
+    env::home_dir().map(|home| home.join(RC_FILE))
+}
+
+// 在env中依次执行prelude和用户的启动脚本
+pub fn load(env: &mut Environment) -> Result<(), String> {
+    load_prelude(env)?;
+    load_rc(env)
+}
+
+pub fn load_prelude(env: &mut Environment) -> Result<(), String> {
+    for builtin in Builtin::ALL {
+        env.set(builtin.name(), Value::Builtin(builtin));
+    }
+    run(PRELUDE, env).map_err(|message| format!("<prelude>: {}", message))
+}
+
+// 启动脚本不存在时什么也不做
+pub fn load_rc(env: &mut Environment) -> Result<(), String> {
+    let Some(path) = rc_path() else {
+        return Ok(());
+    };
+    match fs::read_to_string(&path) {
+        Ok(source) => {
+            run(&source, env).map_err(|message| format!("{}: {}", path.display(), message))
+        }
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(()),
+        Err(err) => Err(format!("{}: {}", path.display(), err)),
+    }
+}
+
+fn run(source: &str, env: &mut Environment) -> Result<(), String> {
+    let mut p = Parser::new(Lexer::new(source.to_string()));
+    let program = p.parse_program();
+    if let Some(error) = p.errors().first() {
+        return Err(error.clone());
+    }
+    match eval_with_context(&program, env, &mut EvalContext::new()) {
+        Value::Error(message) => Err(format!("ERROR: {}", message)),
+        _ => Ok(()),
+    }
+}
@@ -0,0 +1,181 @@
+use super::helper::{COMMAND, COMMENT, ILLEGAL, LITERAL, RESET, STRING};
+use crate::object::Value;
+
+// REPL显示求值结果的方式
+#[derive(Debug, Clone)]
+pub struct DisplayOptions {
+    pub width: usize,      // 一行放不下时数组和哈希分多行显示
+    pub max_items: usize,  // 数组和哈希最多显示的元素数
+    pub max_depth: usize,  // 更深的数组和哈希显示为 [...] 和 {...}
+    pub max_string: usize, // 字符串最多显示的字符数
+    pub colors: bool,
+}
+
+impl Default for DisplayOptions {
+    fn default() -> Self {
+        DisplayOptions {
+            width: 80,
+            max_items: 100,
+            max_depth: 8,
+            max_string: 1000,
+            colors: false,
+        }
+    }
+}
+
+// 显示值和它的类型，例如 3 : Integer。错误只显示错误信息
+pub fn display(value: &Value, options: &DisplayOptions) -> String {
+    if let Value::Error(_) = value {
+        return paint(&value.inspect(), Some(ILLEGAL), options.colors);
+    }
+    let mut out = String::new();
+    layout(value, 0, options).write(0, 0, options, &mut out);
+    out.push_str(" : ");
+    out.push_str(&paint(
+        value.type_obj().name(),
+        Some(COMMENT),
+        options.colors,
+    ));
+    out
+}
+
+// 先把值转成文档，再按宽度决定每个数组和哈希是否换行
+enum Doc {
+    Atom(String, Option<&'static str>),
+    Seq {
+        open: &'static str,
+        close: &'static str,
+        items: Vec<(Option<Doc>, Doc)>, // 哈希的元素带键
+        more: usize,                    // 没有显示的元素数
+    },
+}
+
+fn layout(value: &Value, depth: usize, options: &DisplayOptions) -> Doc {
+    match value {
+        Value::Integer(_) | Value::Boolean(_) => Doc::Atom(value.inspect(), Some(LITERAL)),
+        Value::Null => Doc::Atom(value.inspect(), Some(COMMENT)),
+        Value::String(string) => {
+            let mut text: String = string.chars().take(options.max_string).collect();
+            if text.len() < string.len() {
+                text.push_str("...");
+            }
+            Doc::Atom(format!("\"{}\"", text), Some(STRING))
+        }
+        Value::Function(function) => Doc::Atom(function.signature(), Some(COMMAND)),
+        Value::Builtin(builtin) => Doc::Atom(builtin.signature(), Some(COMMAND)),
+        Value::Array(_) if depth >= options.max_depth => Doc::Atom("[...]".to_string(), None),
+        Value::Hash(_) if depth >= options.max_depth => Doc::Atom("{...}".to_string(), None),
+        Value::Array(array) => Doc::Seq {
+            open: "[",
+            close: "]",
+            items: array
+                .elements
+                .iter()
+                .take(options.max_items)
+                .map(|element| (None, layout(element, depth + 1, options)))
+                .collect(),
+            more: array.elements.len().saturating_sub(options.max_items),
+        },
+        Value::Hash(hash) => Doc::Seq {
+            open: "{",
+            close: "}",
+            items: hash
+                .pairs
+                .values()
+                .take(options.max_items)
+                .map(|pair| {
+                    (
+                        Some(layout(&pair.key, depth + 1, options)),
+                        layout(&pair.value, depth + 1, options),
+                    )
+                })
+                .collect(),
+            more: hash.pairs.len().saturating_sub(options.max_items),
+        },
+        Value::ReturnValue(value) => layout(value, depth, options),
+        Value::Error(_) | Value::TailCall(_) => Doc::Atom(value.inspect(), Some(ILLEGAL)),
+    }
+}
+
+impl Doc {
+    // 写在一行时的宽度，不含颜色
+    fn width(&self) -> usize {
+        match self {
+            Doc::Atom(text, _) => text.chars().count(),
+            Doc::Seq { items, more, .. } => {
+                let items: usize = items
+                    .iter()
+                    .map(|(key, value)| {
+                        key.as_ref().map_or(0, |key| key.width() + 2) + value.width()
+                    })
+                    .sum();
+                let count = self.entries();
+                let more = if *more > 0 { more_text(*more).len() } else { 0 };
+                2 + items + more + 2 * count.saturating_sub(1)
+            }
+        }
+    }
+
+    // 显示的条目数，省略的部分算一条
+    fn entries(&self) -> usize {
+        match self {
+            Doc::Atom(..) => 1,
+            Doc::Seq { items, more, .. } => items.len() + usize::from(*more > 0),
+        }
+    }
+
+    // indent是当前行的缩进，column是已经占用的宽度，换行时子元素多缩进两格
+    fn write(&self, indent: usize, column: usize, options: &DisplayOptions, out: &mut String) {
+        match self {
+            Doc::Atom(text, color) => out.push_str(&paint(text, *color, options.colors)),
+            Doc::Seq {
+                open,
+                close,
+                items,
+                more,
+            } => {
+                let flat = column + self.width() <= options.width;
+                let (separator, inner) = if flat {
+                    (", ".to_string(), String::new())
+                } else {
+                    (",\n".to_string(), " ".repeat(indent + 2))
+                };
+                out.push_str(open);
+                if !flat && self.entries() > 0 {
+                    out.push('\n');
+                }
+                let mut entries = Vec::new();
+                for (key, value) in items {
+                    let mut entry = inner.clone();
+                    if let Some(key) = key {
+                        key.write(indent + 2, indent + 2, options, &mut entry);
+                        entry.push_str(": ");
+                    }
+                    let start = indent + 2 + key.as_ref().map_or(0, |key| key.width() + 2);
+                    value.write(indent + 2, start, options, &mut entry);
+                    entries.push(entry);
+                }
+                if *more > 0 {
+                    entries.push(inner + &more_text(*more));
+                }
+                out.push_str(&entries.join(&separator));
+                if !flat && self.entries() > 0 {
+                    out.push('\n');
+                    out.push_str(&" ".repeat(indent));
+                }
+                out.push_str(close);
+            }
+        }
+    }
+}
+
+fn more_text(more: usize) -> String {
+    format!("... {} more", more)
+}
+
+fn paint(text: &str, color: Option<&str>, colors: bool) -> String {
+    match color {
+        Some(color) if colors => format!("{}{}{}", color, text, RESET),
+        _ => text.to_string(),
+    }
+}
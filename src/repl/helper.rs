@@ -0,0 +1,186 @@
+use std::borrow::Cow;
+use std::env;
+
+use rustyline::completion::Completer;
+use rustyline::highlight::{CmdKind, Highlighter};
+use rustyline::hint::Hinter;
+use rustyline::validate::Validator;
+use rustyline::{Context, Helper};
+
+use crate::lexer::lexer::Lexer;
+use crate::token::token::TokenType;
+
+// REPL命令，以冒号开头时补全，带参数的命令补全后接着输入参数
+const COMMANDS: [&str; 10] = [
+    ":ast ", ":env", ":help", ":load ", ":quit", ":reset", ":save ", ":time ", ":tokens ", ":type ",
+];
+
+pub(super) const RESET: &str = "\x1b[0m";
+pub(super) const KEYWORD: &str = "\x1b[1;35m";
+pub(super) const LITERAL: &str = "\x1b[33m";
+pub(super) const STRING: &str = "\x1b[32m";
+pub(super) const COMMENT: &str = "\x1b[2m";
+pub(super) const ILLEGAL: &str = "\x1b[31m";
+pub(super) const COMMAND: &str = "\x1b[36m";
+
+// 设置了NO_COLOR时不输出颜色
+pub fn colors_enabled() -> bool {
+    env::var_os("NO_COLOR").is_none()
+}
+
+// 行编辑器的补全和语法高亮
+pub struct ReplHelper {
+    names: Vec<String>, // 环境中绑定的名字
+    colors: bool,
+}
+
+impl ReplHelper {
+    pub fn new() -> Self {
+        ReplHelper {
+            names: Vec::new(),
+            colors: colors_enabled(),
+        }
+    }
+
+    // 每次读入前更新，补全的是当前环境中的绑定
+    pub fn set_names(&mut self, names: Vec<String>) {
+        self.names = names;
+    }
+
+    // 按前缀补全关键字、绑定的名字和REPL命令，结果排序去重
+    pub fn candidates(&self, line: &str, pos: usize) -> (usize, Vec<String>) {
+        // pos和返回的位置都是字节下标，前一个字符可能是多字节的
+        let start = line[..pos]
+            .char_indices()
+            .rev()
+            .find(|&(_, c)| !(c.is_ascii_alphabetic() || c == '_'))
+            .map_or(0, |(i, c)| i + c.len_utf8());
+        let word = &line[start..pos];
+
+        // 行首的冒号开始一个命令
+        if line[..start].trim_start() == ":" {
+            let start = line.find(':').unwrap_or(0);
+            let prefix = &line[start..pos];
+            let commands = COMMANDS
+                .iter()
+                .filter(|command| command.starts_with(prefix))
+                .map(|command| command.to_string())
+                .collect();
+            return (start, commands);
+        }
+        if word.is_empty() {
+            return (pos, Vec::new());
+        }
+
+        let mut candidates: Vec<String> = Lexer::keywords()
+            .map(str::to_string)
+            .chain(self.names.iter().cloned())
+            .filter(|name| name.starts_with(word))
+            .collect();
+        candidates.sort();
+        candidates.dedup();
+        (start, candidates)
+    }
+}
+
+impl Default for ReplHelper {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// 按词法单元的类型给一行输入加上ANSI颜色，显示宽度不变
+pub fn highlight(line: &str) -> String {
+    // 命令名单独着色，其余部分按源码高亮
+    if let Some(rest) = line.trim_start().strip_prefix(':') {
+        let name_end =
+            line.len() - rest.len() + rest.find(char::is_whitespace).unwrap_or(rest.len());
+        let (name, source) = line.split_at(name_end);
+        return format!("{}{}{}{}", COMMAND, name, RESET, highlight(source));
+    }
+
+    // 词法分析器按字符计位置，这里换算成字节
+    let offsets: Vec<usize> = line
+        .char_indices()
+        .map(|(i, _)| i)
+        .chain(Some(line.len()))
+        .collect();
+    let mut lexer = Lexer::new(line.to_string());
+    lexer.set_keep_comments(true);
+
+    let mut out = String::new();
+    let mut end = 0;
+    loop {
+        let token = lexer.next_token();
+        if token.token_type == TokenType::EOF {
+            break;
+        }
+        let range = lexer.token_range();
+        let (start, stop) = (offsets[range.start], offsets[range.end]);
+        out.push_str(&line[end..start]);
+        let text = &line[start..stop];
+        match color(token.token_type) {
+            Some(color) => {
+                out.push_str(color);
+                out.push_str(text);
+                out.push_str(RESET);
+            }
+            None => out.push_str(text),
+        }
+        end = stop;
+    }
+    out.push_str(&line[end..]);
+    out
+}
+
+fn color(token_type: TokenType) -> Option<&'static str> {
+    match token_type {
+        TokenType::LET
+        | TokenType::FN
+        | TokenType::IF
+        | TokenType::ELSE
+        | TokenType::RETURN
+        | TokenType::MATCH => Some(KEYWORD),
+        TokenType::INT | TokenType::TRUE | TokenType::FALSE => Some(LITERAL),
+        TokenType::STRING => Some(STRING),
+        TokenType::COMMENT => Some(COMMENT),
+        TokenType::ILLEGAL => Some(ILLEGAL),
+        _ => None,
+    }
+}
+
+impl Completer for ReplHelper {
+    type Candidate = String;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _ctx: &Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<String>)> {
+        Ok(self.candidates(line, pos))
+    }
+}
+
+impl Highlighter for ReplHelper {
+    fn highlight<'l>(&self, line: &'l str, _pos: usize) -> Cow<'l, str> {
+        if self.colors {
+            Cow::Owned(highlight(line))
+        } else {
+            Cow::Borrowed(line)
+        }
+    }
+
+    // 每输入一个字符都重新高亮，例如输入引号后整行的颜色都会变
+    fn highlight_char(&self, _line: &str, _pos: usize, kind: CmdKind) -> bool {
+        self.colors && kind != CmdKind::MoveCursor
+    }
+}
+
+impl Hinter for ReplHelper {
+    type Hint = String;
+}
+
+impl Validator for ReplHelper {}
+
+impl Helper for ReplHelper {}
@@ -1 +1,3 @@
+pub mod display;
+pub mod helper;
 pub mod repl;
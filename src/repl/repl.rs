@@ -1,15 +1,36 @@
-use std::io::{self, Write};
+use std::env;
+use std::fs;
+use std::io::{self, IsTerminal};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Instant;
 
+use rustyline::Editor;
+use rustyline::error::ReadlineError;
+use rustyline::history::DefaultHistory;
+
+use super::display::{DisplayOptions, display};
+use super::helper::{ReplHelper, colors_enabled};
 use crate::{
-    ast::Node,
-    evaluator::{self, evaluator::eval},
+    ast::{Program, Statement},
+    cli::cli::strip_shebang,
+    dump::dump::{DumpMode, dump},
+    evaluator::{
+        context::{DEFAULT_MAX_DEPTH, EvalContext, run_with_stack, stack_size_for},
+        evaluator::eval_with_context,
+        trace::IndentedTrace,
+    },
     lexer::lexer::Lexer,
-    object::environment::Environment,
+    object::{ObjectType, Value, environment::Environment},
     parser::parser::Parser,
+    prelude::prelude,
     token::token::TokenType,
 };
 
 const PROMPT: &str = ">> ";
+const CONTINUATION_PROMPT: &str = ".. ";
+const HISTORY_FILE: &str = ".monkey_history";
 const MONKEY_FACE: &str = r#"            __,__
    .--.  .-"     "-.  .--.
   / .. \/  .-. .-.  \/ .. \
@@ -22,47 +43,495 @@ const MONKEY_FACE: &str = r#"            __,__
         '._ '-=-' _.'
            '-----'
 "#;
-pub fn start() {
-    let stdin = io::stdin();
+pub fn start() -> std::io::Result<()> {
+    run_with_stack(stack_size_for(DEFAULT_MAX_DEPTH), || {
+        start_with(None, false, false, DEFAULT_MAX_DEPTH)
+    })
+}
+
+// 以冒号开头的REPL命令，与Monkey源码分开解析
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Command {
+    Env,            // 列出绑定和类型
+    Type(String),   // 求值表达式，只输出类型
+    Ast(String),    // 输出表达式的AST
+    Tokens(String), // 输出表达式的词法单元
+    Time(String),   // 求值并输出用时
+    Load(String),   // 在当前环境中执行文件
+    Save(String),   // 把本次会话的输入写到文件
+    Reset,          // 清空环境和会话输入
+    Quit,
+    Help,
+}
+
+const HELP: &str = ":env            list the bindings and their types
+:type <expr>    evaluate <expr> and show its type
+:ast <expr>     show the AST of <expr>
+:tokens <expr>  show the tokens of <expr>
+:time <expr>    evaluate <expr> and show how long it took
+:load <file>    run <file> in the current environment
+:save <file>    write the inputs of this session to <file>
+:reset          clear all bindings and the session inputs
+:quit           leave the REPL
+:help           show this list";
+
+impl Command {
+    // 不以冒号开头时返回None，是Monkey源码
+    pub fn parse(input: &str) -> Option<Result<Command, String>> {
+        let input = input.trim();
+        let rest = input.strip_prefix(':')?;
+        let (name, arg) = match rest.find(char::is_whitespace) {
+            Some(i) => (&rest[..i], rest[i..].trim()),
+            None => (rest, ""),
+        };
+        let with_arg = |command: fn(String) -> Command, usage: &str| {
+            if arg.is_empty() {
+                Err(format!("usage: :{} {}", name, usage))
+            } else {
+                Ok(command(arg.to_string()))
+            }
+        };
+        let without_arg = |command: Command| {
+            if arg.is_empty() {
+                Ok(command)
+            } else {
+                Err(format!(":{} takes no arguments", name))
+            }
+        };
+        Some(match name {
+            "env" => without_arg(Command::Env),
+            "type" => with_arg(Command::Type, "<expr>"),
+            "ast" => with_arg(Command::Ast, "<expr>"),
+            "tokens" => with_arg(Command::Tokens, "<expr>"),
+            "time" => with_arg(Command::Time, "<expr>"),
+            "load" => with_arg(Command::Load, "<file>"),
+            "save" => with_arg(Command::Save, "<file>"),
+            "reset" => without_arg(Command::Reset),
+            "quit" | "q" => without_arg(Command::Quit),
+            "help" | "h" | "?" => without_arg(Command::Help),
+            _ => Err(format!("unknown command :{}, type :help for a list", name)),
+        })
+    }
+
+    // 参数是Monkey源码的命令
+    fn source(&self) -> Option<&str> {
+        match self {
+            Command::Type(source)
+            | Command::Ast(source)
+            | Command::Tokens(source)
+            | Command::Time(source) => Some(source),
+            _ => None,
+        }
+    }
+}
+
+// 一次REPL会话的状态
+pub struct Session {
+    env: Environment,
+    inputs: Vec<String>, // 解析成功的输入，:save时写出
+    dump_mode: Option<DumpMode>,
+    trace: bool,
+    display: DisplayOptions,
+    startup: bool,    // 新环境中先载入prelude和启动脚本
+    max_depth: usize, // 每条输入最多嵌套的函数调用数
+    interrupted: Arc<AtomicBool>,
+}
+
+impl Session {
+    pub fn new() -> Self {
+        Session {
+            env: Environment::new(),
+            inputs: Vec::new(),
+            dump_mode: None,
+            trace: false,
+            display: DisplayOptions::default(),
+            startup: false,
+            max_depth: DEFAULT_MAX_DEPTH,
+            interrupted: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    pub fn set_max_depth(&mut self, max_depth: usize) {
+        self.max_depth = max_depth;
+    }
+
+    pub fn env(&self) -> &Environment {
+        &self.env
+    }
+
+    pub fn inputs(&self) -> &[String] {
+        &self.inputs
+    }
+
+    // 执行一条输入，返回要输出的内容；:quit时返回None
+    pub fn execute(&mut self, input: &str) -> Option<Result<String, String>> {
+        match Command::parse(input) {
+            Some(Ok(command)) => self.command(command),
+            Some(Err(message)) => Some(Err(message)),
+            None => Some(self.source(input)),
+        }
+    }
+
+    fn source(&mut self, input: &str) -> Result<String, String> {
+        if let Some(mode) = self.dump_mode {
+            return dump(input, mode).map_err(|errors| parser_errors(&errors));
+        }
+        let value = self.eval(input, true)?;
+        Ok(self.show(value))
+    }
+
+    // 显示结果并绑定到 _，没有值时不输出
+    fn show(&mut self, value: Option<Value>) -> String {
+        match value {
+            Some(value) => {
+                let out = display(&value, &self.display);
+                self.bind_last(value);
+                out
+            }
+            None => String::new(),
+        }
+    }
+
+    fn bind_last(&mut self, value: Value) {
+        if value.type_obj() != ObjectType::Error {
+            self.env.set("_", value);
+        }
+    }
+
+    // 解析并在会话环境中求值，record为true时记下输入。
+    // 最后一条语句是let或函数声明时没有值，返回None
+    fn eval(&mut self, input: &str, record: bool) -> Result<Option<Value>, String> {
+        let mut ctx = EvalContext::with_max_depth(self.max_depth);
+        self.eval_with(input, record, &mut ctx)
+    }
+
+    fn eval_with(
+        &mut self,
+        input: &str,
+        record: bool,
+        ctx: &mut EvalContext,
+    ) -> Result<Option<Value>, String> {
+        let mut p = Parser::new(Lexer::new(input.to_string()));
+        let program = p.parse_program();
+        if !p.errors().is_empty() {
+            return Err(parser_errors(&p.errors()));
+        }
+        if record {
+            self.inputs.push(input.trim_end().to_string());
+        }
+        Ok(self.run(&program, ctx))
+    }
+
+    // 在会话环境中求值已经解析的程序，返回值的规则同eval
+    fn run(&mut self, program: &Program, ctx: &mut EvalContext) -> Option<Value> {
+        // 先清除输入期间按下的 Ctrl-C
+        self.interrupted.store(false, Ordering::Relaxed);
+        ctx.set_interrupt_flag(self.interrupted.clone());
+        if self.trace {
+            ctx.set_trace_sink(Box::new(IndentedTrace::new(io::stderr())));
+        }
+        let value = eval_with_context(program, &mut self.env, ctx);
+        match program.statements.last() {
+            Some(Statement::Expression(_) | Statement::Return(_) | Statement::Block(_)) => {
+                Some(value)
+            }
+            // 出错时仍然显示错误
+            _ if value.type_obj() == ObjectType::Error => Some(value),
+            _ => None,
+        }
+    }
+
+    fn command(&mut self, command: Command) -> Option<Result<String, String>> {
+        let source = command.source().unwrap_or_default().to_string();
+        let result = match command {
+            Command::Env => Ok(self.bindings()),
+            Command::Type(_) => {
+                // 在子作用域中求值，表达式中的let不影响会话
+                let outer = self.env.clone();
+                self.env = Environment::new_enclosed(&outer);
+                let result = self.eval(&source, false);
+                self.env = outer;
+                result.map(|value| match value {
+                    Some(value) => value.type_obj().name().to_string(),
+                    None => ObjectType::Null.name().to_string(),
+                })
+            }
+            Command::Ast(_) => {
+                dump(&source, DumpMode::Tree).map_err(|errors| parser_errors(&errors))
+            }
+            Command::Tokens(_) => {
+                dump(&source, DumpMode::Tokens).map_err(|errors| parser_errors(&errors))
+            }
+            Command::Time(_) => {
+                let mut ctx = EvalContext::with_max_depth(self.max_depth);
+                let start = Instant::now();
+                self.eval_with(&source, true, &mut ctx).map(|value| {
+                    let elapsed = start.elapsed();
+                    let mut out = self.show(value);
+                    if !out.is_empty() {
+                        out.push('\n');
+                    }
+                    out.push_str(&format!("time: {:?}, {} steps", elapsed, ctx.steps()));
+                    out
+                })
+            }
+            Command::Load(path) => self.load(&path),
+            Command::Save(path) => {
+                let mut out = self.inputs.join("\n");
+                out.push('\n');
+                fs::write(&path, out)
+                    .map(|_| match self.inputs.len() {
+                        1 => format!("saved 1 input to {}", path),
+                        n => format!("saved {} inputs to {}", n, path),
+                    })
+                    .map_err(|err| format!("{}: {}", path, err))
+            }
+            Command::Reset => {
+                self.inputs.clear();
+                self.reset_env().map(|_| String::new())
+            }
+            Command::Quit => return None,
+            Command::Help => Ok(HELP.to_string()),
+        };
+        Some(result)
+    }
+
+    // 换成新的环境，startup为true时载入prelude和启动脚本
+    fn reset_env(&mut self) -> Result<(), String> {
+        self.env = Environment::new();
+        if self.startup {
+            prelude::load(&mut self.env)?;
+        }
+        Ok(())
+    }
+
+    // 文件内容作为一条输入记下，:save得到的文件不依赖被载入的文件。
+    // 像输入一样逐条语句求值并绑定 _，:save写出的会话载入后 _ 的含义不变；
+    // 遇到错误时停止并显示错误
+    fn load(&mut self, path: &str) -> Result<String, String> {
+        let source = fs::read_to_string(path).map_err(|err| format!("{}: {}", path, err))?;
+        let source = strip_shebang(&source);
+        let mut p = Parser::new(Lexer::new(source.clone()));
+        let program = p.parse_program();
+        if !p.errors().is_empty() {
+            return Err(parser_errors(&p.errors()));
+        }
+        self.inputs.push(source.trim_end().to_string());
+
+        for statement in program.statements {
+            let mut ctx = EvalContext::with_max_depth(self.max_depth);
+            match self.run(
+                &Program {
+                    statements: vec![statement],
+                },
+                &mut ctx,
+            ) {
+                Some(value @ Value::Error(_)) => return Ok(display(&value, &self.display)),
+                Some(value) => self.bind_last(value),
+                None => {}
+            }
+        }
+        Ok(String::new())
+    }
+
+    // 按名字排序，每行一个绑定：名字、类型和值，函数只写签名
+    fn bindings(&self) -> String {
+        let bindings = self.env.bindings();
+        let width = bindings
+            .iter()
+            .map(|(name, _)| name.len())
+            .max()
+            .unwrap_or(0);
+        bindings
+            .iter()
+            .map(|(name, value)| {
+                let shown = match value {
+                    Value::Function(function) => function.signature(),
+                    Value::Builtin(builtin) => builtin.signature(),
+                    value => value.inspect(),
+                };
+                format!(
+                    "{:<width$}  {:<8}  {}",
+                    name,
+                    value.type_obj().name(),
+                    shown,
+                    width = width
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+impl Default for Session {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// dump不为None时只输出每行输入的词法单元或AST，不求值；trace为true时把求值过程写到标准错误；
+// bare为true时不载入prelude和启动脚本；max_depth是每条输入最多嵌套的函数调用数，
+// 调用者需要保证当前线程的栈足够大，见run_with_stack
+pub fn start_with(dump_mode: Option<DumpMode>, trace: bool, bare: bool, max_depth: usize) {
+    let mut rl: Editor<ReplHelper, DefaultHistory> = match Editor::new() {
+        Ok(rl) => rl,
+        Err(err) => {
+            eprintln!("failed to start line editor: {}", err);
+            return;
+        }
+    };
+    let history = history_path();
+    if let Some(path) = &history {
+        // 第一次运行时还没有历史文件
+        let _ = rl.load_history(path);
+    }
+
+    let mut session = Session::new();
+    session.dump_mode = dump_mode;
+    session.trace = trace;
+    session.max_depth = max_depth;
+    session.display.colors = colors_enabled() && io::stdout().is_terminal();
+    session.startup = !bare;
+    if let Err(message) = session.reset_env() {
+        eprintln!("{}", message);
+    }
+
+    // Ctrl-C 只设置取消标志，由求值器中止当前输入，环境中的绑定保持不变
+    if let Err(err) =
+        signal_hook::flag::register(signal_hook::consts::SIGINT, session.interrupted.clone())
+    {
+        eprintln!("failed to install Ctrl-C handler: {}", err);
+    }
 
-    let mut env = Environment::new();
+    rl.set_helper(Some(ReplHelper::new()));
     loop {
-        print!("{}", PROMPT);
-        io::stdout().flush().expect("fail");
-
-        let mut input = String::new();
-        match stdin.read_line(&mut input) {
-            Ok(n) if n > 0 => {
-                let l = Lexer::new(input.trim().to_string());
-                let mut p = Parser::new(l);
-                let program = p.parse_program();
-
-                if !p.errors().is_empty() {
-                    print_parser_errors(&p.errors());
-                    continue;
-                }
+        // 补全当前环境中的绑定
+        if let Some(helper) = rl.helper_mut() {
+            let names = session.env.bindings().into_iter().map(|(name, _)| name);
+            helper.set_names(names.collect());
+        }
+        let Some(input) = read_input(&mut rl) else {
+            break;
+        };
+        match session.execute(&input) {
+            None => break,
+            Some(Ok(out)) if out.is_empty() => {}
+            Some(Ok(out)) => println!("{}", out.trim_end()),
+            Some(Err(message)) => eprintln!("{}", message),
+        }
+    }
+
+    if let Some(path) = &history
+        && let Err(err) = rl.save_history(path)
+    {
+        eprintln!("failed to save history to {}: {}", path.display(), err);
+    }
+}
 
-                // 评估程序
-                let evaluated = eval(program.as_ref(), &mut env);
+// 历史文件，$MONKEY_HISTORY 优先，否则是主目录下的 .monkey_history
+fn history_path() -> Option<PathBuf> {
+    if let Some(path) = env::var_os("MONKEY_HISTORY") {
+        return Some(PathBuf::from(path));
+    }
+    env::home_dir().map(|home| home.join(HISTORY_FILE))
+}
 
-                // 打印评估结果
-                if evaluated.type_obj() != "NULL" {
-                    // 假设NULL_OBJ是"NULL"
-                    println!("{}", evaluated.inspect());
+// 读入一条完整的输入，没有输入完时用续行提示符继续读。
+// Ctrl-C放弃已经输入的内容；Ctrl-D时提交已经输入的内容，没有内容时返回None结束REPL
+fn read_input(rl: &mut Editor<ReplHelper, DefaultHistory>) -> Option<String> {
+    let mut input = String::new();
+    loop {
+        let prompt = if input.is_empty() {
+            PROMPT
+        } else {
+            CONTINUATION_PROMPT
+        };
+        match rl.readline(prompt) {
+            Ok(line) => {
+                if input.is_empty() && line.trim().is_empty() {
+                    continue;
+                }
+                input.push_str(&line);
+                input.push('\n');
+                // 命令只在参数是没写完的表达式时续行
+                let incomplete = match Command::parse(&input) {
+                    Some(Ok(command)) => command.source().is_some_and(is_incomplete),
+                    Some(Err(_)) => false,
+                    None => is_incomplete(&input),
+                };
+                if incomplete {
+                    continue;
                 }
+            }
+            Err(ReadlineError::Interrupted) => {
+                input.clear();
+                continue;
+            }
+            Err(ReadlineError::Eof) if !input.is_empty() => {}
+            Err(ReadlineError::Eof) => return None,
+            Err(err) => {
+                eprintln!("failed to read input: {}", err);
+                return None;
+            }
+        }
+        let _ = rl.add_history_entry(input.trim_end());
+        return Some(input);
+    }
+}
 
-                // println!("{:?}", program.to_string());
+// 输入是否还没有写完：括号没有闭合、字符串没有结束，或者以运算符、逗号或关键字结尾。
+// 多出来的右括号是语法错误，交给解析器报告
+pub fn is_incomplete(input: &str) -> bool {
+    let mut l = Lexer::new(input.to_string());
+    let mut depth: i64 = 0;
+    let mut last = TokenType::EOF;
+    loop {
+        let tok = l.next_token();
+        match tok.token_type {
+            TokenType::EOF => break,
+            TokenType::LPAREN | TokenType::LBRACE | TokenType::LBRACKET => depth += 1,
+            TokenType::RPAREN | TokenType::RBRACE | TokenType::RBRACKET => depth -= 1,
+            TokenType::STRING => {
+                let text = l.slice(l.token_range());
+                if text.len() < 2 || !text.ends_with('"') {
+                    return true;
+                }
             }
-            _ => break,
+            _ => {}
         }
+        last = tok.token_type;
     }
+    depth > 0
+        || matches!(
+            last,
+            TokenType::ASSIGN
+                | TokenType::PLUS
+                | TokenType::MINUS
+                | TokenType::ASTERISK
+                | TokenType::SLASH
+                | TokenType::LT
+                | TokenType::GT
+                | TokenType::EQ
+                | TokenType::NOTEQ
+                | TokenType::BANG
+                | TokenType::COMMA
+                | TokenType::COLON
+                | TokenType::FATARROW
+                | TokenType::ELLIPSIS
+                | TokenType::LET
+                | TokenType::FN
+                | TokenType::IF
+                | TokenType::ELSE
+                | TokenType::RETURN
+                | TokenType::MATCH
+        )
 }
 
-fn print_parser_errors(errors: &[String]) {
-    eprintln!("{}", MONKEY_FACE);
-    eprintln!("parsing ERROR!");
-    eprintln!("errors:");
+fn parser_errors(errors: &[String]) -> String {
+    let mut out = format!("{}parsing ERROR!\nerrors:", MONKEY_FACE);
     for msg in errors {
-        eprintln!("\t{}", msg);
+        out.push_str("\n\t");
+        out.push_str(msg);
     }
+    out
 }
@@ -1,14 +1,12 @@
 use crate::{
-    evaluator::evaluator::eval,
-    lexer::lexer::Lexer,
-    object::{
-        Object,
-        boolean::Boolean,
-        environment::Environment,
-        error::Error,
-        integer::Integer,
-        object::{BOOLEAN_OBJ, INTEGER_OBJ},
+    evaluator::{
+        context::{
+            DEFAULT_MAX_DEPTH, DEFAULT_STACK_SIZE, EvalContext, run_with_stack, stack_size_for,
+        },
+        evaluator::{eval, eval_with_context},
     },
+    lexer::lexer::Lexer,
+    object::{Value, environment::Environment, object::ObjectType},
     parser::parser::Parser,
 };
 
@@ -31,35 +29,28 @@ fn test_eval_integer_expression() {
     }
 }
 
-fn test_eval(input: &str) -> Box<dyn Object> {
+fn test_eval(input: &str) -> Value {
     let l = Lexer::new(input.to_string());
     let mut p = Parser::new(l);
     let program = p.parse_program();
     // println!("AST: {:#?}", program); // 打印AST
     let mut new_env = Environment::new();
-    eval(program.as_ref(), &mut new_env)
+    eval(&program, &mut new_env)
 }
 
-fn test_integer_object(
-    //obj: &dyn Object,
-    obj: &Box<dyn Object>,
-    expected: i64,
-) {
-    if obj.type_obj() != INTEGER_OBJ {
+fn test_integer_object(obj: &Value, expected: i64) {
+    if obj.type_obj() != ObjectType::Integer {
         panic!("对象不是整数。得到={:?}", obj.inspect());
     }
 
-    // 由于Rust的类型系统，我们需要确保对象是Integer类型
-    // 并且安全地访问其值
-    let int_obj = match obj.as_any().downcast_ref::<Integer>() {
-        Some(i) => i,
-        None => panic!("对象不能转换为Integer类型"),
+    let Value::Integer(value) = obj else {
+        panic!("对象不能转换为Integer类型");
     };
 
     assert_eq!(
-        int_obj.value, expected,
+        *value, expected,
         "对象的值错误。得到={}，期望={}",
-        int_obj.value, expected
+        value, expected
     );
 }
 
@@ -80,21 +71,12 @@ fn test_eval_boolean_expression() {
     }
 }
 
-fn test_boolean_object(obj: &Box<dyn Object>, expected: bool) {
-    if obj.type_obj() != BOOLEAN_OBJ {
+fn test_boolean_object(obj: &Value, expected: bool) {
+    if obj.type_obj() != ObjectType::Boolean {
         panic!("object is not Boolean. got{}", obj.inspect());
     }
-    // let bool_obj = match obj.as_any().downcast_ref::<Boolean>() {
-    //     Some(b) => b,
-    //     None => panic!(""),
-    // };
-
-    if let Some(b) = obj.as_any().downcast_ref::<Boolean>() {
-        assert_eq!(
-            b.value, expected,
-            "对象的值错误。得到={}，期望={}",
-            b.value, expected
-        );
+    if let Value::Boolean(b) = obj {
+        assert_eq!(*b, expected, "对象的值错误。得到={}，期望={}", b, expected);
     } else {
         panic!("对象不能转换为Boolean类型");
     }
@@ -144,7 +126,7 @@ fn test_eval_if_else_expression() {
                 // test_null_object(&evaluated);
                 assert_eq!(
                     evaluated.type_obj(),
-                    "NULL",
+                    ObjectType::Null,
                     "对象不是NULL。得到={:?}",
                     evaluated.inspect()
                 );
@@ -195,25 +177,60 @@ fn test_error_handling() {
     for (input, expected_msg) in tests {
         let evaluated = test_eval(input);
 
-        if evaluated.type_obj() != "ERROR" {
+        if evaluated.type_obj() != ObjectType::Error {
             panic!(
                 "没有返回错误对象。得到={}({})",
                 evaluated.type_obj(),
                 evaluated.inspect()
             );
         }
-        let error_obj = match evaluated.as_any().downcast_ref::<Error>() {
+        let error_obj = match error_message(&evaluated) {
             Some(e) => e,
             None => panic!("对象不能转换为Error类型"),
         };
         assert_eq!(
-            error_obj.message, expected_msg,
+            error_obj, expected_msg,
             "错误消息不匹配。期望=\"{}\", 得到=\"{}\"",
-            expected_msg, error_obj.message
+            expected_msg, error_obj
         );
     }
 }
 
+#[test]
+fn test_integer_arithmetic_errors() {
+    // 溢出和除以零是运行时错误，不会让解释器panic
+    let min = "(-9223372036854775807 - 1)";
+    let tests = vec![
+        ("1 / 0".to_string(), "division by zero"),
+        (
+            "let zero = 5 - 5; 10 / zero".to_string(),
+            "division by zero",
+        ),
+        ("9223372036854775807 + 1".to_string(), "integer overflow"),
+        (format!("{} - 1", min), "integer overflow"),
+        ("4611686018427387904 * 2".to_string(), "integer overflow"),
+        (format!("{} / -1", min), "integer overflow"),
+        (format!("-{}", min), "integer overflow"),
+        (
+            "fn f(n) { if (n == 0) { 1 } else { 10 * f(n - 1) } } f(19)".to_string(),
+            "integer overflow",
+        ),
+    ];
+
+    for (input, expected_msg) in tests {
+        let evaluated = test_eval(&input);
+        let error_obj = error_message(&evaluated)
+            .unwrap_or_else(|| panic!("no error object returned for {}", input));
+        assert_eq!(error_obj, expected_msg, "input: {}", input);
+    }
+
+    // 边界上的结果仍然是整数
+    test_integer_object(&test_eval("9223372036854775806 + 1"), i64::MAX);
+    test_integer_object(&test_eval(min), i64::MIN);
+    test_integer_object(&test_eval(&format!("{} / 1", min)), i64::MIN);
+    test_integer_object(&test_eval("-7 / 2"), -3);
+}
+
 #[test]
 fn test_let_statements() {
     let tests = vec![
@@ -227,3 +244,900 @@ fn test_let_statements() {
         test_integer_object(&test_eval(input), expected);
     }
 }
+
+#[test]
+fn test_collection_literals() {
+    let tests = vec![
+        (r#""hello""#, "hello"),
+        ("[1, 2 * 2, 3 + 3]", "[1, 4, 6]"),
+        (r#"{"one": 1, "two": 1 + 1}"#, "{one: 1, two: 2}"),
+        (r#"{"a": [1, 2]}"#, "{a: [1, 2]}"),
+    ];
+
+    for (input, expected) in tests {
+        assert_eq!(test_eval(input).inspect(), expected);
+    }
+}
+
+#[test]
+fn test_destructuring_let_statements() {
+    let tests = vec![
+        ("let [a, b] = [1, 2]; a + b;", "3"),
+        ("let [a, ...rest] = [1, 2, 3]; rest;", "[2, 3]"),
+        ("let [a, ...rest] = [1]; rest;", "[]"),
+        ("let [a, [b, c]] = [1, [2, 3]]; a + b * c;", "7"),
+        (
+            r#"let {name, age: years} = {"name": 1, "age": 30}; years;"#,
+            "30",
+        ),
+        (
+            r#"let {name} = {"name": "monkey", "age": 30}; name;"#,
+            "monkey",
+        ),
+        (r#"let {point: [x, y]} = {"point": [3, 4]}; x * y;"#, "12"),
+        (r#"let [{"k": v}, ...others] = [{"k": 5}, 6]; v;"#, "5"),
+    ];
+
+    for (input, expected) in tests {
+        assert_eq!(test_eval(input).inspect(), expected, "input: {}", input);
+    }
+}
+
+#[test]
+fn test_destructuring_let_errors() {
+    let tests = vec![
+        (
+            "let [a, b] = 5;",
+            "cannot destructure INTEGER with array pattern [a, b]",
+        ),
+        (
+            "let [a, b] = [1, 2, 3];",
+            "array pattern [a, b] length mismatch: want=2, got=3",
+        ),
+        (
+            "let [a, b, ...c] = [1];",
+            "array pattern [a, b, ...c] length mismatch: want at least 2, got=1",
+        ),
+        (
+            "let {name} = [1];",
+            "cannot destructure ARRAY with hash pattern {name}",
+        ),
+        (r#"let {name} = {"age": 1};"#, "key not found in hash: name"),
+        (
+            r#"let {a: [x]} = {"a": true};"#,
+            "cannot destructure BOOLEAN with array pattern [x]",
+        ),
+        ("let [a] = [missing];", "identifier not found: missing"),
+        ("{[1]: 2}", "unusable as hash key: ARRAY"),
+    ];
+
+    for (input, expected_msg) in tests {
+        let evaluated = test_eval(input);
+        let error_obj = match error_message(&evaluated) {
+            Some(e) => e,
+            None => panic!("没有返回错误对象。得到={}", evaluated.inspect()),
+        };
+        assert_eq!(error_obj, expected_msg);
+    }
+}
+
+#[test]
+fn test_match_expressions() {
+    let tests = vec![
+        ("match (1) { 1 => 10, _ => 20 }", "10"),
+        ("match (2) { 1 => 10, _ => 20 }", "20"),
+        ("match (-3) { -3 => true, _ => false }", "true"),
+        (r#"match ("b") { "a" => 1, "b" => 2, _ => 3 }"#, "2"),
+        ("match (1 < 2) { false => 0, true => 1 }", "1"),
+        ("match (5) { n => n * 2 }", "10"),
+        ("match ([1, 2]) { [x] => x, [x, y] => x + y }", "3"),
+        ("match ([1, 2, 3]) { [x, ...rest] => rest }", "[2, 3]"),
+        ("match ([1, 2]) { [1, 3] => 0, [1, y] => y }", "2"),
+        (r#"match ({"k": 7, "j": 1}) { {"k": v} => v }"#, "7"),
+        (r#"match ({"j": 1}) { {"k": v} => v, _ => 0 }"#, "0"),
+        (
+            "match (5) { n if n > 10 => 1, n if n > 3 => 2, _ => 3 }",
+            "2",
+        ),
+        ("match (5) { n => { let m = n + 1; m * 2 } }", "12"),
+        ("let n = 1; match (5) { n if n > 10 => n, _ => n }", "1"),
+        ("let x = match (3) { 3 => 30, _ => 0 }; x + 1", "31"),
+        ("if (true) { match (1) { 1 => { return 5; } }; 10 }", "5"),
+    ];
+
+    for (input, expected) in tests {
+        assert_eq!(test_eval(input).inspect(), expected, "input: {}", input);
+    }
+}
+
+#[test]
+fn test_match_bindings_are_scoped() {
+    // 分支中的模式变量和let只在分支内可见，不影响外层的同名绑定
+    let tests = vec![
+        ("let x = 1; match (5) { x => x }; x", "1"),
+        ("let x = 1; match (5) { x if x > 3 => x }; x", "1"),
+        ("let x = 1; match (5) { x if x > 9 => x, _ => 0 }; x", "1"),
+        (
+            "let y = match ([1, 2]) { [a, b] => a + b }; [y, a]",
+            "identifier not found: a",
+        ),
+        ("let m = 1; match (5) { n => { let m = n; m } }; m", "1"),
+        (
+            "fn f(n) { match (n) { 0 => 0, k => f(k - 1) } } f(5000)",
+            "0",
+        ),
+    ];
+
+    for (input, expected) in tests {
+        let evaluated = test_eval(input);
+        let actual = error_message(&evaluated)
+            .map(str::to_string)
+            .unwrap_or_else(|| evaluated.inspect());
+        assert_eq!(actual, expected, "input: {}", input);
+    }
+}
+
+#[test]
+fn test_match_expression_errors() {
+    let tests = vec![
+        ("match (3) { 1 => 1, 2 => 2 }", "no match arm for value: 3"),
+        ("match ([1]) { [a, b] => a }", "no match arm for value: [1]"),
+        (
+            "match (missing) { _ => 1 }",
+            "identifier not found: missing",
+        ),
+        (
+            "match (1) { n if n + true => 1 }",
+            "type mismatch: INTEGER + BOOLEAN",
+        ),
+        ("let [a, 2] = [1, 3];", "pattern 2 does not match 3"),
+    ];
+
+    for (input, expected_msg) in tests {
+        let evaluated = test_eval(input);
+        let error_obj = match error_message(&evaluated) {
+            Some(e) => e,
+            None => panic!("没有返回错误对象。得到={}", evaluated.inspect()),
+        };
+        assert_eq!(error_obj, expected_msg);
+    }
+}
+
+#[test]
+fn test_function_application() {
+    let tests = vec![
+        ("let identity = fn(x) { x; }; identity(5);", 5),
+        ("let identity = fn(x) { return x; }; identity(5);", 5),
+        ("let double = fn(x) { x * 2; }; double(5);", 10),
+        ("let add = fn(x, y) { x + y; }; add(5, 5);", 10),
+        ("let add = fn(x, y) { x + y; }; add(5 + 5, add(5, 5));", 20),
+        ("fn(x) { x; }(5)", 5),
+        (
+            "let newAdder = fn(x) { fn(y) { x + y } }; let addTwo = newAdder(2); addTwo(3);",
+            5,
+        ),
+    ];
+
+    for (input, expected) in tests {
+        test_integer_object(&test_eval(input), expected);
+    }
+}
+
+#[test]
+fn test_named_function_declarations() {
+    let tests = vec![
+        ("fn add(x, y) { x + y } add(2, 3)", 5),
+        // 直接递归
+        (
+            "fn fact(n) { if (n < 2) { 1 } else { n * fact(n - 1) } } fact(5)",
+            120,
+        ),
+        // 提升：声明之前就可以调用
+        ("let r = twice(4); fn twice(x) { x * 2 } r", 8),
+        // 相互递归
+        (
+            "fn isEven(n) { if (n == 0) { true } else { isOdd(n - 1) } }
+             fn isOdd(n) { if (n == 0) { false } else { isEven(n - 1) } }
+             if (isEven(10)) { 1 } else { 0 }",
+            1,
+        ),
+        // 函数体内的具名函数只在函数内可见，并能捕获外层参数
+        (
+            "fn outer(x) { fn inner(y) { x + y } inner(10) } outer(5)",
+            15,
+        ),
+        ("fn one() { 1 } let f = one; f()", 1),
+    ];
+
+    for (input, expected) in tests {
+        test_integer_object(&test_eval(input), expected);
+    }
+}
+
+#[test]
+fn test_function_object_inspect() {
+    let tests = vec![
+        ("fn(x) { x + 2; };", "fn(x) { (x + 2) }"),
+        ("fn add(a, b) { a + b } add", "fn add(a, b) { (a + b) }"),
+    ];
+
+    for (input, expected) in tests {
+        assert_eq!(test_eval(input).inspect(), expected);
+    }
+}
+
+#[test]
+fn test_function_errors() {
+    let tests = vec![
+        (
+            "fn add(x, y) { x + y } add(1)",
+            "wrong number of arguments to add: want=2, got=1",
+        ),
+        (
+            "fn(x) { x }(1, 2)",
+            "wrong number of arguments: want=1, got=2",
+        ),
+        ("5(1)", "not a function: INTEGER"),
+        ("fn f() { g() } f()", "identifier not found: g"),
+        ("fn f(x) { x } f(missing)", "identifier not found: missing"),
+        (
+            "fn f() { inner } fn g() { let inner = 1; f() } g()",
+            "identifier not found: inner",
+        ),
+    ];
+
+    for (input, expected_msg) in tests {
+        let evaluated = test_eval(input);
+        let error_obj = match error_message(&evaluated) {
+            Some(e) => e,
+            None => panic!("没有返回错误对象。得到={}", evaluated.inspect()),
+        };
+        assert_eq!(error_obj, expected_msg);
+    }
+}
+
+#[test]
+fn test_default_and_rest_parameters() {
+    let tests = vec![
+        ("fn add(a, b = 10) { a + b } add(1)", "11"),
+        ("fn add(a, b = 10) { a + b } add(1, 2)", "3"),
+        ("fn f(a, b = a * 2) { b } f(4)", "8"),
+        ("let k = 3; let f = fn(a = k) { a }; f()", "3"),
+        ("fn f(first, ...rest) { rest } f(1, 2, 3)", "[2, 3]"),
+        ("fn f(first, ...rest) { rest } f(1)", "[]"),
+        (
+            "fn f(a, b = 2, ...rest) { [a, b, rest] } f(1)",
+            "[1, 2, []]",
+        ),
+        (
+            "fn f(a, b = 2, ...rest) { [a, b, rest] } f(1, 5, 6, 7)",
+            "[1, 5, [6, 7]]",
+        ),
+        (
+            "fn add(a, b) { a + b } let args = [1, 2]; add(...args)",
+            "3",
+        ),
+        ("fn add(a, b, c) { a + b + c } add(1, ...[2, 3])", "6"),
+        ("fn f(...xs) { xs } f(...[1], 2, ...[3, 4])", "[1, 2, 3, 4]"),
+        ("fn f(...xs) { xs } f(...[])", "[]"),
+        ("fn(a, b = 1, ...c) { a }", "fn(a, b = 1, ...c) { a }"),
+    ];
+
+    for (input, expected) in tests {
+        assert_eq!(test_eval(input).inspect(), expected, "input: {}", input);
+    }
+}
+
+#[test]
+fn test_arity_errors() {
+    let tests = vec![
+        (
+            "fn f(a, b = 1) { a } f(1, 2, 3, 4, 5)",
+            "wrong number of arguments to f: want=1..2, got=5",
+        ),
+        (
+            "fn(a, b, c = 1) { a }(1)",
+            "wrong number of arguments: want=2..3, got=1",
+        ),
+        (
+            "fn f(a, b, ...rest) { a } f(1)",
+            "wrong number of arguments to f: want=2+, got=1",
+        ),
+        (
+            "fn f(a, b) { a } f(...[1, 2, 3])",
+            "wrong number of arguments to f: want=2, got=3",
+        ),
+        (
+            "fn f(a) { a } f(...5)",
+            "cannot spread INTEGER: expected ARRAY",
+        ),
+        (
+            "fn f(a = missing) { a } f()",
+            "identifier not found: missing",
+        ),
+    ];
+
+    for (input, expected_msg) in tests {
+        let evaluated = test_eval(input);
+        let error_obj = match error_message(&evaluated) {
+            Some(e) => e,
+            None => panic!("没有返回错误对象。得到={}", evaluated.inspect()),
+        };
+        assert_eq!(error_obj, expected_msg);
+    }
+}
+
+#[test]
+fn test_tail_calls() {
+    let tests = vec![
+        (
+            "fn count(n, acc) { if (n == 0) { acc } else { count(n - 1, acc + 1) } } count(1000000, 0)",
+            1000000,
+        ),
+        // return语句和match分支中的尾调用
+        (
+            "fn down(n) { if (n == 0) { return 7; } return down(n - 1); } down(100000)",
+            7,
+        ),
+        (
+            "fn down(n) { match (n) { 0 => 3, _ => down(n - 1) } } down(100000)",
+            3,
+        ),
+        // 相互递归的尾调用
+        (
+            "fn isEven(n) { if (n == 0) { true } else { isOdd(n - 1) } }
+             fn isOdd(n) { if (n == 0) { false } else { isEven(n - 1) } }
+             if (isEven(100000)) { 1 } else { 0 }",
+            1,
+        ),
+        // 非尾位置的调用保持原有语义
+        (
+            "fn sum(n) { if (n == 0) { 0 } else { n + sum(n - 1) } } sum(50)",
+            1275,
+        ),
+        ("fn f(x) { g(x) + 1 } fn g(x) { x * 2 } f(5)", 11),
+    ];
+
+    for (input, expected) in tests {
+        test_integer_object(&test_eval(input), expected);
+    }
+}
+
+#[test]
+fn test_max_recursion_depth() {
+    // 按函数调用计深度，在栈足够大的线程上求值，结果转成字符串带回
+    let eval_on_stack = |input: &str, max_depth: usize| {
+        let input = input.to_string();
+        run_with_stack(stack_size_for(max_depth), move || {
+            let program = Parser::new(Lexer::new(input)).parse_program();
+            let mut ctx = EvalContext::with_max_depth(max_depth);
+            eval_with_context(&program, &mut Environment::new(), &mut ctx).inspect()
+        })
+        .unwrap()
+    };
+
+    // 非尾递归超过上限时返回Error而不是让宿主栈溢出
+    let tests = vec![
+        "fn f(n) { f(n + 1) + 1 } f(0)",
+        "let f = fn(n) { 1 + f(n + 1) }; f(0)",
+        "fn f(n) { [f(n + 1)] } f(0)",
+        "fn f(n) { match (n) { x => { 1 + f(x + 1) } } } f(0)",
+    ];
+
+    for input in tests {
+        assert_eq!(
+            eval_on_stack(input, DEFAULT_MAX_DEPTH),
+            "ERROR: maximum recursion depth exceeded",
+            "input: {}",
+            input
+        );
+    }
+
+    // 默认上限允许1000层调用，不受函数体中表达式嵌套的影响
+    let sum = "fn sum(n) { if (n == 0) { 0 } else { n + (0 + (0 + sum(n - 1))) } } sum(N)";
+    assert_eq!(
+        eval_on_stack(&sum.replace('N', "999"), DEFAULT_MAX_DEPTH),
+        "499500"
+    );
+    assert_eq!(
+        eval_on_stack(&sum.replace('N', "1000"), DEFAULT_MAX_DEPTH),
+        "ERROR: maximum recursion depth exceeded"
+    );
+    assert_eq!(eval_on_stack(&sum.replace('N', "5000"), 5001), "12502500");
+
+    // 库的默认上限在默认大小的线程栈上不会溢出
+    let input =
+        "fn f(n) { match (n) { x => { let y = [{\"k\": 1 + (2 * (3 + f(x + 1)))}]; y } } } f(0)";
+    let evaluated = std::thread::Builder::new()
+        .stack_size(DEFAULT_STACK_SIZE)
+        .spawn(move || {
+            let program = Parser::new(Lexer::new(input.to_string())).parse_program();
+            eval(&program, &mut Environment::new()).inspect()
+        })
+        .unwrap()
+        .join()
+        .unwrap();
+    assert_eq!(evaluated, "ERROR: maximum recursion depth exceeded");
+
+    // 上限可以配置
+    let input = "fn sum(n) { if (n == 0) { 0 } else { n + sum(n - 1) } } sum(10)";
+    let program = Parser::new(Lexer::new(input.to_string())).parse_program();
+    let evaluated = eval_with_context(
+        &program,
+        &mut Environment::new(),
+        &mut EvalContext::with_max_depth(10),
+    );
+    let error_obj = error_message(&evaluated).unwrap();
+    assert_eq!(error_obj, "maximum recursion depth exceeded");
+
+    let evaluated = eval_with_context(
+        &program,
+        &mut Environment::new(),
+        &mut EvalContext::with_max_depth(100),
+    );
+    test_integer_object(&evaluated, 55);
+}
+
+#[test]
+fn test_deeply_nested_values() {
+    use crate::object::value::MAX_INSPECT_DEPTH;
+
+    // 循环构造的嵌套值不受调用深度限制，显示和释放时都不能递归到底
+    let input = "fn nest(i, acc) { if (i == 0) { acc } else { nest(i - 1, [{\"k\": acc}]) } }
+        fn wrap(i, f) { if (i == 0) { f } else { wrap(i - 1, fn() { f }) } }
+        let f = wrap(100000, fn() { 0 });
+        nest(100000, [])";
+    let program = Parser::new(Lexer::new(input.to_string())).parse_program();
+    let mut env = Environment::new();
+    let evaluated = eval(&program, &mut env);
+    let shown = evaluated.inspect();
+    assert_eq!(shown.matches("[{k: ").count(), MAX_INSPECT_DEPTH / 2);
+    assert!(shown.contains("[{k: [...]}]"), "{}", shown);
+    drop(evaluated);
+    drop(env);
+}
+
+#[test]
+fn test_step_budget_and_timeout() {
+    let parse = |input: &str| Parser::new(Lexer::new(input.to_string())).parse_program();
+    let expect_error = |evaluated: Value, expected: &str| {
+        let error_obj = error_message(&evaluated)
+            .unwrap_or_else(|| panic!("no error object returned, got {}", evaluated.inspect()));
+        assert_eq!(error_obj, expected);
+    };
+
+    // 死循环在步数预算耗尽后中止
+    let forever = parse("fn spin(n) { spin(n + 1) } spin(0)");
+    let mut ctx = EvalContext::new();
+    ctx.set_max_steps(10_000);
+    let evaluated = eval_with_context(&forever, &mut Environment::new(), &mut ctx);
+    expect_error(evaluated, "step budget of 10000 exceeded");
+    assert_eq!(ctx.steps(), 10_000);
+
+    // 预算足够时正常求值
+    let program = parse("fn sum(n) { if (n == 0) { 0 } else { n + sum(n - 1) } } sum(10)");
+    let mut ctx = EvalContext::new();
+    ctx.set_max_steps(10_000);
+    let evaluated = eval_with_context(&program, &mut Environment::new(), &mut ctx);
+    test_integer_object(&evaluated, 55);
+    assert!(ctx.steps() > 0 && ctx.steps() < 10_000);
+
+    // 超过截止时间后中止
+    let mut ctx = EvalContext::new();
+    ctx.set_timeout(std::time::Duration::from_millis(50));
+    let evaluated = eval_with_context(&forever, &mut Environment::new(), &mut ctx);
+    expect_error(evaluated, "evaluation timed out");
+}
+
+#[test]
+fn test_interrupt_flag() {
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    let parse = |input: &str| Parser::new(Lexer::new(input.to_string())).parse_program();
+    let mut env = Environment::new();
+    let flag = Arc::new(AtomicBool::new(false));
+
+    let mut ctx = EvalContext::new();
+    ctx.set_interrupt_flag(flag.clone());
+    eval_with_context(&parse("let x = 5;"), &mut env, &mut ctx);
+
+    // 另一个线程设置标志后，死循环被中止
+    let setter = flag.clone();
+    let handle = std::thread::spawn(move || {
+        std::thread::sleep(std::time::Duration::from_millis(50));
+        setter.store(true, Ordering::Relaxed);
+    });
+    let evaluated = eval_with_context(
+        &parse("fn spin(n) { spin(n + 1) } spin(0)"),
+        &mut env,
+        &mut ctx,
+    );
+    handle.join().unwrap();
+    let error_obj = error_message(&evaluated).unwrap();
+    assert_eq!(error_obj, "interrupted");
+
+    // 中止之前的绑定仍然保留
+    flag.store(false, Ordering::Relaxed);
+    let evaluated = eval_with_context(&parse("x"), &mut env, &mut ctx);
+    test_integer_object(&evaluated, 5);
+}
+
+#[test]
+fn test_allocation_budget() {
+    let parse = |input: &str| Parser::new(Lexer::new(input.to_string())).parse_program();
+
+    // 每次调用都分配一个新数组，旧数组虽然已经释放，累计分配仍然很快超过预算
+    let input = "fn spin(n) { let a = [n, n, n, n, n, n, n, n]; spin(n + 1) } spin(0)";
+    let mut ctx = EvalContext::new();
+    ctx.set_allocation_budget(1 << 20);
+    let evaluated = eval_with_context(&parse(input), &mut Environment::new(), &mut ctx);
+    let error_obj = error_message(&evaluated).unwrap();
+    assert_eq!(error_obj, "allocation budget of 1048576 bytes exceeded");
+    let stats = ctx.memory_stats();
+    assert!(stats.allocated_bytes > 1 << 20);
+    assert_eq!(stats.budget, Some(1 << 20));
+
+    // 展开参数、剩余参数和模式中的剩余元素新建的数组也计入预算，
+    // 否则参数个数每层翻倍的递归会在报错前耗尽内存
+    let tests = vec![
+        "fn f(n, ...xs) { if (n == 0) { 0 } else { f(n - 1, ...xs, ...xs) } } f(22, 1)",
+        "let xs = [1, 2, 3, 4, 5, 6, 7, 8];
+         fn g(a, b, c, d, e, f, g, h) { 0 } fn f(n) { g(...xs); f(n + 1) } f(0)",
+        "fn g(...a) { 0 } fn f(n) { g(1, 2, 3, 4, 5, 6, 7, 8); f(n + 1) } f(0)",
+        "let xs = [1, 2, 3, 4, 5, 6, 7, 8]; fn f(n) { let [...a] = xs; f(n + 1) } f(0)",
+        "let xs = [1, 2, 3, 4, 5, 6, 7, 8]; fn f(n) { match (xs) { [...a] => f(n + 1) } } f(0)",
+    ];
+    for input in tests {
+        let mut ctx = EvalContext::new();
+        ctx.set_allocation_budget(1 << 20);
+        let evaluated = eval_with_context(&parse(input), &mut Environment::new(), &mut ctx);
+        assert_eq!(
+            error_message(&evaluated),
+            Some("allocation budget of 1048576 bytes exceeded"),
+            "input: {}",
+            input
+        );
+        assert!(ctx.memory_stats().allocated_bytes < 4 << 20);
+    }
+
+    // 预算以内正常求值，并且可以读取统计
+    let mut ctx = EvalContext::new();
+    ctx.set_allocation_budget(1 << 20);
+    let evaluated = eval_with_context(
+        &parse("let a = [1, 2, 3]; let s = \"hello\"; 4"),
+        &mut Environment::new(),
+        &mut ctx,
+    );
+    test_integer_object(&evaluated, 4);
+    let stats = ctx.memory_stats();
+    assert!(stats.allocated_bytes > 0 && stats.allocated_bytes < 1 << 20);
+    assert_eq!(stats.allocations, 2);
+}
+
+#[test]
+fn test_run_script() {
+    use crate::cli::cli::{EXIT_PARSE_ERROR, EXIT_RUNTIME_ERROR, ScriptError, run_script};
+
+    let args = vec!["first".to_string(), "second".to_string()];
+
+    // 第一行的 #! 被忽略
+    let evaluated = run_script("#!/usr/bin/env monkey\nlet x = 2;\nx * 21", &[])
+        .ok()
+        .unwrap();
+    test_integer_object(&evaluated, 42);
+
+    let evaluated = run_script("let [a, b] = args; a", &args).ok().unwrap();
+    assert_eq!(evaluated.inspect(), "first");
+    let evaluated = run_script("args", &[]).ok().unwrap();
+    assert_eq!(evaluated.inspect(), "[]");
+
+    let err = run_script("let x = ;", &args).err().unwrap();
+    assert!(matches!(&err, ScriptError::Parse(errors) if !errors.is_empty()));
+    assert_eq!(err.exit_code(), EXIT_PARSE_ERROR);
+
+    let err = run_script("#!/bin/monkey\n1 + true", &args).err().unwrap();
+    assert_eq!(
+        err,
+        ScriptError::Runtime("type mismatch: INTEGER + BOOLEAN".to_string())
+    );
+    assert_eq!(err.exit_code(), EXIT_RUNTIME_ERROR);
+
+    // 过大的 --max-depth 是用法错误，而不是分配不了栈时 panic
+    let args = ["--max-depth=100000000000000000", "-e", "1"].map(String::from);
+    assert_eq!(crate::cli::cli::run(&args), EXIT_PARSE_ERROR);
+}
+
+#[test]
+fn test_call_graph() {
+    use crate::cli::cli::run_script_with_context;
+    use crate::dot::dot::call_graph_to_dot;
+    use crate::evaluator::call_graph::CallEdge;
+
+    let input = "fn fib(n) { if (n < 2) { return n; } fib(n - 1) + fib(n - 2) }
+                 let square = fn(x) { x * x };
+                 fn count(i) { if (i > 0) { count(i - 1) } else { square(fib(4)) } }
+                 count(2); fib(1); 1(2)";
+    let mut ctx = EvalContext::new();
+    ctx.enable_call_graph();
+    assert!(run_script_with_context(input, &[], &mut ctx).is_err());
+
+    let graph = ctx.call_graph().unwrap();
+    let edge = |caller: &str, callee: &str, count| CallEdge {
+        caller: caller.to_string(),
+        callee: callee.to_string(),
+        count,
+    };
+    // 尾调用的调用者是被替换的函数，出错后的调用栈不影响后面的调用
+    assert_eq!(
+        graph.edges(),
+        [
+            edge("<main>", "count", 1),
+            edge("count", "count", 2),
+            edge("count", "fib", 1),
+            edge("fib", "fib", 8),
+            edge("count", "fn(x)", 1),
+            edge("<main>", "fib", 1),
+        ]
+    );
+    assert_eq!(graph.calls("fib"), 10);
+    assert_eq!(graph.functions(), ["<main>", "count", "fib", "fn(x)"]);
+
+    let dot = call_graph_to_dot(graph);
+    assert!(dot.contains("  \"fib\" [label=\"fib\\n10 calls\"];\n"));
+    assert!(dot.contains("  \"count\" -> \"fn(x)\" [label=\"1\"];\n"));
+
+    // 默认不记录
+    let mut ctx = EvalContext::new();
+    run_script_with_context(input, &[], &mut ctx).err().unwrap();
+    assert!(ctx.call_graph().is_none());
+}
+
+#[test]
+fn test_trace() {
+    use std::cell::RefCell;
+    use std::io::Write;
+    use std::rc::Rc;
+
+    use crate::evaluator::trace::IndentedTrace;
+
+    #[derive(Clone, Default)]
+    struct Buffer(Rc<RefCell<Vec<u8>>>);
+
+    impl Write for Buffer {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.borrow_mut().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    let input = "let x = -1 + 2; fn f(n) { if (n > 0) { f(n - 1) } else { [n] } } f(x)";
+    let program = Parser::new(Lexer::new(input.to_string())).parse_program();
+    let buffer = Buffer::default();
+    let mut ctx = EvalContext::new();
+    ctx.set_trace_sink(Box::new(IndentedTrace::new(buffer.clone())));
+    let evaluated = eval_with_context(&program, &mut Environment::new(), &mut ctx);
+    assert_eq!(evaluated.inspect(), "[0]");
+
+    let trace = String::from_utf8(buffer.0.borrow().clone()).unwrap();
+    assert_eq!(
+        trace,
+        "LetStatement x
+  InfixExpression +
+    PrefixExpression -
+      IntegerLiteral 1 => 1
+    => -1
+    IntegerLiteral 2 => 2
+  => 1
+=> null
+FunctionDeclaration f => null
+ExpressionStatement
+  CallExpression
+    Identifier f => fn f(n)
+    Identifier x => 1
+    ExpressionStatement
+      IfExpression
+        InfixExpression >
+          Identifier n => 1
+          IntegerLiteral 0 => 0
+        => true
+        ExpressionStatement
+          CallExpression
+            Identifier f => fn f(n)
+            InfixExpression -
+              Identifier n => 1
+              IntegerLiteral 1 => 1
+            => 0
+          => tail call fn f(n)
+        => tail call fn f(n)
+      => tail call fn f(n)
+    => tail call fn f(n)
+    ExpressionStatement
+      IfExpression
+        InfixExpression >
+          Identifier n => 0
+          IntegerLiteral 0 => 0
+        => false
+        ExpressionStatement
+          ArrayLiteral
+            Identifier n => 0
+          => [0]
+        => [0]
+      => [0]
+    => [0]
+  => [0]
+=> [0]
+"
+    );
+}
+
+#[test]
+fn test_prelude() {
+    use crate::cli::cli::{ScriptError, run_script_in};
+    use crate::prelude::prelude::load_prelude;
+
+    let tests = vec![
+        ("len([])", "0"),
+        ("len([1, 2, 3])", "3"),
+        ("reverse([1, 2, 3])", "[3, 2, 1]"),
+        ("map([1, 2, 3], fn(x) { x * 2 })", "[2, 4, 6]"),
+        ("filter([1, 2, 3, 4], fn(x) { x > 2 })", "[3, 4]"),
+        ("reduce([1, 2, 3], 0, fn(acc, x) { acc + x })", "6"),
+        ("map(args, fn(s) { [s, s] })", "[[a, a], [b, b]]"),
+        (
+            "len(map(reverse(list(...args, ...args)), fn(x) { x }))",
+            "4",
+        ),
+        ("list()", "[]"),
+        ("filter([1, 2, 3], fn(x) { if (x > 5) { true } })", "[]"),
+        ("let f = map; f([1], fn(x) { -x })", "[-1]"),
+        ("slice([1, 2, 3, 4], 1, 3)", "[2, 3]"),
+        ("slice([1, 2, 3], -5, 10)", "[1, 2, 3]"),
+        ("slice([1, 2, 3], 2, 1)", "[]"),
+        ("len", "fn len(xs) { <builtin> }"),
+    ];
+    for (input, expected) in tests {
+        let mut env = Environment::new();
+        load_prelude(&mut env).unwrap();
+        let args = ["a".to_string(), "b".to_string()];
+        let value = run_script_in(input, &args, &mut env, &mut EvalContext::new());
+        assert_eq!(
+            value.map(|v| v.inspect()),
+            Ok(expected.to_string()),
+            "{}",
+            input
+        );
+    }
+
+    // 只有len和slice是内置函数，其余用Monkey写成
+    let mut env = Environment::new();
+    load_prelude(&mut env).unwrap();
+    for name in ["list", "reverse", "map", "filter", "reduce"] {
+        assert!(
+            matches!(env.get(name), Some(Value::Function(_))),
+            "{} is not a Monkey function",
+            name
+        );
+    }
+    for name in ["len", "slice"] {
+        assert!(matches!(env.get(name), Some(Value::Builtin(_))), "{}", name);
+    }
+
+    // 对半递归，长数组不会超出调用深度
+    let source = (0..10).fold("let xs = [1];".to_string(), |s, _| {
+        s + "let xs = list(...xs, ...xs);"
+    });
+    let value = run_script_in(
+        &(source + "reduce(map(xs, fn(x) { x * 2 }), 0, fn(a, b) { a + b })"),
+        &[],
+        &mut env,
+        &mut EvalContext::new(),
+    );
+    assert_eq!(value.ok().unwrap().inspect(), "2048");
+
+    // 长数组上不是平方复杂度：分配预算按累计分配计算，每一步复制剩下的数组时远远超出
+    let mut env = Environment::new();
+    load_prelude(&mut env).unwrap();
+    let mut ctx = EvalContext::new();
+    ctx.set_allocation_budget(128 * 1024 * 1024);
+    let source = (0..14).fold("let xs = [1];".to_string(), |s, _| {
+        s + "let xs = list(...xs, ...xs);"
+    });
+    let value = run_script_in(
+        &(source
+            + "let ys = filter(map(reverse(xs), fn(x) { x * 2 }), fn(x) { x > 0 });
+               [len(ys), reduce(ys, 0, fn(a, b) { a + b })]"),
+        &[],
+        &mut env,
+        &mut ctx,
+    );
+    assert_eq!(value.map(|v| v.inspect()), Ok("[16384, 32768]".to_string()));
+
+    let tests = vec![
+        (
+            "map([1])",
+            "wrong number of arguments to map: want=2, got=1",
+        ),
+        ("len(\"abc\")", "len: expected ARRAY, got STRING"),
+        (
+            "slice([1], 0, true)",
+            "slice: expected INTEGER, got BOOLEAN",
+        ),
+        (
+            "slice([1], 0)",
+            "wrong number of arguments to slice: want=3, got=2",
+        ),
+        ("reduce([1, 2], 0, fn(a, b) { a / 0 })", "division by zero"),
+        ("map([1], 2)", "not a function: INTEGER"),
+    ];
+    for (input, expected) in tests {
+        let mut env = Environment::new();
+        load_prelude(&mut env).unwrap();
+        let value = run_script_in(input, &[], &mut env, &mut EvalContext::new());
+        assert_eq!(
+            value.map(|v| v.inspect()),
+            Err(ScriptError::Runtime(expected.to_string())),
+            "{}",
+            input
+        );
+    }
+}
+
+#[test]
+fn test_closure_cycles_are_collected() {
+    use crate::object::environment::{collect_cycles, live_scopes};
+
+    // mk的调用作用域中的f捕获了这个作用域，形成环
+    let input = "let mk = fn() { let f = fn() { 1 }; f }; let g = mk();";
+    let program = Parser::new(Lexer::new(input.to_string())).parse_program();
+    let mut env = Environment::new();
+    eval(&program, &mut env);
+    let Some(Value::Function(g)) = env.remove("g") else {
+        panic!("g is not a function");
+    };
+    let scope = g.env.downgrade();
+    collect_cycles();
+    assert!(scope.upgrade().is_some(), "scope freed while g is alive");
+    drop(g);
+    assert!(scope.upgrade().is_some());
+    collect_cycles();
+    assert!(
+        scope.upgrade().is_none(),
+        "scope held only by a cycle not freed"
+    );
+
+    // 作用域数量达到阈值时自动回收
+    let input = "let mk = fn(n) { let f = fn() { n }; 0 };
+        fn run(i) { if (i == 0) { 0 } else { mk(i); run(i - 1) } }
+        run(20000)";
+    let program = Parser::new(Lexer::new(input.to_string())).parse_program();
+    eval(&program, &mut env);
+    assert!(live_scopes() < 4096, "{} scopes alive", live_scopes());
+
+    // 只登记被函数捕获的作用域
+    let before = live_scopes();
+    let call_scope = Environment::new_enclosed(&env);
+    assert_eq!(live_scopes(), before);
+    let captured = call_scope.capture();
+    assert_eq!(live_scopes(), before + 1);
+    drop((call_scope, captured));
+
+    // 遍历嵌套很深的值时不递归
+    let input = "fn nest(i, acc) { if (i == 0) { acc } else { nest(i - 1, [acc]) } }
+        let deep = nest(100000, []);
+        let keep = fn() { deep };";
+    let program = Parser::new(Lexer::new(input.to_string())).parse_program();
+    eval(&program, &mut env);
+    collect_cycles();
+    assert!(matches!(env.get("deep"), Some(Value::Array(_))));
+}
+
+fn error_message(obj: &Value) -> Option<&str> {
+    match obj {
+        Value::Error(message) => Some(message),
+        _ => None,
+    }
+}
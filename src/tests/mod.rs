@@ -1,2 +1,3 @@
+#[cfg(test)]
 mod evaluator_test;
 mod tests;
@@ -1,11 +1,6 @@
 #[cfg(test)]
 mod tests {
-    use crate::ast::{
-        BlockStatement, Boolean, CallExpression, Expression, FunctionLiteral, Identifier,
-        IfExpression, InfixExpression, IntegerLiteral, LetStatement, NodeType, PrefixExpression,
-        ReturnStatement,
-    };
-    use crate::ast::{ExpressionStatement, Node};
+    use crate::ast::{Expression, Node, Parameter, Statement};
     use crate::lexer::lexer::Lexer;
     use crate::parser::parser::Parser;
     use crate::token::token::TokenType;
@@ -208,23 +203,16 @@ mod tests {
                 return;
             }
 
-            match stmt {
-                NodeType::Statement(statement) => {
-                    let let_stmt = statement
-                        .as_any()
-                        .downcast_ref::<LetStatement>()
-                        .expect("statement not LetStatement");
-
-                    if !test_literal_expression(&let_stmt.value, tt.expected_value) {
-                        panic!("Value test failed");
-                    }
-                }
-                NodeType::Expression(_) => panic!("stmt is not Statement"),
+            let Statement::Let(let_stmt) = stmt else {
+                panic!("statement not LetStatement");
+            };
+            if !test_literal_expression(&let_stmt.value, tt.expected_value) {
+                panic!("Value test failed");
             }
         }
     }
 
-    fn test_let_statement(stmt: &NodeType, name: &str) -> bool {
+    fn test_let_statement(stmt: &Statement, name: &str) -> bool {
         if stmt.token_literal() != "let" {
             println!(
                 "stmt.token_literal not 'let'. got={:?}",
@@ -233,42 +221,29 @@ mod tests {
             return false;
         }
 
-        // 尝试将 NodeType::Statement 转换为 LetStatement
-        match stmt {
-            NodeType::Statement(statement) => {
-                // 使用as_any()来进行向下转型
-                let let_stmt = match statement.as_any().downcast_ref::<LetStatement>() {
-                    Some(ls) => ls,
-                    None => {
-                        println!("statement not LetStatement. got={:?}", statement);
-                        return false;
-                    }
-                };
-
-                if &let_stmt.name.value != name {
-                    println!(
-                        "let_stmt.name.value not '{}'. got={}",
-                        name, let_stmt.name.value
-                    );
-                    return false;
-                }
+        let Statement::Let(let_stmt) = stmt else {
+            println!("statement not LetStatement. got={:?}", stmt);
+            return false;
+        };
 
-                if let_stmt.name.token_literal().as_str() != name {
-                    println!(
-                        "let_stmt.name.token_literal() not '{}'. got={}",
-                        name,
-                        let_stmt.name.token_literal()
-                    );
-                    return false;
-                }
+        if let_stmt.name.value != name {
+            println!(
+                "let_stmt.name.value not '{}'. got={}",
+                name, let_stmt.name.value
+            );
+            return false;
+        }
 
-                true
-            }
-            NodeType::Expression(_) => {
-                println!("stmt not Statement. got=Expression");
-                false
-            }
+        if let_stmt.name.token_literal().as_str() != name {
+            println!(
+                "let_stmt.name.token_literal() not '{}'. got={}",
+                name,
+                let_stmt.name.token_literal()
+            );
+            return false;
         }
+
+        true
     }
 
     fn check_parser_errors(p: &Parser) {
@@ -282,6 +257,14 @@ mod tests {
         }
     }
 
+    // 取出唯一一条表达式语句中的表达式
+    fn expression_statement(stmt: &Statement) -> &Expression {
+        match stmt {
+            Statement::Expression(expr_stmt) => &expr_stmt.expression,
+            _ => panic!("stmt not ExpressionStatement. got={:?}", stmt),
+        }
+    }
+
     #[test]
     fn test_return_statements() {
         struct Test {
@@ -316,54 +299,27 @@ mod tests {
                 );
             }
 
-            match &program.statements[0] {
-                NodeType::Statement(stmt) => {
-                    let return_stmt = stmt
-                        .as_any()
-                        .downcast_ref::<ReturnStatement>()
-                        .expect("not RetrunStatement!");
-
-                    assert_eq!(
-                        return_stmt.token_literal(),
-                        "return",
-                        "returnStmt.token_literal not 'return', got {}",
-                        return_stmt.token_literal()
-                    );
-                    if !test_literal_expression(&return_stmt.return_value, tt.expected_value) {
-                        panic!("return_stmt.return_value not matching expected value");
-                    }
-                }
-                _ => panic!("stmt not Statement. got Expression"),
+            let Statement::Return(return_stmt) = &program.statements[0] else {
+                panic!("not RetrunStatement!");
+            };
+
+            assert_eq!(
+                return_stmt.token_literal(),
+                "return",
+                "returnStmt.token_literal not 'return', got {}",
+                return_stmt.token_literal()
+            );
+            if !test_literal_expression(&return_stmt.return_value, tt.expected_value) {
+                panic!("return_stmt.return_value not matching expected value");
             }
         }
-
-        // for stmt in &program.statements {
-        //     match stmt {
-        //         NodeType::Statement(statement) => {
-        //             let return_stmt = match statement.as_any().downcast_ref::<ReturnStatement>() {
-        //                 Some(rs) => rs,
-        //                 None => {
-        //                     panic!("statement not ReturnStatement. got={:?}", statement);
-        //                 }
-        //             };
-        //             assert_eq!(
-        //                 return_stmt.token_literal(),
-        //                 "return",
-        //                 "returnStmt.token_literal not 'return',got {}",
-        //                 return_stmt.token_literal()
-        //             );
-        //         }
-        //         NodeType::Expression(_) => {
-        //             panic!("stmt not Statement. got Expression");
-        //         }
-        //     }
-        // }
     }
 
     #[test]
     fn test_to_string() {
         use crate::ast::{
-            ExpressionStatement, Identifier, LetStatement, NodeType, Program, ReturnStatement,
+            Expression, ExpressionStatement, Identifier, LetStatement, Program, ReturnStatement,
+            Statement,
         };
         use crate::token::token::{Token, TokenType};
 
@@ -390,13 +346,13 @@ mod tests {
                 },
                 value: "x".to_string(),
             }),
-            value: Box::new(NodeType::Expression(Box::new(Identifier {
+            value: Box::new(Expression::Identifier(Identifier {
                 token: Token {
                     token_type: TokenType::IDENT,
                     literal: "y".to_string(),
                 },
                 value: "y".to_string(),
-            }))),
+            })),
         };
         assert_eq!(
             let_stmt.to_string(),
@@ -410,13 +366,13 @@ mod tests {
                 token_type: TokenType::RETURN,
                 literal: "return".to_string(),
             },
-            return_value: Box::new(NodeType::Expression(Box::new(Identifier {
+            return_value: Box::new(Expression::Identifier(Identifier {
                 token: Token {
                     token_type: TokenType::IDENT,
                     literal: "result".to_string(),
                 },
                 value: "result".to_string(),
-            }))),
+            })),
         };
         assert_eq!(
             return_stmt.to_string(),
@@ -430,13 +386,13 @@ mod tests {
                 token_type: TokenType::IDENT,
                 literal: "x".to_string(),
             },
-            expression: Box::new(NodeType::Expression(Box::new(Identifier {
+            expression: Box::new(Expression::Identifier(Identifier {
                 token: Token {
                     token_type: TokenType::IDENT,
                     literal: "x".to_string(),
                 },
                 value: "x".to_string(),
-            }))),
+            })),
         };
         assert_eq!(
             expr_stmt.to_string(),
@@ -447,9 +403,9 @@ mod tests {
         // 测试完整程序
         let program = Program {
             statements: vec![
-                NodeType::Statement(Box::new(let_stmt)),
-                NodeType::Statement(Box::new(return_stmt)),
-                NodeType::Statement(Box::new(expr_stmt)),
+                Statement::Let(let_stmt),
+                Statement::Return(return_stmt),
+                Statement::Expression(expr_stmt),
             ],
         };
 
@@ -479,25 +435,11 @@ mod tests {
             );
         }
 
-        match &program.statements[0] {
-            NodeType::Statement(stmt) => {
-                let expr_stmt = stmt
-                    .as_any()
-                    .downcast_ref::<ExpressionStatement>()
-                    .expect("stmt not ExpressionStatement");
-
-                let expr = &*expr_stmt.expression;
-                if let NodeType::Expression(e) = expr {
-                    let ident = e
-                        .as_any()
-                        .downcast_ref::<Identifier>()
-                        .expect("not Identifier");
-                    assert_eq!(ident.value, "foobar");
-                    assert_eq!(ident.token_literal(), "foobar");
-                }
-            }
-            NodeType::Expression(_) => panic!("program.statements[0] not Statement"),
-        }
+        let Expression::Identifier(ident) = expression_statement(&program.statements[0]) else {
+            panic!("not Identifier");
+        };
+        assert_eq!(ident.value, "foobar");
+        assert_eq!(ident.token_literal(), "foobar");
     }
 
     #[test]
@@ -515,22 +457,11 @@ mod tests {
             );
         }
 
-        match &program.statements[0] {
-            NodeType::Statement(stmt) => {
-                let expr_stmt = stmt
-                    .as_any()
-                    .downcast_ref::<ExpressionStatement>()
-                    .expect("stmt not ExpressionStatement");
-
-                let expr = &*expr_stmt.expression;
-                if let NodeType::Expression(e) = expr {
-                    let ident = e.as_any().downcast_ref::<Boolean>().expect("not Boolean");
-                    assert_eq!(ident.value, true);
-                    assert_eq!(ident.token_literal(), "true");
-                }
-            }
-            NodeType::Expression(_) => panic!("program.statements[0] not Statement"),
-        }
+        let Expression::Boolean(ident) = expression_statement(&program.statements[0]) else {
+            panic!("not Boolean");
+        };
+        assert!(ident.value);
+        assert_eq!(ident.token_literal(), "true");
     }
 
     #[test]
@@ -547,36 +478,22 @@ mod tests {
                 program.statements.len()
             );
         }
-        match &program.statements[0] {
-            NodeType::Statement(stmt) => {
-                let expr_stmt = stmt
-                    .as_any()
-                    .downcast_ref::<ExpressionStatement>()
-                    .expect("stmt not ExpressionStatement");
-                if let NodeType::Expression(expr) = &*expr_stmt.expression {
-                    let literal = expr
-                        .as_any()
-                        .downcast_ref::<IntegerLiteral>()
-                        .expect("expr not IntegerLiteral");
-
-                    assert_eq!(
-                        literal.value, 5,
-                        "literal.value not {}. got={}",
-                        5, literal.value
-                    );
-                    assert_eq!(
-                        literal.token_literal(),
-                        "5",
-                        "literal.token_literal not {}. got={}",
-                        "5",
-                        literal.token_literal()
-                    );
-                }
-            }
-            NodeType::Expression(_) => {
-                panic!("program.Statements[0] is not ast.ExpressionStatement.")
-            }
-        }
+        let Expression::Integer(literal) = expression_statement(&program.statements[0]) else {
+            panic!("expr not IntegerLiteral");
+        };
+
+        assert_eq!(
+            literal.value, 5,
+            "literal.value not {}. got={}",
+            5, literal.value
+        );
+        assert_eq!(
+            literal.token_literal(),
+            "5",
+            "literal.token_literal not {}. got={}",
+            "5",
+            literal.token_literal()
+        );
     }
 
     #[test]
@@ -630,65 +547,41 @@ mod tests {
                 );
             }
 
-            match &program.statements[0] {
-                NodeType::Statement(stmt) => {
-                    let expr_stmt = stmt
-                        .as_any()
-                        .downcast_ref::<ExpressionStatement>()
-                        .expect("program.statements[0] is not ExpressionStatement");
-
-                    let prefix_expr = match &*expr_stmt.expression {
-                        NodeType::Expression(expr) => expr
-                            .as_any()
-                            .downcast_ref::<PrefixExpression>()
-                            .expect("expression is not PrefixExpression"),
-                        _ => panic!("not an Expression type"),
-                    };
-
-                    assert_eq!(
-                        prefix_expr.operator, tt.operator,
-                        "exp.operator is not '{}'. got={}",
-                        tt.operator, prefix_expr.operator
-                    );
-
-                    match tt.value {
-                        TestValue::Boolean(bo) => test_boolean_literal(&prefix_expr.right, bo),
-                        TestValue::Int(value) => test_integer_literal(&prefix_expr.right, value),
-                    };
-                }
-                NodeType::Expression(_) => panic!("program.statements[0] is not Statement"),
-            }
-        }
-    }
-    fn test_integer_literal(node: &NodeType, value: i64) -> bool {
-        if let NodeType::Expression(expr) = node {
-            let literal = match expr.as_any().downcast_ref::<IntegerLiteral>() {
-                Some(il) => il,
-                None => {
-                    panic!("expr is not IntegerLiteral. got={:?}", expr);
-                    return false;
-                }
+            let Expression::Prefix(prefix_expr) = expression_statement(&program.statements[0])
+            else {
+                panic!("expression is not PrefixExpression");
             };
 
-            if literal.value != value {
-                panic!("literal.value not {}. got={}", value, literal.value);
-                return false;
-            }
+            assert_eq!(
+                prefix_expr.operator, tt.operator,
+                "exp.operator is not '{}'. got={}",
+                tt.operator, prefix_expr.operator
+            );
 
-            if literal.token_literal() != value.to_string() {
-                panic!(
-                    "literal.token_literal not {}. got={}",
-                    value,
-                    literal.token_literal()
-                );
-                return false;
-            }
+            match tt.value {
+                TestValue::Boolean(bo) => test_boolean_literal(&prefix_expr.right, bo),
+                TestValue::Int(value) => test_integer_literal(&prefix_expr.right, value),
+            };
+        }
+    }
+    fn test_integer_literal(expr: &Expression, value: i64) -> bool {
+        let Expression::Integer(literal) = expr else {
+            panic!("expr is not IntegerLiteral. got={:?}", expr);
+        };
+
+        if literal.value != value {
+            panic!("literal.value not {}. got={}", value, literal.value);
+        }
 
-            true
-        } else {
-            panic!("node is not Expression");
-            false
+        if literal.token_literal() != value.to_string() {
+            panic!(
+                "literal.token_literal not {}. got={}",
+                value,
+                literal.token_literal()
+            );
         }
+
+        true
     }
 
     // 中缀表达式
@@ -807,55 +700,30 @@ mod tests {
             //     }
             //     NodeType::Expression(_) => panic!("program.statements[0] is not Statement"),
 
-            match &program.statements[0] {
-                NodeType::Statement(stmt) => {
-                    let expr_stmt = stmt
-                        .as_any()
-                        .downcast_ref::<ExpressionStatement>()
-                        .expect("not expression statement");
-
-                    match &tt.left_value {
-                        TestValue::Int(left_int) => match &tt.right_value {
-                            TestValue::Int(right_int) => {
-                                test_infix_expression(
-                                    &expr_stmt.expression,
-                                    ExpectedValue::Integer(*left_int),
-                                    &tt.operator,
-                                    ExpectedValue::Integer(*right_int),
-                                );
-                            }
-                            // TestValue::Boolean(right_bool) => {
-                            //     test_infix_expression(
-                            //         &expr_stmt.expression,
-                            //         ExpectedValue::Integer(*left_int),
-                            //         &tt.operator,
-                            //         ExpectedValue::Boolean(*right_bool),
-                            //     );
-                            // }
-                            TestValue::Boolean(_) => panic!("left is i64,but right is bool"),
-                        },
-                        TestValue::Boolean(left_bool) => match &tt.right_value {
-                            // TestValue::Int(right_int) => {
-                            //     test_infix_expression(
-                            //         &expr_stmt.expression,
-                            //         ExpectedValue::Boolean(*left_bool),
-                            //         &tt.operator,
-                            //         ExpectedValue::Integer(*right_int),
-                            //     );
-                            // }
-                            TestValue::Boolean(right_bool) => {
-                                test_infix_expression(
-                                    &expr_stmt.expression,
-                                    ExpectedValue::Boolean(*left_bool),
-                                    &tt.operator,
-                                    ExpectedValue::Boolean(*right_bool),
-                                );
-                            }
-                            TestValue::Int(_) => panic!("left is bool,but right is i64"),
-                        },
+            let expression = expression_statement(&program.statements[0]);
+            match &tt.left_value {
+                TestValue::Int(left_int) => match &tt.right_value {
+                    TestValue::Int(right_int) => {
+                        test_infix_expression(
+                            expression,
+                            ExpectedValue::Integer(*left_int),
+                            &tt.operator,
+                            ExpectedValue::Integer(*right_int),
+                        );
                     }
-                }
-                NodeType::Expression(_) => panic!("program.statements[0] is not Statement"),
+                    TestValue::Boolean(_) => panic!("left is i64,but right is bool"),
+                },
+                TestValue::Boolean(left_bool) => match &tt.right_value {
+                    TestValue::Boolean(right_bool) => {
+                        test_infix_expression(
+                            expression,
+                            ExpectedValue::Boolean(*left_bool),
+                            &tt.operator,
+                            ExpectedValue::Boolean(*right_bool),
+                        );
+                    }
+                    TestValue::Int(_) => panic!("left is bool,but right is i64"),
+                },
             }
         }
     }
@@ -979,42 +847,31 @@ mod tests {
         }
     }
 
-    fn test_identifier(node: &NodeType, value: &str) -> bool {
-        if let NodeType::Expression(expr) = node {
-            let ident = match expr.as_any().downcast_ref::<Identifier>() {
-                Some(id) => id,
-                None => {
-                    panic!("expr is not Identifier. got={:?}", expr);
-                    return false;
-                }
-            };
-
-            if ident.value != value {
-                panic!("ident.value not {}. got={}", value, ident.value);
-                return false;
-            }
+    fn test_identifier(expr: &Expression, value: &str) -> bool {
+        let Expression::Identifier(ident) = expr else {
+            panic!("expr is not Identifier. got={:?}", expr);
+        };
 
-            if ident.token_literal() != value {
-                panic!(
-                    "ident.token_literal not {}. got={}",
-                    value,
-                    ident.token_literal()
-                );
-                return false;
-            }
+        if ident.value != value {
+            panic!("ident.value not {}. got={}", value, ident.value);
+        }
 
-            true
-        } else {
-            panic!("node is not Expression");
-            false
+        if ident.token_literal() != value {
+            panic!(
+                "ident.token_literal not {}. got={}",
+                value,
+                ident.token_literal()
+            );
         }
+
+        true
     }
     enum ExpectedValue<'a> {
         Integer(i64),
         String(&'a str),
         Boolean(bool),
     }
-    fn test_literal_expression(exp: &NodeType, expected: ExpectedValue) -> bool {
+    fn test_literal_expression(exp: &Expression, expected: ExpectedValue) -> bool {
         match expected {
             ExpectedValue::Integer(value) => test_integer_literal(exp, value),
             ExpectedValue::String(value) => test_identifier(exp, value),
@@ -1022,81 +879,62 @@ mod tests {
         }
     }
 
-    fn test_boolean_literal(exp: &NodeType, value: bool) -> bool {
-        if let NodeType::Expression(expr) = exp {
-            let bo = match expr.as_any().downcast_ref::<Boolean>() {
-                Some(bo) => bo,
-                None => {
-                    panic!("exp is not Boolean.got {:?}", expr);
-                    return false;
-                }
-            };
+    // 没有默认值的普通参数
+    fn test_parameter(param: &Parameter, value: &str) -> bool {
+        let Parameter::Plain(ident) = param else {
+            panic!("param is not a plain parameter. got={:?}", param);
+        };
+        test_identifier(&Expression::Identifier(ident.clone()), value)
+    }
 
-            if bo.value != value {
-                panic!("bo.value not {},got {}", value, bo.value);
-                return false;
-            }
+    fn test_boolean_literal(exp: &Expression, value: bool) -> bool {
+        let Expression::Boolean(bo) = exp else {
+            panic!("exp is not Boolean.got {:?}", exp);
+        };
 
-            let expected_literal = if value { "true" } else { "false" };
-            if bo.token_literal() != expected_literal {
-                panic!(
-                    "bo.token_literal not {}. got={}",
-                    expected_literal,
-                    bo.token_literal()
-                );
-                return false;
-            }
-            return true;
-        } else {
-            panic!("node is not Expression");
-            return false;
+        if bo.value != value {
+            panic!("bo.value not {},got {}", value, bo.value);
+        }
+
+        let expected_literal = if value { "true" } else { "false" };
+        if bo.token_literal() != expected_literal {
+            panic!(
+                "bo.token_literal not {}. got={}",
+                expected_literal,
+                bo.token_literal()
+            );
         }
+        true
     }
 
     fn test_infix_expression(
-        exp: &NodeType,
+        exp: &Expression,
         left: ExpectedValue,
         operator: &str,
         right: ExpectedValue,
     ) -> bool {
-        if let NodeType::Expression(expr) = exp {
-            // let op_exp = match expr.as_any().downcast_ref::<InfixExpression>() {
-            //     Some(op) => op,
-            //     None => {
-            //         eprintln!("exp is not InfixExpression. got={:?}", expr);
-            //         return false;
-            //     }
-            // };
-            if let Some(op) = expr.as_any().downcast_ref::<InfixExpression>() {
-                let op_exp = op;
-
-                if !test_literal_expression(&op_exp.left, left) {
-                    return false;
-                }
+        let Expression::Infix(op_exp) = exp else {
+            panic!("exp is not InfixExpression. got={:?}", exp);
+        };
 
-                // 检查操作符
-                if op_exp.operator != operator {
-                    panic!(
-                        "exp.operator is not '{}'. got='{}'",
-                        operator, op_exp.operator
-                    );
-                    return false;
-                }
+        if !test_literal_expression(&op_exp.left, left) {
+            return false;
+        }
 
-                // 测试右操作数
-                if !test_literal_expression(&op_exp.right, right) {
-                    return false;
-                }
+        // 检查操作符
+        if op_exp.operator != operator {
+            panic!(
+                "exp.operator is not '{}'. got='{}'",
+                operator, op_exp.operator
+            );
+        }
 
-                true
-            } else {
-                panic!("exp is not InfixExpression. got={:?}", expr);
-                false
-            }
-        } else {
-            panic!("exp is not Expression");
-            false
+        // 测试右操作数
+        if !test_literal_expression(&op_exp.right, right) {
+            return false;
         }
+
+        true
     }
 
     #[test]
@@ -1115,63 +953,33 @@ mod tests {
             );
         }
 
-        match &program.statements[0] {
-            NodeType::Statement(stmt) => {
-                if let Some(expr_stmt) = stmt.as_any().downcast_ref::<ExpressionStatement>() {
-                    let node = &*expr_stmt.expression;
-                    let if_expr = match node {
-                        NodeType::Expression(expr) => expr
-                            .as_any()
-                            .downcast_ref::<IfExpression>()
-                            .expect("not IfExpressino"),
-                        NodeType::Statement(_) => panic!("not ExpressionStatement"),
-                    };
-
-                    test_infix_expression(
-                        &if_expr.condition,
-                        ExpectedValue::String("x"),
-                        ">",
-                        ExpectedValue::String("y"),
-                    );
-                    let block_stmt = match &*if_expr.consequence {
-                        NodeType::Statement(stmt) => stmt
-                            .as_any()
-                            .downcast_ref::<BlockStatement>()
-                            .expect("not BlockStatement"),
-                        _ => panic!("if_expr.consequence is not a Statement"),
-                    };
-                    if block_stmt.statements.len() != 1 {
-                        panic!(
-                            "consequence is not 1 statements. got={}",
-                            block_stmt.statements.len()
-                        );
-                    }
-
-                    match &block_stmt.statements[0] {
-                        NodeType::Statement(stmt) => {
-                            if let Some(consequence) =
-                                stmt.as_any().downcast_ref::<ExpressionStatement>()
-                            {
-                                test_identifier(&consequence.expression, "x");
-                            } else {
-                                panic!("Consequence.Statements[0] is not ExpressionStatement");
-                            }
-                        }
-                        _ => panic!("Not a Statement type"),
-                    }
+        let Expression::If(if_expr) = expression_statement(&program.statements[0]) else {
+            panic!("not IfExpressino");
+        };
 
-                    // 这个测试用例没有 else 语句
-                    assert!(
-                        if_expr.alternative.is_none(),
-                        "if_expr.alternative was not None. got={:?}",
-                        if_expr.alternative
-                    );
-                } else {
-                    panic!("not ExpressionStatement");
-                }
-            }
-            NodeType::Expression(_) => panic!("program.statements[0] is not Statement"),
+        test_infix_expression(
+            &if_expr.condition,
+            ExpectedValue::String("x"),
+            ">",
+            ExpectedValue::String("y"),
+        );
+        if if_expr.consequence.statements.len() != 1 {
+            panic!(
+                "consequence is not 1 statements. got={}",
+                if_expr.consequence.statements.len()
+            );
         }
+        test_identifier(
+            expression_statement(&if_expr.consequence.statements[0]),
+            "x",
+        );
+
+        // 这个测试用例没有 else 语句
+        assert!(
+            if_expr.alternative.is_none(),
+            "if_expr.alternative was not None. got={:?}",
+            if_expr.alternative
+        );
     }
 
     #[test]
@@ -1188,91 +996,38 @@ mod tests {
             );
         }
 
-        match &program.statements[0] {
-            NodeType::Statement(stmt) => {
-                if let Some(expr_stmt) = stmt.as_any().downcast_ref::<ExpressionStatement>() {
-                    let node = &*expr_stmt.expression;
-                    let if_expr = match node {
-                        NodeType::Expression(expr) => expr
-                            .as_any()
-                            .downcast_ref::<IfExpression>()
-                            .expect("not IfExpressino"),
-                        NodeType::Statement(_) => panic!("not ExpressionStatement"),
-                    };
-
-                    test_infix_expression(
-                        &if_expr.condition,
-                        ExpectedValue::String("x"),
-                        ">",
-                        ExpectedValue::String("y"),
-                    );
-                    let block_stmt = match &*if_expr.consequence {
-                        NodeType::Statement(stmt) => stmt
-                            .as_any()
-                            .downcast_ref::<BlockStatement>()
-                            .expect("not BlockStatement"),
-                        _ => panic!("if_expr.consequence is not a Statement"),
-                    };
-                    if block_stmt.statements.len() != 1 {
-                        panic!(
-                            "consequence is not 1 statements. got={}",
-                            block_stmt.statements.len()
-                        );
-                    }
-
-                    match &block_stmt.statements[0] {
-                        NodeType::Statement(stmt) => {
-                            if let Some(consequence) =
-                                stmt.as_any().downcast_ref::<ExpressionStatement>()
-                            {
-                                test_identifier(&consequence.expression, "x");
-                            } else {
-                                panic!("Consequence.Statements[0] is not ExpressionStatement");
-                            }
-                        }
-                        _ => panic!("Not a Statement type"),
-                    }
-
-                    if let Some(alt) = &if_expr.alternative {
-                        // let a = alt;
-                        let block_stmt = match &**alt {
-                            NodeType::Statement(stmt) => stmt
-                                .as_any()
-                                .downcast_ref::<BlockStatement>()
-                                .expect("not BlockStatement"),
-                            _ => panic!("if_expr.alternative is not a Statement"),
-                        };
+        let Expression::If(if_expr) = expression_statement(&program.statements[0]) else {
+            panic!("not IfExpressino");
+        };
 
-                        // 检查 else 块中的语句
-                        if block_stmt.statements.len() != 1 {
-                            panic!(
-                                "alternative is not 1 statements. got={}",
-                                block_stmt.statements.len()
-                            );
-                        }
+        test_infix_expression(
+            &if_expr.condition,
+            ExpectedValue::String("x"),
+            ">",
+            ExpectedValue::String("y"),
+        );
+        if if_expr.consequence.statements.len() != 1 {
+            panic!(
+                "consequence is not 1 statements. got={}",
+                if_expr.consequence.statements.len()
+            );
+        }
+        test_identifier(
+            expression_statement(&if_expr.consequence.statements[0]),
+            "x",
+        );
 
-                        // 检查 else 块中的表达式
-                        match &block_stmt.statements[0] {
-                            NodeType::Statement(stmt) => {
-                                if let Some(alternative) =
-                                    stmt.as_any().downcast_ref::<ExpressionStatement>()
-                                {
-                                    test_identifier(&alternative.expression, "y");
-                                } else {
-                                    panic!("Alternative.Statements[0] is not ExpressionStatement");
-                                }
-                            }
-                            _ => panic!("Not a Statement type"),
-                        }
-                    } else {
-                        panic!("if_expr.alternative was None");
-                    }
-                } else {
-                    panic!("not ExpressionStatement");
-                }
-            }
-            NodeType::Expression(_) => panic!("program.statements[0] is not Statement"),
+        let Some(alt) = &if_expr.alternative else {
+            panic!("if_expr.alternative was None");
+        };
+        // 检查 else 块中的语句
+        if alt.statements.len() != 1 {
+            panic!(
+                "alternative is not 1 statements. got={}",
+                alt.statements.len()
+            );
         }
+        test_identifier(expression_statement(&alt.statements[0]), "y");
     }
 
     #[test]
@@ -1290,66 +1045,33 @@ mod tests {
                 program.statements.len()
             )
         }
-        match &program.statements[0] {
-            NodeType::Statement(stmt) => {
-                let expr_stmt = stmt
-                    .as_any()
-                    .downcast_ref::<ExpressionStatement>()
-                    .expect("not ExpressionStatement!");
-
-                let function = match &*expr_stmt.expression {
-                    NodeType::Expression(expr) => expr
-                        .as_any()
-                        .downcast_ref::<FunctionLiteral>()
-                        .expect("not function literal"),
-                    _ => panic!("expr_stmt.expression is not an Expression"),
-                };
-
-                if function.parameters.len() != 2 {
-                    panic!(
-                        "function literal params wrong.want 2,got {}",
-                        function.parameters.len()
-                    );
-                }
-
-                test_literal_expression(&function.parameters[0], ExpectedValue::String("x"));
-                test_literal_expression(&function.parameters[1], ExpectedValue::String("y"));
+        let Expression::Function(function) = expression_statement(&program.statements[0]) else {
+            panic!("not function literal");
+        };
 
-                let body = match &*function.body {
-                    NodeType::Statement(stmt) => stmt
-                        .as_any()
-                        .downcast_ref::<BlockStatement>()
-                        .expect("function body stmt is not BlockStatement"),
-                    _ => {
-                        panic!("function.body is not a Statement type")
-                    }
-                };
-                if body.statements.len() != 1 {
-                    panic!(
-                        "function.Body.Statements has not 1 statements. got={}",
-                        body.statements.len()
-                    );
-                }
+        if function.parameters.len() != 2 {
+            panic!(
+                "function literal params wrong.want 2,got {}",
+                function.parameters.len()
+            );
+        }
 
-                match &body.statements[0] {
-                    NodeType::Statement(body_stmt) => {
-                        let body_expr_stmt = body_stmt
-                            .as_any()
-                            .downcast_ref::<ExpressionStatement>()
-                            .expect("function body stmt is not ExpressionStatement");
-                        test_infix_expression(
-                            &body_expr_stmt.expression,
-                            ExpectedValue::String("x"),
-                            "+",
-                            ExpectedValue::String("y"),
-                        );
-                    }
-                    _ => panic!("function.Body.Statements[0] is not Statement"),
-                }
-            }
+        test_parameter(&function.parameters[0], "x");
+        test_parameter(&function.parameters[1], "y");
 
-            NodeType::Expression(_) => panic!("program.statements[0] is not Statement"),
+        if function.body.statements.len() != 1 {
+            panic!(
+                "function.Body.Statements has not 1 statements. got={}",
+                function.body.statements.len()
+            );
         }
+
+        test_infix_expression(
+            expression_statement(&function.body.statements[0]),
+            ExpectedValue::String("x"),
+            "+",
+            ExpectedValue::String("y"),
+        );
     }
 
     #[test]
@@ -1386,36 +1108,20 @@ mod tests {
                 );
             }
 
-            match &program.statements[0] {
-                NodeType::Statement(stmt) => {
-                    let expr_stmt = stmt
-                        .as_any()
-                        .downcast_ref::<ExpressionStatement>()
-                        .expect("not ExpressionStatement!");
-
-                    let function = match &*expr_stmt.expression {
-                        NodeType::Expression(expr) => expr
-                            .as_any()
-                            .downcast_ref::<FunctionLiteral>()
-                            .expect("not function literal"),
-                        _ => panic!("not an Expression type"),
-                    };
-                    assert_eq!(
-                        function.parameters.len(),
-                        tt.expected_params.len(),
-                        "length parameters wrong. want {}, got={}",
-                        tt.expected_params.len(),
-                        function.parameters.len()
-                    );
-
-                    for (index, ident) in tt.expected_params.iter().enumerate() {
-                        test_literal_expression(
-                            &function.parameters[index],
-                            ExpectedValue::String(ident),
-                        );
-                    }
-                }
-                _ => panic!("program.statements[0] is not Statement"),
+            let Expression::Function(function) = expression_statement(&program.statements[0])
+            else {
+                panic!("not function literal");
+            };
+            assert_eq!(
+                function.parameters.len(),
+                tt.expected_params.len(),
+                "length parameters wrong. want {}, got={}",
+                tt.expected_params.len(),
+                function.parameters.len()
+            );
+
+            for (index, ident) in tt.expected_params.iter().enumerate() {
+                test_parameter(&function.parameters[index], ident);
             }
         }
     }
@@ -1436,45 +1142,1443 @@ mod tests {
             );
         }
 
-        match &program.statements[0] {
-            NodeType::Statement(stmt) => {
-                let expr_stmt = stmt
-                    .as_any()
-                    .downcast_ref::<ExpressionStatement>()
-                    .expect("stmt is not expressionStatement");
-
-                let exp = match &*expr_stmt.expression {
-                    NodeType::Expression(expr) => expr
-                        .as_any()
-                        .downcast_ref::<CallExpression>()
-                        .expect(&format!(
-                            "not CallExpression. got {:?}",
-                            expr_stmt.expression
-                        )),
-                    _ => panic!("expr_stmt.expression is not an Expression"),
-                };
+        let expression = expression_statement(&program.statements[0]);
+        let Expression::Call(exp) = expression else {
+            panic!("not CallExpression. got {:?}", expression);
+        };
 
-                test_identifier(&exp.function, "add");
+        test_identifier(&exp.function, "add");
 
-                if exp.arguments.len() != 3 {
-                    panic!("wrong length of arguments. got {}", exp.arguments.len());
-                }
+        if exp.arguments.len() != 3 {
+            panic!("wrong length of arguments. got {}", exp.arguments.len());
+        }
 
-                test_literal_expression(&exp.arguments[0], ExpectedValue::Integer(1));
-                test_infix_expression(
-                    &exp.arguments[1],
-                    ExpectedValue::Integer(2),
-                    "*",
-                    ExpectedValue::Integer(3),
-                );
-                test_infix_expression(
-                    &exp.arguments[2],
-                    ExpectedValue::Integer(4),
-                    "+",
-                    ExpectedValue::Integer(5),
-                );
-            }
-            _ => panic!("is not Statement"),
+        test_literal_expression(&exp.arguments[0], ExpectedValue::Integer(1));
+        test_infix_expression(
+            &exp.arguments[1],
+            ExpectedValue::Integer(2),
+            "*",
+            ExpectedValue::Integer(3),
+        );
+        test_infix_expression(
+            &exp.arguments[2],
+            ExpectedValue::Integer(4),
+            "+",
+            ExpectedValue::Integer(5),
+        );
+    }
+
+    #[test]
+    fn test_collection_literal_parsing() {
+        let tests = vec![
+            (r#""hello world";"#, r#""hello world""#),
+            ("[1, 2 * 2, 3 + 3]", "[1, (2 * 2), (3 + 3)]"),
+            ("[]", "[]"),
+            (r#"{"one": 1, "two": 2}"#, r#"{"one": 1, "two": 2}"#),
+            ("{}", "{}"),
+        ];
+
+        for (input, expected) in tests {
+            let l = Lexer::new(input.to_string());
+            let mut p = Parser::new(l);
+            let program = p.parse_program();
+            assert!(p.errors().is_empty(), "parser errors: {:?}", p.errors());
+            assert_eq!(program.to_string(), expected);
+        }
+    }
+
+    #[test]
+    fn test_destructuring_let_statements() {
+        let tests = vec![
+            ("let [a, b] = xs;", "let [a, b] = xs;"),
+            ("let [first, ...rest] = xs;", "let [first, ...rest] = xs;"),
+            ("let [...all] = xs;", "let [...all] = xs;"),
+            ("let [a, [b, c]] = xs;", "let [a, [b, c]] = xs;"),
+            (
+                "let {name, age: years} = person;",
+                "let {name, age: years} = person;",
+            ),
+            (
+                r#"let {"full name": n, tags: [t, ...ts]} = p;"#,
+                r#"let {"full name": n, tags: [t, ...ts]} = p;"#,
+            ),
+        ];
+
+        for (input, expected) in tests {
+            let l = Lexer::new(input.to_string());
+            let mut p = Parser::new(l);
+            let program = p.parse_program();
+            assert!(p.errors().is_empty(), "parser errors: {:?}", p.errors());
+            assert_eq!(program.statements.len(), 1);
+
+            let Statement::DestructuringLet(let_stmt) = &program.statements[0] else {
+                panic!("not DestructuringLetStatement");
+            };
+            assert_eq!(let_stmt.to_string(), expected);
+        }
+    }
+
+    #[test]
+    fn test_destructuring_let_parse_errors() {
+        let tests = vec![
+            (
+                "let [a, ...rest, b] = xs;",
+                "expected next token to be RBRACKET",
+            ),
+            ("let [+] = xs;", "expected pattern, got PLUS instead"),
+            (
+                "let {1: a} = xs;",
+                "expected hash pattern key, got INT instead",
+            ),
+            (r#"let {"a"} = xs;"#, "expected next token to be COLON"),
+        ];
+
+        for (input, expected) in tests {
+            let l = Lexer::new(input.to_string());
+            let mut p = Parser::new(l);
+            p.parse_program();
+            let errors = p.errors();
+            assert!(
+                errors.iter().any(|e| e.starts_with(expected)),
+                "expected error starting with {:?}, got {:?}",
+                expected,
+                errors
+            );
         }
     }
+
+    #[test]
+    fn test_match_expression_parsing() {
+        let tests = vec![
+            (
+                "match (x) { 1 => a, _ => b }",
+                "match (x) { 1 => a, _ => b }",
+            ),
+            (
+                r#"match (x) { -1 => "neg", "s" => true, false => 0, }"#,
+                r#"match (x) { -1 => "neg", "s" => true, false => 0 }"#,
+            ),
+            (
+                "match (x) { [a, ...rest] if a > 1 => a, {k: v} => v }",
+                "match (x) { [a, ...rest] if (a > 1) => a, {k: v} => v }",
+            ),
+            (
+                "match (x + 1) { n => { n * 2 } _ => { 0 } }",
+                "match ((x + 1)) { n => (n * 2), _ => 0 }",
+            ),
+        ];
+
+        for (input, expected) in tests {
+            let l = Lexer::new(input.to_string());
+            let mut p = Parser::new(l);
+            let program = p.parse_program();
+            assert!(p.errors().is_empty(), "parser errors: {:?}", p.errors());
+            assert_eq!(program.statements.len(), 1);
+            assert_eq!(program.to_string(), expected);
+        }
+    }
+
+    #[test]
+    fn test_match_expression_parse_errors() {
+        let tests = vec![
+            ("match x { _ => 1 }", "expected next token to be LPAREN"),
+            ("match (x) { 1 2 }", "expected next token to be FATARROW"),
+            (
+                "match (x) { 1 => 2 3 => 4 }",
+                "expected next token to be COMMA",
+            ),
+        ];
+
+        for (input, expected) in tests {
+            let l = Lexer::new(input.to_string());
+            let mut p = Parser::new(l);
+            p.parse_program();
+            let errors = p.errors();
+            assert!(
+                errors.iter().any(|e| e.starts_with(expected)),
+                "expected error starting with {:?}, got {:?}",
+                expected,
+                errors
+            );
+        }
+    }
+
+    #[test]
+    fn test_function_declaration_parsing() {
+        let input = "fn add(x, y) { x + y; } add(1, 2);";
+
+        let l = Lexer::new(input.to_string());
+        let mut p = Parser::new(l);
+        let program = p.parse_program();
+        assert!(p.errors().is_empty(), "parser errors: {:?}", p.errors());
+        assert_eq!(program.statements.len(), 2);
+
+        let Statement::FunctionDeclaration(decl) = &program.statements[0] else {
+            panic!("not FunctionDeclaration");
+        };
+        assert_eq!(decl.name.value, "add");
+        assert_eq!(decl.function.parameters.len(), 2);
+        test_parameter(&decl.function.parameters[0], "x");
+        test_parameter(&decl.function.parameters[1], "y");
+        assert_eq!(decl.to_string(), "fn add(x, y) (x + y)");
+
+        // 匿名函数字面量仍然是表达式语句
+        let l = Lexer::new("fn(x) { x }(1);".to_string());
+        let mut p = Parser::new(l);
+        let program = p.parse_program();
+        assert!(p.errors().is_empty(), "parser errors: {:?}", p.errors());
+        assert_eq!(program.to_string(), "fn(x) x(1)");
+    }
+
+    #[test]
+    fn test_default_rest_and_spread_parsing() {
+        let tests = vec![
+            ("fn(a, b = 10) { a }", "fn(a, b = 10) a"),
+            (
+                "fn(a, b = 1 + 2, ...rest) { a }",
+                "fn(a, b = (1 + 2), ...rest) a",
+            ),
+            ("fn(...xs) { xs }", "fn(...xs) xs"),
+            ("f(...args)", "f(...args)"),
+            ("f(1, ...g(x), 2)", "f(1, ...g(x), 2)"),
+        ];
+
+        for (input, expected) in tests {
+            let l = Lexer::new(input.to_string());
+            let mut p = Parser::new(l);
+            let program = p.parse_program();
+            assert!(p.errors().is_empty(), "parser errors: {:?}", p.errors());
+            assert_eq!(program.to_string(), expected);
+        }
+    }
+
+    #[test]
+    fn test_function_parameter_kinds() {
+        use crate::json::schema::source_to_json;
+
+        let input = "fn(a, b = a + 1, ...rest) { rest }";
+        let mut p = Parser::new(Lexer::new(input.to_string()));
+        let program = p.parse_program();
+        assert!(p.errors().is_empty(), "parser errors: {:?}", p.errors());
+        let Statement::Expression(stmt) = &program.statements[0] else {
+            panic!("not an expression statement");
+        };
+        let Expression::Function(function) = stmt.expression.as_ref() else {
+            panic!("not a function literal");
+        };
+
+        test_parameter(&function.parameters[0], "a");
+        let Parameter::Default(default) = &function.parameters[1] else {
+            panic!("not a default parameter: {:?}", function.parameters[1]);
+        };
+        assert_eq!(default.name.value, "b");
+        assert_eq!(default.default.to_string(), "(a + 1)");
+        let Parameter::Rest(rest) = &function.parameters[2] else {
+            panic!("not a rest parameter: {:?}", function.parameters[2]);
+        };
+        assert_eq!(rest.name.value, "rest");
+        assert_eq!(function.to_string(), "fn(a, b = (a + 1), ...rest) rest");
+
+        // JSON中每种参数有自己的节点种类
+        let json = source_to_json(input).unwrap().to_string();
+        for kind in [
+            "\"Identifier\"",
+            "\"DefaultParameter\"",
+            "\"RestParameter\"",
+        ] {
+            assert!(json.contains(kind), "{} not in {}", kind, json);
+        }
+        assert!(!json.contains("SpreadExpression"), "{}", json);
+    }
+
+    #[test]
+    fn test_function_parameter_errors() {
+        let tests = vec![
+            (
+                "fn(...rest, a) { a }",
+                "rest parameter must be the last parameter",
+            ),
+            (
+                "fn(a = 1, b) { a }",
+                "required parameter b cannot follow a parameter with a default value",
+            ),
+            ("fn(1) { 1 }", "expected parameter name, got INT instead"),
+            ("fn(...1) { 1 }", "expected next token to be IDENT"),
+        ];
+
+        for (input, expected) in tests {
+            let l = Lexer::new(input.to_string());
+            let mut p = Parser::new(l);
+            p.parse_program();
+            let errors = p.errors();
+            assert!(
+                errors.iter().any(|e| e.starts_with(expected)),
+                "expected error starting with {:?}, got {:?}",
+                expected,
+                errors
+            );
+        }
+    }
+
+    #[test]
+    fn test_misplaced_spread_errors() {
+        // ... 只能出现在调用参数和参数列表中，其他位置是带行列号的解析错误
+        let tests = vec![
+            ("let x = ...[1];", "1:9"),
+            ("let a = [1];\n[...a]", "2:2"),
+            ("{\"a\": ...[1]}", "1:7"),
+            ("f(1, [...a])", "1:7"),
+            ("-...a", "1:2"),
+        ];
+
+        for (input, position) in tests {
+            let l = Lexer::new(input.to_string());
+            let mut p = Parser::new(l);
+            p.parse_program();
+            let expected = format!(
+                "{}: spread is only allowed in call arguments and parameter lists",
+                position
+            );
+            assert!(
+                p.errors().contains(&expected),
+                "input {:?}: expected {:?}, got {:?}",
+                input,
+                expected,
+                p.errors()
+            );
+        }
+
+        // 调用参数中的 ... 仍然可以解析
+        let mut p = Parser::new(Lexer::new("f(...a, ...[1, 2], 3)".to_string()));
+        p.parse_program();
+        assert!(p.errors().is_empty(), "parser errors: {:?}", p.errors());
+    }
+
+    #[test]
+    fn test_max_nesting_depth() {
+        // 深度嵌套的输入应当得到解析错误而不是栈溢出
+        let tests = vec![
+            ("(".repeat(100_000), 256),
+            ("-".repeat(100_000) + "1", 256),
+            ("[".repeat(100_000), 256),
+            ("fn f() { ".repeat(100_000), 256),
+            (format!("{}1{}", "(".repeat(20), ")".repeat(20)), 10),
+        ];
+
+        for (input, max_depth) in tests {
+            let l = Lexer::new(input);
+            let mut p = Parser::new(l);
+            if max_depth != 256 {
+                p.set_max_depth(max_depth);
+            }
+            p.parse_program();
+            let expected = format!("maximum nesting depth of {} exceeded", max_depth);
+            assert_eq!(p.errors(), vec![expected]);
+        }
+
+        // 上限以内的嵌套正常解析
+        let input = format!("{}1{}", "(".repeat(200), ")".repeat(200));
+        let mut p = Parser::new(Lexer::new(input));
+        let program = p.parse_program();
+        assert!(p.errors().is_empty(), "parser errors: {:?}", p.errors());
+        assert_eq!(program.to_string(), "1");
+    }
+
+    #[test]
+    fn test_visitor() {
+        use crate::ast::Identifier;
+        use crate::ast::visitor::{Visitor, walk_expression};
+
+        // 统计引用的标识符和出现过的函数字面量
+        #[derive(Default)]
+        struct Counter {
+            identifiers: Vec<String>,
+            functions: usize,
+        }
+
+        impl Visitor for Counter {
+            fn visit_expression(&mut self, expr: &Expression) {
+                if let Expression::Function(_) = expr {
+                    self.functions += 1;
+                }
+                walk_expression(self, expr);
+            }
+            fn visit_identifier(&mut self, ident: &Identifier) {
+                self.identifiers.push(ident.value.clone());
+            }
+        }
+
+        let input = "let f = fn(x, y = 1) { if (x > y) { [x] } else { {\"k\": g(y)} } }; \
+                     match (f) { [a, ...rest] if a => rest, _ => fn() { 0 } }";
+        let mut p = Parser::new(Lexer::new(input.to_string()));
+        let program = p.parse_program();
+        check_parser_errors(&p);
+
+        let mut counter = Counter::default();
+        counter.visit_program(&program);
+        assert_eq!(
+            counter.identifiers,
+            vec![
+                "f", "x", "y", "x", "y", "x", "g", "y", "f", "a", "rest", "a", "rest"
+            ]
+        );
+        assert_eq!(counter.functions, 2);
+    }
+
+    #[test]
+    fn test_fold() {
+        use crate::ast::IntegerLiteral;
+        use crate::ast::fold::{Fold, walk_expression};
+        use crate::token::token::{Token, TokenType};
+
+        // 自底向上折叠整数常量的加法和乘法
+        struct ConstantFolder;
+
+        impl Fold for ConstantFolder {
+            fn fold_expression(&mut self, expr: Expression) -> Expression {
+                let expr = walk_expression(self, expr);
+                let Expression::Infix(infix) = &expr else {
+                    return expr;
+                };
+                let value = match (
+                    infix.left.as_ref(),
+                    infix.operator.as_str(),
+                    infix.right.as_ref(),
+                ) {
+                    (Expression::Integer(l), "+", Expression::Integer(r)) => l.value + r.value,
+                    (Expression::Integer(l), "*", Expression::Integer(r)) => l.value * r.value,
+                    _ => return expr,
+                };
+                Expression::Integer(IntegerLiteral {
+                    token: Token {
+                        token_type: TokenType::INT,
+                        literal: value.to_string(),
+                    },
+                    value,
+                })
+            }
+        }
+
+        let tests = vec![
+            ("1 + 2 * 3", "7"),
+            ("x + 2 * 3", "(x + 6)"),
+            (
+                "let f = fn(a = 2 * 2) { a + (1 + 1) };",
+                "let f = fn(a = 4) (a + 2);",
+            ),
+            ("[1 + 1, f(2 * 5)]", "[2, f(10)]"),
+        ];
+
+        for (input, expected) in tests {
+            let mut p = Parser::new(Lexer::new(input.to_string()));
+            let program = p.parse_program();
+            check_parser_errors(&p);
+            let folded = ConstantFolder.fold_program(program.clone());
+            assert_eq!(folded.to_string(), expected);
+            // 没有可折叠的内容时结果与原树相等
+            assert_eq!(ConstantFolder.fold_program(folded.clone()), folded);
+        }
+    }
+
+    #[test]
+    fn test_print_program() {
+        use crate::ast::printer::print_program;
+
+        let tests = vec![
+            ("((1 + 2)) * (3)", "(1 + 2) * 3;"),
+            ("a - (b - c); (a - b) - c", "a - (b - c);\na - b - c;"),
+            (
+                "-(-a); !(a == b); (-f)(x); -f(x)",
+                "--a;\n!(a == b);\n(-f)(x);\n-f(x);",
+            ),
+            (
+                "let add = fn(a, b = 1 * 2, ...rest) { let c = a + b; c };",
+                "let add = fn(a, b = 1 * 2, ...rest) {\n    let c = a + b;\n    c\n};",
+            ),
+            (
+                "fn max(a, b) { if (a > b) { return a; } else { b } } max(1, 2);",
+                "fn max(a, b) {\n    if (a > b) {\n        return a;\n    } else {\n        b\n    }\n}\nmax(1, 2);",
+            ),
+            (
+                r#"let [x, ...xs] = [1, 2]; let {name, "full name": n} = {"name": 1, "full name": 2};"#,
+                "let [x, ...xs] = [1, 2];\nlet {name, \"full name\": n} = {\"name\": 1, \"full name\": 2};",
+            ),
+            (
+                r#"match (x) { 0 => "zero", [a, ...r] if a > 0 => { a } _ => ({"k": 1}) }"#,
+                "match (x) {\n    0 => \"zero\",\n    [a, ...r] if a > 0 => {\n        a\n    }\n    _ => ({\"k\": 1}),\n}",
+            ),
+            // if后面紧跟(开头的语句时保留分号，否则会被解析成调用
+            (
+                "if (a) { 1 }; (b + c) * d; if (a) { 1 } f()",
+                "if (a) {\n    1\n};\n(b + c) * d;\nif (a) {\n    1\n}\nf();",
+            ),
+            (
+                "f(...(a + b), ...-c); fn() {}",
+                "f(...(a + b), ...-c);\nfn() {};",
+            ),
+        ];
+
+        for (input, expected) in tests {
+            let mut p = Parser::new(Lexer::new(input.to_string()));
+            let program = p.parse_program();
+            assert!(p.errors().is_empty(), "parser errors: {:?}", p.errors());
+            assert_eq!(print_program(&program), expected, "input: {}", input);
+        }
+    }
+
+    // 生成随机的、所有复合子表达式都带括号的源码
+    struct Generator(u64);
+
+    impl Generator {
+        fn next(&mut self, n: u64) -> u64 {
+            // xorshift64
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0 % n
+        }
+
+        fn atom(&mut self) -> String {
+            match self.next(4) {
+                0 => ["a", "b", "foo"][self.next(3) as usize].to_string(),
+                1 => self.next(100).to_string(),
+                2 => ["true", "false"][self.next(2) as usize].to_string(),
+                _ => "\"s\"".to_string(),
+            }
+        }
+
+        fn expression(&mut self, depth: u32) -> String {
+            if depth == 0 {
+                return self.atom();
+            }
+            let sub = |g: &mut Generator| format!("({})", g.expression(depth - 1));
+            match self.next(12) {
+                0 | 1 => self.atom(),
+                2 => format!("{}{}", ["-", "!"][self.next(2) as usize], sub(self)),
+                3..=5 => {
+                    let op = ["+", "-", "*", "/", "<", ">", "==", "!="][self.next(8) as usize];
+                    format!("{} {} {}", sub(self), op, sub(self))
+                }
+                6 => format!("{}({}, ...{})", sub(self), sub(self), sub(self)),
+                7 => format!("[{}, {}]", sub(self), sub(self)),
+                8 => format!("{{{}: {}}}", sub(self), sub(self)),
+                9 => format!(
+                    "if ({}) {{ let x = {}; {} }} else {{ {} }}",
+                    sub(self),
+                    sub(self),
+                    sub(self),
+                    sub(self)
+                ),
+                10 => format!("fn(x, y = {}) {{ return {}; }}", sub(self), sub(self)),
+                _ => format!(
+                    "match ({}) {{ [x, ...r] if {} => {}, _ => {{ {} }} }}",
+                    sub(self),
+                    sub(self),
+                    sub(self),
+                    sub(self)
+                ),
+            }
+        }
+
+        fn program(&mut self) -> String {
+            (0..1 + self.next(3))
+                .map(|_| match self.next(4) {
+                    0 => format!("let v = {};", self.expression(3)),
+                    _ => format!("{};", self.expression(3)),
+                })
+                .collect::<Vec<_>>()
+                .join(" ")
+        }
+    }
+
+    #[test]
+    fn test_print_round_trip() {
+        use crate::ast::Program;
+        use crate::ast::fold::{Fold, walk_statement};
+        use crate::ast::printer::print_program;
+        use crate::token::token::Token;
+
+        // 表达式语句的词法单元是语句的第一个词法单元，去掉多余的括号后会变化，比较前统一清空
+        struct ClearStatementTokens;
+
+        impl Fold for ClearStatementTokens {
+            fn fold_statement(&mut self, stmt: Statement) -> Statement {
+                match walk_statement(self, stmt) {
+                    Statement::Expression(mut expr_stmt) => {
+                        expr_stmt.token = Token {
+                            token_type: TokenType::ILLEGAL,
+                            literal: String::new(),
+                        };
+                        Statement::Expression(expr_stmt)
+                    }
+                    stmt => stmt,
+                }
+            }
+        }
+
+        fn parse(input: &str) -> Program {
+            let mut p = Parser::new(Lexer::new(input.to_string()));
+            let program = p.parse_program();
+            assert!(
+                p.errors().is_empty(),
+                "parser errors {:?} for:\n{}",
+                p.errors(),
+                input
+            );
+            ClearStatementTokens.fold_program(program)
+        }
+
+        let mut inputs: Vec<String> = vec![
+            "let add = fn(a, b) { a + b }; add(1, 2 * 3) - -4 / (5 - 6);".to_string(),
+            "fn fib(n) { if (n < 2) { return n; } fib(n - 1) + fib(n - 2) } fib(10);".to_string(),
+            "let [a, [b, c], ...rest] = xs; let {name, age: years} = person;".to_string(),
+            r#"match (x) { 1 => "one", [h, ...t] if h > 0 => { h } {k: v} => v, _ => ({}) }"#
+                .to_string(),
+            "if (a) { 1 } (b)(c); if (a) { 1 } -1; (fn(x) { x })(1); !(!a) == -(-b);".to_string(),
+        ];
+        let mut generator = Generator(0x2545_f491_4f6c_dd1d);
+        inputs.extend((0..500).map(|_| generator.program()));
+
+        for input in inputs {
+            let original = parse(&input);
+            let printed = print_program(&original);
+            let reparsed = parse(&printed);
+            assert_eq!(
+                original, reparsed,
+                "input:\n{}\nprinted:\n{}",
+                input, printed
+            );
+            // 打印结果已经是规范形式
+            assert_eq!(print_program(&reparsed), printed);
+        }
+    }
+
+    #[test]
+    fn test_lexer_comments() {
+        let input = "let a = 10 / 2; // 注释\n\n// 单独一行\n\"字符串\"";
+
+        // 默认跳过注释
+        let mut l = Lexer::new(input.to_string());
+        let mut types = Vec::new();
+        loop {
+            let tok = l.next_token();
+            if tok.token_type == TokenType::EOF {
+                break;
+            }
+            types.push(tok.token_type);
+        }
+        assert!(!types.contains(&TokenType::COMMENT));
+        assert_eq!(types[4], TokenType::SLASH);
+
+        let mut l = Lexer::new(input.to_string());
+        l.set_keep_comments(true);
+        let tests = vec![
+            (TokenType::LET, "let", 0),
+            (TokenType::IDENT, "a", 0),
+            (TokenType::ASSIGN, "=", 0),
+            (TokenType::INT, "10", 0),
+            (TokenType::SLASH, "/", 0),
+            (TokenType::INT, "2", 0),
+            (TokenType::SEMICOLON, ";", 0),
+            (TokenType::COMMENT, "// 注释", 0),
+            (TokenType::COMMENT, "// 单独一行", 2),
+            (TokenType::STRING, "字符串", 1),
+            (TokenType::EOF, "", 0),
+        ];
+        for (expected_type, expected_literal, newlines) in tests {
+            let tok = l.next_token();
+            assert_eq!(tok.token_type, expected_type);
+            assert_eq!(tok.literal, expected_literal);
+            assert_eq!(l.newlines_before(), newlines, "before {:?}", tok.literal);
+        }
+    }
+
+    #[test]
+    fn test_format_source() {
+        use crate::formatter::formatter::format_source;
+
+        let tests = vec![
+            (
+                "// 头部\n\nlet a = 1;   // 尾部\n\n\n// 说明\nlet b = a+1\n// 结尾\n",
+                "// 头部\n\nlet a = 1; // 尾部\n\n// 说明\nlet b = a + 1;\n// 结尾\n",
+            ),
+            (
+                "fn f(x) {\n  // 块首\n  let y = x;\n\n  y // 值\n  // 块尾\n}\nif (x) { // 空块\n}",
+                "fn f(x) {\n    // 块首\n    let y = x;\n\n    y // 值\n    // 块尾\n}\nif (x) {\n    // 空块\n}\n",
+            ),
+            ("// 只有注释", "// 只有注释\n"),
+            ("", ""),
+            // 第一行的 #! 原样保留
+            (
+                "#!/usr/bin/env  monkey\nlet a=1\n\n// 注释\na",
+                "#!/usr/bin/env  monkey\nlet a = 1;\n\n// 注释\na;\n",
+            ),
+            ("#!/usr/bin/env monkey", "#!/usr/bin/env monkey\n"),
+            // 超过行宽时每个元素一行
+            (
+                "let f = fn(alpha, beta) { add(alpha, beta, [alpha, beta, alpha, beta], {\"k\": alpha}) };",
+                "let f = fn(alpha, beta) {\n    add(\n        alpha,\n        beta,\n        [alpha, beta, alpha, beta],\n        {\"k\": alpha}\n    )\n};\n",
+            ),
+        ];
+        for (input, expected) in tests {
+            assert_eq!(
+                format_source(input, 40).unwrap(),
+                expected,
+                "input: {}",
+                input
+            );
+        }
+
+        let errors = format_source("let = 1;", 80).unwrap_err();
+        assert!(!errors.is_empty());
+        // 去掉 #! 后错误的行号不变
+        let errors = format_source("#!/usr/bin/env monkey\nlet = 1;", 80).unwrap_err();
+        assert_eq!(errors, format_source("\nlet = 1;", 80).unwrap_err());
+    }
+
+    #[test]
+    fn test_format_idempotent() {
+        use crate::formatter::formatter::format_source;
+
+        let inputs = [
+            "let add = fn(a,b){ a+b }; // 加法\n// 下一个\nadd(1,2)",
+            "match (x) { 1 => \"one\", // 一\n _ => { // 其他\n 0 } }\n// 结尾",
+            "let xs = [1, // 一\n 2, 3];\nputs(xs)",
+            "if (a) { b }; // 注释\n(c)(d); // 调用\n-1",
+            "fn long(aaaaaaaaaa, bbbbbbbbbbbbb, ccccccccccccccc) { [aaaaaaaaaa, bbbbbbbbbbbbb, fn(x) { x }] }",
+        ];
+        for input in inputs {
+            let mut l = Lexer::new(input.to_string());
+            l.set_keep_comments(true);
+            let mut p = Parser::new(l);
+            p.parse_program();
+            let count = p.comments().len();
+
+            for width in [20, 80] {
+                let once = format_source(input, width).unwrap();
+                assert_eq!(
+                    format_source(&once, width).unwrap(),
+                    once,
+                    "input: {}",
+                    input
+                );
+                assert_eq!(
+                    once.matches("//").count(),
+                    count,
+                    "comments lost in:\n{}",
+                    once
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_format_expression_comments() {
+        use crate::formatter::formatter::format_source;
+
+        // 列表元素和match分支上的注释留在原来的元素旁边
+        let tests = vec![
+            (
+                "let h = {\"a\": 1, // 甲\n // 乙前\n \"b\": 2\n // 哈希尾\n};",
+                "let h = {\n    \"a\": 1, // 甲\n    // 乙前\n    \"b\": 2\n    // 哈希尾\n};\n",
+            ),
+            (
+                "match (x) {\n // 一前\n 1 => \"one\", // 一\n _ => { 0 } // 其他\n // 分支尾\n}",
+                "match (x) {\n    // 一前\n    1 => \"one\", // 一\n    _ => {\n        0\n    } // 其他\n    // 分支尾\n}\n",
+            ),
+            (
+                "let xs = [1, // 一\n 2];",
+                "let xs = [\n    1, // 一\n    2\n];\n",
+            ),
+            ("f(a, // 参数\n b)", "f(\n    a, // 参数\n    b\n);\n"),
+            (
+                "fn g(a, // 参数\n ...rest) { a }",
+                "fn g(\n    a, // 参数\n    ...rest\n) {\n    a\n}\n",
+            ),
+            ("f( // 空\n)", "f(\n    // 空\n);\n"),
+        ];
+        for (input, expected) in tests {
+            let once = format_source(input, 80).unwrap();
+            assert_eq!(once, expected, "input: {}", input);
+            assert_eq!(format_source(&once, 80).unwrap(), once, "input: {}", input);
+        }
+
+        // 其他位置的注释放不回原处，报错而不是把它挪到别的语句上
+        let tests = vec![
+            (
+                "let x = 1 + // 加数\n 2;",
+                "1:13: cannot keep comment in place: // 加数",
+            ),
+            (
+                "if (a) { b } // 分支\nelse { c }",
+                "1:14: cannot keep comment in place: // 分支",
+            ),
+            (
+                "let y = // 值\n 5;\nlet z = 6;",
+                "1:9: cannot keep comment in place: // 值",
+            ),
+        ];
+        for (input, expected) in tests {
+            assert_eq!(
+                format_source(input, 80).unwrap_err(),
+                vec![expected.to_string()],
+                "input: {}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn test_format_diff() {
+        use crate::formatter::formatter::diff;
+
+        assert_eq!(diff("a.mk", "x\ny\n", "x\ny\n"), "");
+
+        let old = "1\n2\n3\n4\n5\n6\n7\n8\n9\n10\n11\n12\n";
+        let new = "1\n2\nthree\n4\n5\n6\n7\n8\n9\n10\n11\n12\nthirteen\n";
+        let expected = "--- a.mk\n+++ a.mk (formatted)\n\
+                        @@ -1,6 +1,6 @@\n 1\n 2\n-3\n+three\n 4\n 5\n 6\n\
+                        @@ -10,3 +10,4 @@\n 10\n 11\n 12\n+thirteen\n";
+        assert_eq!(diff("a.mk", old, new), expected);
+
+        // 大文件中只改一行时只比较改动的部分
+        let old: String = (0..100_000).map(|i| format!("{}\n", i)).collect();
+        let new = old.replace("\n50000\n", "\nfifty\n");
+        let expected = "--- a.mk\n+++ a.mk (formatted)\n\
+                        @@ -49998,7 +49998,7 @@\n 49997\n 49998\n 49999\n-50000\n+fifty\n 50001\n 50002\n 50003\n";
+        assert_eq!(diff("a.mk", &old, &new), expected);
+    }
+
+    #[test]
+    fn test_cst_lossless() {
+        use crate::cst::{SyntaxElement, SyntaxKind, SyntaxNode, parse};
+
+        // 所有词法单元的范围首尾相接，原文与源码对应位置一致
+        fn check_ranges(node: &SyntaxNode, input: &str, offset: &mut usize) {
+            assert_eq!(node.text_range().start, *offset);
+            for element in node.children_with_tokens() {
+                match element {
+                    SyntaxElement::Node(child) => {
+                        assert_eq!(child.parent().unwrap().text_range(), node.text_range());
+                        check_ranges(&child, input, offset);
+                    }
+                    SyntaxElement::Token(token) => {
+                        assert_eq!(token.text_range().start, *offset);
+                        assert_eq!(&input[token.text_range()], token.text());
+                        *offset = token.text_range().end;
+                    }
+                }
+            }
+            assert_eq!(node.text_range().end, *offset);
+        }
+
+        let mut inputs: Vec<String> = vec![
+            "".to_string(),
+            "  \n\t// 只有注释\n".to_string(),
+            "let five = 5;\nlet ten = 10;\n\nlet add = fn(x, y) {\n  x + y;\n};\n".to_string(),
+            "let result = add(five, ten);\n!-/*5;\n5 < 10 > 5;\n".to_string(),
+            "if (5 < 10) {\n return true; // 真\n} else {\n return false;\n}\n".to_string(),
+            "10 == 10; 10 != 9; \"foobar\" \"foo bar\" [1, 2]; {\"foo\": \"bar\"}".to_string(),
+            "((1 + 2)) * (3); -(-a); (fn(x) { x })(1)".to_string(),
+            "fn fib(n) { if (n < 2) { return n; } fib(n - 1) + fib(n - 2) }\r\nfib(10);\r\n"
+                .to_string(),
+            "let f = fn(a, b = 1 * 2, ...rest) { f(...rest, ...[a]) };".to_string(),
+            "let [a, [b, c], ...rest] = xs; let {name, \"full name\": n, age: [y]} = p;"
+                .to_string(),
+            "match (x) { 1 => \"one\", -2 => two, [h, ...t] if h > 0 => { h } {k: v} => v, _ => ({}) }"
+                .to_string(),
+            "let s = \"你好，世界\"; // 非ASCII\nputs(s)".to_string(),
+            // 解析错误也要原样保留
+            "let = 5; let x 5; 1 + ; ok(1)".to_string(),
+            "fn(a, b = 1, c) { a }; let y = 2;".to_string(),
+            "if (x { 1 } @ .. \"unterminated".to_string(),
+            "{ 1; 2 ".to_string(),
+            "match (x) { 1 => 2 3 => 4 } let z = 1;".to_string(),
+            format!("{}1{}", "[".repeat(300), "]".repeat(300)),
+        ];
+        // 随机程序，词法单元之间换成随机的空白和注释
+        let mut generator = Generator(0x9e37_79b9_7f4a_7c15);
+        for _ in 0..300 {
+            let program = generator.program();
+            let trivia = ["\n", "\t", "  ", " // 注释\n", "\r\n", "\n\n  "];
+            let mut input = String::new();
+            for (i, part) in program.split(' ').enumerate() {
+                if i > 0 {
+                    input.push_str(trivia[generator.next(trivia.len() as u64) as usize]);
+                }
+                input.push_str(part);
+            }
+            inputs.push(input);
+        }
+
+        for input in inputs {
+            let mut p = Parser::new(Lexer::new(input.clone()));
+            let program = p.parse_program();
+
+            let parse = parse(&input);
+            let root = parse.syntax();
+            assert_eq!(root.kind(), SyntaxKind::Program);
+            assert_eq!(root.text(), input, "reconstructed source differs");
+            check_ranges(&root, &input, &mut 0);
+            assert_eq!(root.text_range().end, input.len());
+
+            assert_eq!(parse.errors(), p.errors().as_slice(), "input:\n{}", input);
+            assert_eq!(parse.program(), program, "input:\n{}", input);
+            if !p.errors().is_empty() {
+                assert!(
+                    root.children()
+                        .iter()
+                        .any(|n| n.kind() == SyntaxKind::Error),
+                    "no error node for:\n{}",
+                    input
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_cst_structure() {
+        use crate::cst::{SyntaxElement, SyntaxNode, parse};
+        use crate::token::token::TokenType;
+
+        // 输出节点种类和非trivia词法单元，便于比较树的形状
+        fn shape(node: &SyntaxNode) -> String {
+            let parts: Vec<String> = node
+                .children_with_tokens()
+                .into_iter()
+                .filter_map(|element| match element {
+                    SyntaxElement::Node(child) => Some(shape(&child)),
+                    SyntaxElement::Token(token) if !token.is_trivia() => {
+                        Some(token.text().to_string())
+                    }
+                    SyntaxElement::Token(_) => None,
+                })
+                .collect();
+            format!("{:?}({})", node.kind(), parts.join(" "))
+        }
+
+        let root = parse("// 开头\nlet x = (1 + 2) * f(y); // 结尾\n").syntax();
+        assert_eq!(
+            shape(&root),
+            "Program(LetStatement(let x = Infix(Paren(( Infix(Integer(1) + Integer(2)) )) * \
+             Call(Identifier(f) ( Identifier(y) ))) ;))"
+        );
+
+        // 语句之间的trivia在语句节点之外
+        let tokens: Vec<TokenType> = root
+            .children_with_tokens()
+            .into_iter()
+            .map(|element| match element {
+                SyntaxElement::Node(_) => TokenType::ILLEGAL,
+                SyntaxElement::Token(token) => token.kind(),
+            })
+            .collect();
+        assert_eq!(
+            tokens,
+            vec![
+                TokenType::COMMENT,
+                TokenType::WHITESPACE,
+                TokenType::ILLEGAL,
+                TokenType::WHITESPACE,
+                TokenType::COMMENT,
+                TokenType::WHITESPACE,
+            ]
+        );
+
+        let root = parse("fn(a, ...r) { match (a) { [h] if h => h, _ => {} } }").syntax();
+        assert_eq!(
+            shape(&root),
+            "Program(ExpressionStatement(Function(fn ParameterList(( Parameter(a) , \
+             Parameter(... r) )) Block({ ExpressionStatement(Match(match ( Identifier(a) ) { \
+             MatchArm(ArrayPattern([ IdentifierPattern(h) ]) if Identifier(h) => Identifier(h)) , \
+             MatchArm(WildcardPattern(_) => Block({ })) })) }))))"
+        );
+    }
+
+    #[test]
+    fn test_token_positions() {
+        let input = "let x = \"你好\";\n\n  // 注释\n\tx(1)";
+        let tests = vec![
+            (TokenType::LET, 1, 1),
+            (TokenType::IDENT, 1, 5),
+            (TokenType::ASSIGN, 1, 7),
+            (TokenType::STRING, 1, 9),
+            (TokenType::SEMICOLON, 1, 13),
+            (TokenType::IDENT, 4, 2),
+            (TokenType::LPAREN, 4, 3),
+            (TokenType::INT, 4, 4),
+            (TokenType::RPAREN, 4, 5),
+            (TokenType::EOF, 4, 6),
+        ];
+
+        let mut l = Lexer::new(input.to_string());
+        for (expected_type, line, column) in tests {
+            let tok = l.next_token();
+            assert_eq!(tok.token_type, expected_type);
+            let position = l.token_position();
+            assert_eq!(
+                (position.line, position.column),
+                (line, column),
+                "{:?}",
+                tok
+            );
+        }
+    }
+
+    #[test]
+    fn test_dump() {
+        use crate::dump::dump::{DumpMode, dump};
+
+        assert_eq!(
+            dump("let a = -1;\nf(a)", DumpMode::Tokens).unwrap(),
+            "1:1     LET         \"let\"\n\
+             1:5     IDENT       \"a\"\n\
+             1:7     ASSIGN      \"=\"\n\
+             1:9     MINUS       \"-\"\n\
+             1:10    INT         \"1\"\n\
+             1:11    SEMICOLON   \";\"\n\
+             2:1     IDENT       \"f\"\n\
+             2:2     LPAREN      \"(\"\n\
+             2:3     IDENT       \"a\"\n\
+             2:4     RPAREN      \")\"\n\
+             2:5     EOF         \"\"\n"
+        );
+
+        let input = "let f = fn(x, ...r) { if (x) { r } else { {\"k\": [x]} } }; \
+                     match (f(1)) { [h] if h => h, _ => { 0 } }";
+        assert_eq!(
+            dump(input, DumpMode::Tree).unwrap(),
+            "Program
+  Let f
+    Function
+      parameters:
+        Identifier x
+        Rest r
+      body: Block
+        ExpressionStatement
+          If
+            condition: Identifier x
+            consequence: Block
+              ExpressionStatement
+                Identifier r
+            alternative: Block
+              ExpressionStatement
+                Hash
+                  key: String \"k\"
+                  value: Array
+                    Identifier x
+  ExpressionStatement
+    Match
+      subject: Call
+        function: Identifier f
+        arguments:
+          Integer 1
+      Arm [h]
+        guard: Identifier h
+        body: ExpressionStatement
+          Identifier h
+      Arm _
+        body: Block
+          ExpressionStatement
+            Integer 0
+"
+        );
+        assert_eq!(
+            dump(input, DumpMode::Sexp).unwrap(),
+            "(let f (fn (x (... r)) (block (if x (block r) (block (hash (\"k\" (array x))))))))\n\
+             (match (call f 1) (arm [h] (if h) h) (arm _ (block 0)))\n"
+        );
+        assert_eq!(
+            dump("fn g() {} return !(1 + 2) * 3;", DumpMode::Sexp).unwrap(),
+            "(fn g () (block))\n(return (* (! (+ 1 2)) 3))\n"
+        );
+
+        assert!(dump("let = 1;", DumpMode::Tree).is_err());
+    }
+
+    #[test]
+    fn test_json_value() {
+        use crate::json::{Json, parse};
+
+        let input =
+            r#" {"a": [1, -2, 3.5e2, true, null], "s": "x\"\\\n\u00e9\ud83d\ude00", "o": {}} "#;
+        let value = parse(input).unwrap();
+        assert_eq!(
+            value,
+            Json::Object(vec![
+                (
+                    "a".to_string(),
+                    Json::Array(vec![
+                        Json::Int(1),
+                        Json::Int(-2),
+                        Json::Float(350.0),
+                        Json::Bool(true),
+                        Json::Null,
+                    ])
+                ),
+                ("s".to_string(), Json::String("x\"\\\né😀".to_string())),
+                ("o".to_string(), Json::Object(vec![])),
+            ])
+        );
+        assert_eq!(
+            value.to_string(),
+            r#"{"a":[1,-2,350.0,true,null],"s":"x\"\\\né😀","o":{}}"#
+        );
+        assert_eq!(parse(&value.pretty()).unwrap(), value);
+        assert_eq!(
+            parse(&Json::String("\u{1}".to_string()).to_string())
+                .unwrap()
+                .as_str(),
+            Some("\u{1}")
+        );
+
+        for bad in [
+            "",
+            "[1,]",
+            "{\"a\" 1}",
+            "\"abc",
+            "[1] 2",
+            "\"\\ud800\"",
+            "tru",
+            "{1: 2}",
+        ] {
+            assert!(parse(bad).is_err(), "{}", bad);
+        }
+        assert!(parse(&"[".repeat(5000)).is_err());
+    }
+
+    #[test]
+    fn test_json_round_trip() {
+        use crate::json::parse;
+        use crate::json::schema::{
+            program_from_json, program_to_json, source_to_json, tokens_from_json, tokens_to_json,
+        };
+
+        let mut inputs: Vec<String> = vec![
+            "let add = fn(a, b = 1, ...rest) { a + b }; add(1, ...[2 * 3]) - -4 / (5 - 6);"
+                .to_string(),
+            "fn fib(n) { if (n < 2) { return n; } else { fib(n - 1) + fib(n - 2) } } fib(10);"
+                .to_string(),
+            "let [a, [b, c], ...rest] = xs; let {name, \"full name\": n, age: [y]} = p;".to_string(),
+            "match (x) { -1 => \"one\", [h, ...t] if h > 0 => { h } {k: v} => v, true => 1, _ => ({}) }"
+                .to_string(),
+            "let s = \"你好\\n\\\"; {\"k\": [s, false]}[\"k\"]".to_string(),
+        ];
+        let mut generator = Generator(0x9e37_79b9_7f4a_7c15);
+        inputs.extend((0..200).map(|_| generator.program()));
+
+        for input in inputs {
+            let mut p = Parser::new(Lexer::new(input.clone()));
+            let program = p.parse_program();
+            assert!(p.errors().is_empty(), "{:?} for:\n{}", p.errors(), input);
+
+            let plain = program_to_json(&program);
+            let with_spans = source_to_json(&input).unwrap();
+            for json in [plain, with_spans] {
+                let text = json.to_string();
+                let decoded = program_from_json(&parse(&text).unwrap()).unwrap();
+                assert_eq!(decoded, program, "input:\n{}\njson:\n{}", input, text);
+            }
+
+            let tokens = tokens_from_json(&tokens_to_json(&input)).unwrap();
+            let mut l = Lexer::new(input.clone());
+            for token in tokens {
+                assert_eq!(token, l.next_token());
+            }
+        }
+
+        let json = parse(r#"{"schema": "monkey-ast", "version": 1, "program": {}}"#).unwrap();
+        assert!(program_from_json(&json).is_err());
+        let json = parse(r#"{"schema": "monkey-ast", "version": 2, "program": {"kind": "Program", "statements": [{"kind": "LetStatement"}]}}"#).unwrap();
+        assert_eq!(
+            program_from_json(&json).err().unwrap(),
+            "LetStatement: missing field `token`"
+        );
+        assert!(source_to_json("let = 1;").is_err());
+    }
+
+    #[test]
+    fn test_json_spans() {
+        use crate::json::Json;
+        use crate::json::schema::source_to_json;
+
+        // 从源码生成时每个节点都有span，叶子节点的span正好是它的词法单元
+        fn check(json: &Json, input: &str) {
+            match json {
+                Json::Array(items) => items.iter().for_each(|item| check(item, input)),
+                Json::Object(fields) => {
+                    if let Some(kind) = json.get("kind").and_then(Json::as_str) {
+                        let span = json
+                            .get("span")
+                            .unwrap_or_else(|| panic!("{} has no span", kind));
+                        let start = span.get("start").unwrap().as_i64().unwrap() as usize;
+                        let end = span.get("end").unwrap().as_i64().unwrap() as usize;
+                        let text = &input[start..end];
+                        if let Some(token) = json.get("token")
+                            && matches!(kind, "Identifier" | "IntegerLiteral" | "Boolean")
+                        {
+                            assert_eq!(text, token.get("literal").unwrap().as_str().unwrap());
+                        }
+                        if kind == "StringLiteral" {
+                            assert!(text.starts_with('"') && text.ends_with('"'), "{}", text);
+                        }
+                    }
+                    fields.iter().for_each(|(_, value)| check(value, input));
+                }
+                _ => {}
+            }
+        }
+
+        let input = "let f = fn(a, b = (1), ...r) { f(...r, \"é\") }; // 注释\n\
+                     fn g(x) { match (x) { [h, ...t] if (h) => { h } {k, v: [w]} => -1, _ => x } }\n\
+                     let [p, ...q] = [1, 2]; if (true) { (g)((2)) } else { {1: 2} }";
+        let json = source_to_json(input).unwrap();
+        check(&json, input);
+
+        let statements = json.get("program").unwrap().get("statements").unwrap();
+        let name = statements.as_array().unwrap()[0].get("name").unwrap();
+        assert_eq!(
+            name.get("span").unwrap().to_string(),
+            r#"{"start":4,"end":5}"#
+        );
+    }
+
+    #[test]
+    fn test_program_to_dot() {
+        use crate::dot::dot::program_to_dot;
+
+        let mut p = Parser::new(Lexer::new(
+            "let s = \"a\\b\"; f(1, -x) + {true: [y]}".to_string(),
+        ));
+        let program = p.parse_program();
+        assert!(p.errors().is_empty());
+        assert_eq!(
+            program_to_dot(&program),
+            r#"digraph ast {
+  ordering=out;
+  node [shape=box, fontname="monospace"];
+  n0 [label="Program"];
+  n1 [label="LetStatement\ns"];
+  n2 [label="StringLiteral\n\"a\\b\""];
+  n1 -> n2;
+  n0 -> n1;
+  n3 [label="ExpressionStatement"];
+  n4 [label="InfixExpression\n+"];
+  n5 [label="CallExpression"];
+  n6 [label="Identifier\nf"];
+  n5 -> n6 [label="function"];
+  n7 [label="IntegerLiteral\n1"];
+  n5 -> n7 [label="argument"];
+  n8 [label="PrefixExpression\n-"];
+  n9 [label="Identifier\nx"];
+  n8 -> n9;
+  n5 -> n8 [label="argument"];
+  n4 -> n5 [label="left"];
+  n10 [label="HashLiteral"];
+  n11 [label="Boolean\ntrue"];
+  n10 -> n11 [label="key"];
+  n12 [label="ArrayLiteral"];
+  n13 [label="Identifier\ny"];
+  n12 -> n13;
+  n10 -> n12 [label="value"];
+  n4 -> n10 [label="right"];
+  n3 -> n4;
+  n0 -> n3;
+}
+"#
+        );
+
+        // 字符串的值只转义一次；源码里写不出双引号，直接改AST
+        let mut p = Parser::new(Lexer::new("\"\"".to_string()));
+        let mut program = p.parse_program();
+        let Statement::Expression(stmt) = &mut program.statements[0] else {
+            panic!("not an expression statement");
+        };
+        let Expression::String(string) = stmt.expression.as_mut() else {
+            panic!("not a string literal");
+        };
+        string.value = "say \"hi\" \\ bye".to_string();
+        assert!(
+            program_to_dot(&program)
+                .contains(r#"n2 [label="StringLiteral\n\"say \"hi\" \\ bye\""];"#),
+            "{}",
+            program_to_dot(&program)
+        );
+    }
+
+    #[test]
+    fn test_repl_incomplete_input() {
+        use crate::repl::repl::is_incomplete;
+
+        for input in [
+            "fn add(a, b) {",
+            "fn add(a, b) {\n  a + b\n",
+            "add(1,",
+            "let xs = [1, [2",
+            "let x =",
+            "1 +",
+            "a ==",
+            "match (x) { 1 =>",
+            "let s = \"abc",
+            "{\"k\":",
+            "if (x) { 1 } else",
+            "let",
+        ] {
+            assert!(is_incomplete(input), "{:?}", input);
+        }
+        for input in [
+            "",
+            "1 + 2",
+            "fn add(a, b) {\n  a + b\n}\n",
+            "let s = \"{(\";",
+            "add(1, 2)) // 多出的括号交给解析器报错",
+            "x // 注释里的 {",
+            "-1",
+        ] {
+            assert!(!is_incomplete(input), "{:?}", input);
+        }
+    }
+
+    #[test]
+    fn test_repl_commands() {
+        use crate::repl::repl::{Command, Session};
+
+        assert!(Command::parse("let x = 1;").is_none());
+        assert_eq!(Command::parse(" :env "), Some(Ok(Command::Env)));
+        assert_eq!(
+            Command::parse(":type  1 + 2"),
+            Some(Ok(Command::Type("1 + 2".to_string())))
+        );
+        assert_eq!(
+            Command::parse(":load"),
+            Some(Err("usage: :load <file>".to_string()))
+        );
+        assert_eq!(
+            Command::parse(":reset now"),
+            Some(Err(":reset takes no arguments".to_string()))
+        );
+        assert!(matches!(Command::parse(":nope"), Some(Err(_))));
+
+        let mut session = Session::new();
+        let mut run = |input: &str| session.execute(input).unwrap().unwrap();
+        assert_eq!(run("let x = 1;"), "");
+        assert_eq!(run("fn add(a, b) { a + b }"), "");
+        assert_eq!(run(":type add(x, 2)"), "Integer");
+        assert_eq!(run(":type let y = 2; y"), "Integer");
+        assert_eq!(run(":env"), "add  Function  fn add(a, b)\nx    Integer   1");
+        assert!(run(":time add(x, 2)").starts_with("3 : Integer\ntime: "));
+        assert!(session.execute(":quit").is_none());
+        assert_eq!(session.env().bindings().len(), 3);
+        assert_eq!(
+            session.inputs(),
+            ["let x = 1;", "fn add(a, b) { a + b }", "add(x, 2)"]
+        );
+
+        session.execute(":reset").unwrap().unwrap();
+        assert!(session.env().bindings().is_empty());
+        assert!(session.inputs().is_empty());
+
+        // :save 写出的会话再 :load 时，_ 仍然是上一条输入的值
+        let path = std::env::temp_dir().join(format!("monkey-save-{}.mk", std::process::id()));
+        let path = path.to_str().unwrap();
+        let mut run = |input: &str| session.execute(input).unwrap().unwrap();
+        run("1 + 2");
+        run("_ * 10");
+        run("let y = _;");
+        run("y + 1");
+        assert_eq!(
+            run(&format!(":save {}", path)),
+            format!("saved 4 inputs to {}", path)
+        );
+
+        let mut session = Session::new();
+        let mut run = |input: &str| session.execute(input).unwrap().unwrap();
+        assert_eq!(run(&format!(":load {}", path)), "");
+        assert_eq!(run("[y, _]"), "[30, 31] : Array");
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_repl_helper() {
+        use crate::repl::helper::{ReplHelper, highlight};
+
+        let mut helper = ReplHelper::new();
+        helper.set_names(vec!["fib".to_string(), "first".to_string()]);
+        assert_eq!(
+            helper.candidates("let y = fi", 10),
+            (8, vec!["fib".to_string(), "first".to_string()])
+        );
+        assert_eq!(helper.candidates("fa", 2), (0, vec!["false".to_string()]));
+        assert_eq!(helper.candidates("1 + ", 4), (4, vec![]));
+        // 光标前是多字节字符
+        assert_eq!(helper.candidates("\"é", 3), (3, vec![]));
+        assert_eq!(
+            helper.candidates("\"é\" + fi", 9),
+            (7, vec!["fib".to_string(), "first".to_string()])
+        );
+        assert_eq!(helper.candidates("éfa", 4), (2, vec!["false".to_string()]));
+        assert_eq!(
+            helper.candidates(":t", 2),
+            (
+                0,
+                vec![
+                    ":time ".to_string(),
+                    ":tokens ".to_string(),
+                    ":type ".to_string()
+                ]
+            )
+        );
+
+        assert_eq!(
+            highlight("let s = \"ä\"; // x"),
+            "\x1b[1;35mlet\x1b[0m s = \x1b[32m\"ä\"\x1b[0m; \x1b[2m// x\x1b[0m"
+        );
+        // 高亮不改变显示的内容
+        for line in [":type fn(x) { x }", "let s = \"abc", "x @ 1", "  "] {
+            let mut plain = String::new();
+            let mut escape = false;
+            for c in highlight(line).chars() {
+                match c {
+                    '\x1b' => escape = true,
+                    'm' if escape => escape = false,
+                    _ if escape => {}
+                    c => plain.push(c),
+                }
+            }
+            assert_eq!(plain, line);
+        }
+    }
+
+    #[test]
+    fn test_repl_display() {
+        use crate::repl::display::{DisplayOptions, display};
+        use crate::repl::repl::Session;
+
+        let mut session = Session::new();
+        let mut run = |input: &str| session.execute(input).unwrap().unwrap();
+        assert_eq!(run("1 + 2"), "3 : Integer");
+        assert_eq!(run("_ * 2"), "6 : Integer");
+        assert_eq!(run("let x = _;"), "");
+        assert_eq!(run("if (false) { 1 }"), "null : Null");
+        assert_eq!(run("\"a\""), "\"a\" : String");
+        assert_eq!(run("[1, [true, \"b\"]]"), "[1, [true, \"b\"]] : Array");
+        assert_eq!(run("fn(a) { a }"), "fn(a) : Function");
+        assert_eq!(run("x + true"), "ERROR: type mismatch: INTEGER + BOOLEAN");
+        assert_eq!(run("x"), "6 : Integer");
+
+        let options = DisplayOptions {
+            width: 30,
+            max_items: 3,
+            max_depth: 2,
+            ..DisplayOptions::default()
+        };
+        run("let xs = [1, 2, 3, 4, 5]; let h = {\"key\": [[[1]], \"a long string value\"]};");
+        let xs = session.env().get("xs").unwrap();
+        assert_eq!(display(&xs, &options), "[1, 2, 3, ... 2 more] : Array");
+        let h = session.env().get("h").unwrap();
+        assert_eq!(
+            display(&h, &options),
+            "{\n  \"key\": [\n    [...],\n    \"a long string value\"\n  ]\n} : Hash"
+        );
+    }
 }
@@ -1,3 +1,5 @@
+use core::fmt;
+
 #[derive(Debug, Clone, PartialEq, Eq, Copy, Hash)]
 pub enum TokenType {
     LET,
@@ -21,6 +23,8 @@ pub enum TokenType {
     RBRACE,    // }
     LBRACKET,  // [
     RBRACKET,  // ]
+    ELLIPSIS,  // ...
+    FATARROW,  // =>
     LT,        // <
     GT,        // >
     EQ,        // ==
@@ -28,9 +32,12 @@ pub enum TokenType {
     IF,
     ELSE,
     RETURN,
+    MATCH,
     TRUE,
     FALSE,
     BANG,
+    COMMENT,    // 只在保留注释时出现
+    WHITESPACE, // 只出现在具体语法树中
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -38,3 +45,16 @@ pub struct Token {
     pub token_type: TokenType,
     pub literal: String,
 }
+
+// 词法单元在源码中的位置，行号和列号都从1开始，列按字符计
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Position {
+    pub line: usize,
+    pub column: usize,
+}
+
+impl fmt::Display for Position {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}